@@ -1,120 +1,404 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use reqwest::Client;
 
 use crate::config::ProviderConfig;
 use crate::error::{Result, SageError};
-use super::{AiResponse, TokenUsage};
+use crate::http::request_error;
+use crate::prompts::Prompt;
+use super::tools::{tool_specs, ToolRunner};
+use super::{AiResponse, TokenUsage, DEFAULT_TEMPERATURE};
+
+pub const DEFAULT_MODEL: &str = "claude-3-sonnet-20240229";
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ClaudeRequest {
     model: String,
+    system: Vec<ClaudeSystemBlock>,
     messages: Vec<ClaudeMessage>,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
     max_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ClaudeTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ClaudeToolChoice>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct ClaudeMessage {
-    role: String,
-    content: Vec<ClaudeContent>,
+struct ClaudeTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeToolChoice {
+    #[serde(rename = "type")]
+    choice_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct ClaudeContent {
+struct ClaudeSystemBlock {
     #[serde(rename = "type")]
     content_type: String,
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<ClaudeCacheControl>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct ClaudeResponse {
-    content: Vec<ClaudeResponseContent>,
-    usage: ClaudeUsage,
+struct ClaudeCacheControl {
+    #[serde(rename = "type")]
+    cache_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct ClaudeResponseContent {
-    #[serde(rename = "type")]
-    content_type: String,
-    text: String,
+struct ClaudeMessage {
+    role: String,
+    content: Vec<ClaudeContent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeContent {
+    Text { text: String },
+    ToolUse { id: String, name: String, input: Value },
+    ToolResult { tool_use_id: String, content: String },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeResponse {
+    content: Vec<ClaudeContent>,
+    usage: ClaudeUsage,
+    stop_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ClaudeUsage {
     input_tokens: usize,
     output_tokens: usize,
+    cache_creation_input_tokens: Option<usize>,
+    cache_read_input_tokens: Option<usize>,
 }
 
-pub async fn call_claude_api(provider_config: &ProviderConfig, prompt: &str, max_tokens: Option<usize>) -> Result<AiResponse> {
-    let client = Client::new();
+impl From<ClaudeUsage> for TokenUsage {
+    fn from(usage: ClaudeUsage) -> Self {
+        // Anthropic reports cached prompt tokens separately from input_tokens
+        let cache_write_tokens = usage.cache_creation_input_tokens.unwrap_or(0);
+        let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0);
+        let input_tokens = usage.input_tokens + cache_write_tokens + cache_read_tokens;
+
+        TokenUsage {
+            input_tokens,
+            output_tokens: usage.output_tokens,
+            total_tokens: input_tokens + usage.output_tokens,
+            cache_read_tokens,
+            cache_write_tokens,
+        }
+    }
+}
 
-    let model = provider_config.model.clone().unwrap_or_else(|| "claude-3-sonnet-20240229".to_string());
+/// Repository tools in Anthropic's tool format
+fn build_tools() -> Vec<ClaudeTool> {
+    tool_specs()
+        .into_iter()
+        .map(|spec| ClaudeTool {
+            name: spec.name.to_string(),
+            description: spec.description.to_string(),
+            input_schema: spec.parameters,
+        })
+        .collect()
+}
 
-    let request = ClaudeRequest {
-        model,
-        messages: vec![
-            ClaudeMessage {
-                role: "user".to_string(),
-                content: vec![
-                    ClaudeContent {
-                        content_type: "text".to_string(),
-                        text: prompt.to_string(),
-                    },
-                ],
+/// System block: the shared role prompt plus the task instructions, marked
+/// cacheable so repeated commits only pay full price for the diff.
+///
+/// Anthropic only caches a prefix of at least 1024 tokens (2048 on Haiku
+/// models); anything shorter is billed in full without an error. Commit
+/// prompts reach that size once their extra examples are added, so the role
+/// prompt, instructions and examples go out as one block with the cache
+/// breakpoint after it.
+fn build_system_blocks(prompt: &Prompt) -> Vec<ClaudeSystemBlock> {
+    vec![
+        ClaudeSystemBlock {
+            content_type: "text".to_string(),
+            text: if prompt.extra_examples.is_empty() {
+                format!("{}\n\n{}", prompt.system(), prompt.instructions)
+            } else {
+                format!("{}\n\n{}\n\n{}", prompt.system(), prompt.instructions, prompt.extra_examples)
             },
-        ],
-        temperature: 0.7,
-        max_tokens,
+            cache_control: Some(ClaudeCacheControl {
+                cache_type: "ephemeral".to_string(),
+            }),
+        },
+    ]
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeModelList {
+    data: Vec<ClaudeModel>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeModel {
+    id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeErrorBody {
+    error: ClaudeError,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaudeError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+/// Map an Anthropic error response onto the most specific `SageError`
+fn parse_error(status: u16, body: &str, model: &str) -> SageError {
+    let provider = "Claude".to_string();
+
+    if status == 401 || status == 403 {
+        return SageError::ApiAuthError { provider };
+    }
+
+    let error = match serde_json::from_str::<ClaudeErrorBody>(body) {
+        Ok(parsed) => parsed.error,
+        Err(_) if status == 529 || status == 503 => return SageError::ApiOverloaded { provider },
+        Err(_) => return SageError::ApiResponseError { provider, details: body.to_string() },
     };
 
-    let response = client.post("https://api.anthropic.com/v1/messages")
-        .header("Content-Type", "application/json")
-        .header("x-api-key", &provider_config.api_key)
-        .header("anthropic-version", "2023-06-01")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| SageError::ApiNetworkError {
-            provider: "Claude".to_string(),
-            details: e.to_string(),
-        })?;
+    let message = error.message.to_lowercase();
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
+    match error.error_type.as_str() {
+        "overloaded_error" => SageError::ApiOverloaded { provider },
+        "not_found_error" if message.contains("model") => {
+            SageError::ApiModelNotFound { provider, model: model.to_string() }
+        },
+        "billing_error" => SageError::ApiQuotaExceeded { provider, details: error.message },
+        "invalid_request_error" if message.contains("credit balance") => {
+            SageError::ApiQuotaExceeded { provider, details: error.message }
+        },
+        "invalid_request_error" if message.contains("prompt is too long") || message.contains("context window") => {
+            SageError::ApiContextLengthExceeded { provider, details: error.message }
+        },
+        _ => SageError::ApiResponseError { provider, details: error.message },
+    }
+}
+
+fn base_url(provider_config: &ProviderConfig) -> &str {
+    provider_config.base_url.as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/')
+}
+
+pub async fn call_claude_api(
+    client: &Client,
+    provider_config: &ProviderConfig,
+    prompt: &Prompt,
+    max_tokens: Option<usize>,
+    mut tools: Option<&mut ToolRunner>,
+) -> Result<AiResponse> {
+    let model = provider_config.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
 
-        if status == 401 || status == 403 {
-            return Err(SageError::ApiAuthError {
+    let mut messages = vec![
+        ClaudeMessage {
+            role: "user".to_string(),
+            content: vec![ClaudeContent::Text { text: prompt.input.clone() }],
+        },
+    ];
+    let mut usage = TokenUsage::default();
+
+    loop {
+        let offer_tools = tools.as_ref().is_some_and(|runner| runner.can_continue());
+
+        let request = ClaudeRequest {
+            model: model.clone(),
+            system: build_system_blocks(prompt),
+            messages,
+            // The Messages API has no seed parameter, so only temperature/top_p/stop apply
+            temperature: provider_config.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            top_p: provider_config.top_p,
+            stop_sequences: provider_config.stop.clone(),
+            max_tokens,
+            tools: tools.is_some().then(build_tools),
+            // Once the budget is spent the tools stay declared but the model must answer
+            tool_choice: (tools.is_some() && !offer_tools).then(|| ClaudeToolChoice {
+                choice_type: "none".to_string(),
+            }),
+        };
+
+        let response = client.post(format!("{}/v1/messages", base_url(provider_config)))
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &provider_config.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| request_error("Claude", e))?;
+        messages = request.messages;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(parse_error(status, &error_text, &model));
+        }
+
+        let response_data: ClaudeResponse = response.json().await
+            .map_err(|e| SageError::ApiResponseError {
                 provider: "Claude".to_string(),
+                details: format!("Failed to parse response: {}", e),
+            })?;
+
+        usage += response_data.usage.into();
+
+        if response_data.stop_reason.as_deref() == Some("refusal") {
+            return Err(SageError::ApiContentFiltered {
+                provider: "Claude".to_string(),
+                details: "the model declined to respond".to_string(),
             });
         }
 
-        return Err(SageError::ApiResponseError {
-            provider: "Claude".to_string(),
-            details: error_text,
+        let wants_tools = response_data.stop_reason.as_deref() == Some("tool_use");
+        if let (true, Some(runner)) = (offer_tools && wants_tools, tools.as_deref_mut()) {
+            runner.start_round();
+
+            let content: Vec<ClaudeContent> = response_data.content.into_iter()
+                .filter(|block| !matches!(block, ClaudeContent::Unsupported))
+                .collect();
+
+            let results = content.iter()
+                .filter_map(|block| match block {
+                    ClaudeContent::ToolUse { id, name, input } => Some(ClaudeContent::ToolResult {
+                        tool_use_id: id.clone(),
+                        content: runner.run(name, input),
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            messages.push(ClaudeMessage { role: "assistant".to_string(), content });
+            messages.push(ClaudeMessage { role: "user".to_string(), content: results });
+            continue;
+        }
+
+        let text = response_data.content.iter().find_map(|block| match block {
+            ClaudeContent::Text { text } => Some(text.trim().to_string()),
+            _ => None,
         });
+
+        return match text {
+            Some(message) => Ok(AiResponse { message, usage }),
+            None => Err(SageError::ApiNoResponse {
+                provider: "Claude".to_string(),
+            }),
+        };
     }
+}
 
-    let response_data: ClaudeResponse = response.json().await
+/// Model IDs from a `/models` response
+fn parse_model_list(body: &str) -> Result<Vec<String>> {
+    let model_list: ClaudeModelList = serde_json::from_str(body)
         .map_err(|e| SageError::ApiResponseError {
             provider: "Claude".to_string(),
-            details: format!("Failed to parse response: {}", e),
+            details: format!("Failed to parse model list: {}", e),
         })?;
 
-    if let Some(content) = response_data.content.first() {
-        if content.content_type == "text" {
-            return Ok(AiResponse {
-                message: content.text.trim().to_string(),
-                usage: TokenUsage {
-                    input_tokens: response_data.usage.input_tokens,
-                    output_tokens: response_data.usage.output_tokens,
-                    total_tokens: response_data.usage.input_tokens + response_data.usage.output_tokens,
-                },
-            });
+    Ok(model_list.data.into_iter().map(|m| m.id).collect())
+}
+
+pub async fn list_claude_models(client: &Client, provider_config: &ProviderConfig) -> Result<Vec<String>> {
+    let response = client.get(format!("{}/v1/models?limit=1000", base_url(provider_config)))
+        .header("x-api-key", &provider_config.api_key)
+        .header("anthropic-version", API_VERSION)
+        .send()
+        .await
+        .map_err(|e| request_error("Claude", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        let error_text = response.text().await.unwrap_or_default();
+        let model = provider_config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+        return Err(parse_error(status, &error_text, model));
+    }
+
+    let body = response.text().await.map_err(|e| request_error("Claude", e))?;
+    parse_model_list(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_body(error_type: &str, message: &str) -> String {
+        format!(r#"{{"type": "error", "error": {{"type": "{}", "message": "{}"}}}}"#, error_type, message)
+    }
+
+    #[test]
+    fn test_parse_context_length_error() {
+        let body = error_body("invalid_request_error", "prompt is too long: 250000 tokens > 200000 maximum");
+        assert!(matches!(parse_error(400, &body, "claude-3-opus"), SageError::ApiContextLengthExceeded { .. }));
+    }
+
+    #[test]
+    fn test_parse_model_not_found_error() {
+        let body = error_body("not_found_error", "model: claude-9");
+        match parse_error(404, &body, "claude-9") {
+            SageError::ApiModelNotFound { model, .. } => assert_eq!(model, "claude-9"),
+            other => panic!("unexpected error: {:?}", other),
         }
     }
 
-    Err(SageError::ApiNoResponse {
-        provider: "Claude".to_string(),
-    })
+    #[test]
+    fn test_system_blocks_cache_instructions() {
+        let prompt = crate::prompts::build_commit_prompt(None, "", "src/main.rs", "+line");
+        let blocks = build_system_blocks(&prompt);
+
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks[0].text.starts_with(crate::prompts::SYSTEM_PROMPT));
+        assert!(blocks[0].text.contains(&prompt.instructions));
+        assert!(blocks[0].text.ends_with(crate::prompts::EXTRA_COMMIT_EXAMPLES));
+        assert_eq!(blocks[0].cache_control.as_ref().unwrap().cache_type, "ephemeral");
+    }
+
+    #[test]
+    fn test_usage_includes_cache_tokens() {
+        let usage: ClaudeUsage = serde_json::from_str(
+            r#"{"input_tokens": 50, "output_tokens": 20, "cache_creation_input_tokens": 0, "cache_read_input_tokens": 400}"#
+        ).unwrap();
+        let usage = TokenUsage::from(usage);
+
+        assert_eq!(usage.input_tokens, 450);
+        assert_eq!(usage.total_tokens, 470);
+        assert_eq!(usage.cache_read_tokens, 400);
+        assert_eq!(usage.cache_write_tokens, 0);
+    }
+
+    #[test]
+    fn test_parse_quota_and_overloaded_errors() {
+        let body = error_body("invalid_request_error", "Your credit balance is too low to access the Anthropic API");
+        assert!(matches!(parse_error(400, &body, "claude-3-opus"), SageError::ApiQuotaExceeded { .. }));
+
+        let body = error_body("overloaded_error", "Overloaded");
+        assert!(matches!(parse_error(529, &body, "claude-3-opus"), SageError::ApiOverloaded { .. }));
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = r#"{"data": [
+            {"type": "model", "id": "claude-3-5-sonnet-20241022", "display_name": "Claude 3.5 Sonnet", "created_at": "2024-10-22T00:00:00Z"},
+            {"type": "model", "id": "claude-3-haiku-20240307", "display_name": "Claude 3 Haiku", "created_at": "2024-03-07T00:00:00Z"}
+        ], "has_more": false, "first_id": "claude-3-5-sonnet-20241022", "last_id": "claude-3-haiku-20240307"}"#;
+        assert_eq!(parse_model_list(body).unwrap(), vec!["claude-3-5-sonnet-20241022", "claude-3-haiku-20240307"]);
+    }
 }
@@ -1,17 +1,59 @@
 pub mod openai;
 pub mod claude;
+pub mod ollama;
+pub mod replay;
+pub mod tools;
 
+use std::fmt;
+use std::ops::AddAssign;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
+use crate::cli::Task;
 use crate::config::Config;
+use crate::http;
+use crate::prompts::Prompt;
+use tools::ToolRunner;
 use crate::error::{Result, SageError};
+use crate::git::{smart_truncate_diff, truncate_diff, Diff, RETRY_DIFF_SIZE};
+
+/// Sampling temperature used when a provider does not configure one
+pub const DEFAULT_TEMPERATURE: f32 = 0.7;
+
+/// Seed used by `--deterministic` when no explicit seed is given
+pub const DETERMINISTIC_SEED: u64 = 42;
 
 /// Token usage information from AI API calls
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct TokenUsage {
+    /// All prompt tokens, including any served from or written to the cache
     pub input_tokens: usize,
     pub output_tokens: usize,
     pub total_tokens: usize,
+    #[serde(default)]
+    pub cache_read_tokens: usize,
+    #[serde(default)]
+    pub cache_write_tokens: usize,
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+    }
+}
+
+impl fmt::Display for TokenUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in / {} out / {} total", self.input_tokens, self.output_tokens, self.total_tokens)?;
+        if self.cache_read_tokens > 0 || self.cache_write_tokens > 0 {
+            write!(f, " (cache: {} read / {} written)", self.cache_read_tokens, self.cache_write_tokens)?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -21,16 +63,113 @@ pub struct AiResponse {
     pub usage: TokenUsage,
 }
 
-pub async fn call_ai(config: &Config, prompt: &str) -> Result<AiResponse> {
-    let (provider_name, provider_config) = config.get_active_provider_config()?;
+pub async fn call_ai(config: &Config, task: Task, prompt: &Prompt) -> Result<AiResponse> {
+    let (provider_name, provider_config) = config.get_task_provider_config(task)?;
 
-    match provider_name.as_str() {
-        "openai" => openai::call_openai_api(provider_config, prompt, config.max_tokens).await,
-        "claude" => claude::call_claude_api(provider_config, prompt, config.max_tokens).await,
+    let mut tools = config.use_tools.then(ToolRunner::new);
+    let prompt = if config.use_tools {
+        prompt.with_tool_instructions()
+    } else {
+        prompt.clone()
+    };
+
+    let response = match provider_name.as_str() {
+        "openai" => openai::call_openai_api(http::client(&config.network)?, "OpenAI", &provider_config, &prompt, config.max_tokens, tools.as_mut()).await,
+        "claude" => claude::call_claude_api(http::client(&config.network)?, &provider_config, &prompt, config.max_tokens, tools.as_mut()).await,
+        "ollama" => openai::call_openai_api(http::client(&config.network)?, "Ollama", &ollama::with_defaults(&provider_config), &prompt, config.max_tokens, tools.as_mut()).await,
+        "replay" => replay::replay_response(&provider_config, &prompt.to_string()),
         _ => Err(SageError::ApiUnsupportedProvider {
             provider: provider_name.clone()
         })
+    }?;
+
+    if let Some(dir) = &config.record_dir {
+        replay::record_response(dir, &provider_name, provider_config.model.as_deref(), &prompt.to_string(), &response)?;
     }
+
+    Ok(response)
+}
+
+/// Call the AI with a prompt built around `diff`, retrying once with a much
+/// shorter diff if the provider rejects the request for its context length
+pub async fn call_ai_with_diff(
+    config: &Config,
+    task: Task,
+    diff: &Diff,
+    build_prompt: impl Fn(&str) -> Prompt,
+) -> Result<AiResponse> {
+    let prompt = build_prompt(&smart_truncate_diff(diff));
+
+    match call_ai(config, task, &prompt).await {
+        Err(SageError::ApiContextLengthExceeded { .. }) => {
+            let prompt = build_prompt(&truncate_diff(diff, RETRY_DIFF_SIZE));
+            call_ai(config, task, &prompt).await
+        },
+        result => result,
+    }
+}
+
+/// Call the AI like `call_ai_with_diff`, then check the message with
+/// `validate`. If it reports problems, the model is asked once more with the
+/// problems listed. Returns the better of the two answers and the problems
+/// it still has.
+pub async fn call_ai_validated(
+    config: &Config,
+    task: Task,
+    diff: &Diff,
+    build_prompt: impl Fn(&str) -> Prompt,
+    validate: impl Fn(&str) -> Vec<String>,
+) -> Result<(AiResponse, Vec<String>)> {
+    let mut first = call_ai_with_diff(config, task, diff, &build_prompt).await?;
+    let problems = validate(&first.message);
+    if problems.is_empty() {
+        return Ok((first, problems));
+    }
+
+    let retry = call_ai_with_diff(config, task, diff, |truncated_diff| {
+        build_prompt(truncated_diff).with_feedback(&first.message, &problems)
+    }).await;
+
+    // A failed retry should not lose the answer we already have
+    let Ok(mut retry) = retry else {
+        return Ok((first, problems));
+    };
+
+    let retry_problems = validate(&retry.message);
+    if retry_problems.len() <= problems.len() {
+        retry.usage += first.usage;
+        Ok((retry, retry_problems))
+    } else {
+        first.usage += retry.usage;
+        Ok((first, problems))
+    }
+}
+
+/// Model used by a provider when none is configured
+pub fn default_model(provider_name: &str) -> Option<&'static str> {
+    match provider_name {
+        "openai" => Some(openai::DEFAULT_MODEL),
+        "claude" => Some(claude::DEFAULT_MODEL),
+        "ollama" => Some(ollama::DEFAULT_MODEL),
+        _ => None,
+    }
+}
+
+/// Query a provider's model listing endpoint, returning model IDs sorted by name
+pub async fn list_models(config: &Config, provider_name: &str) -> Result<Vec<String>> {
+    let provider_config = config.get_provider_config(provider_name)?;
+
+    let mut models = match provider_name {
+        "openai" => openai::list_openai_models(http::client(&config.network)?, provider_config).await?,
+        "claude" => claude::list_claude_models(http::client(&config.network)?, provider_config).await?,
+        "ollama" => ollama::list_ollama_models(http::client(&config.network)?, provider_config).await?,
+        _ => return Err(SageError::ApiUnsupportedProvider {
+            provider: provider_name.to_string()
+        }),
+    };
+
+    models.sort();
+    Ok(models)
 }
 
 pub fn sanitize_commit_message(message: &str) -> String {
@@ -59,8 +198,13 @@ pub fn sanitize_commit_message(message: &str) -> String {
 
     result = result.replace("**", "").replace("__", "").replace("*", "").replace("_", "");
 
-    let whitespace_re = Regex::new(r"\s+").unwrap();
+    // Collapse spacing within lines but keep line breaks, so message bodies survive
+    let whitespace_re = Regex::new(r"[^\S\n]+").unwrap();
     result = whitespace_re.replace_all(&result, " ").to_string();
+    result = result.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+
+    let blank_lines_re = Regex::new(r"\n{3,}").unwrap();
+    result = blank_lines_re.replace_all(&result, "\n\n").to_string();
 
     result.trim().to_string()
 }
@@ -97,6 +241,13 @@ mod tests {
         assert_eq!(sanitize_commit_message(input), expected);
     }
 
+    #[test]
+    fn test_sanitize_keeps_body_lines() {
+        let input = "feat: add   cache\r\n\n\n\nCache parsed  diffs  \nbetween runs.";
+        let expected = "feat: add cache\n\nCache parsed diffs\nbetween runs.";
+        assert_eq!(sanitize_commit_message(input), expected);
+    }
+
     #[test]
     fn test_sanitize_plain_text() {
         let input = "feat: add new feature";
@@ -0,0 +1,97 @@
+//! Ollama, a local model server
+//!
+//! Completions go through Ollama's OpenAI-compatible API, so they reuse the
+//! OpenAI client with Ollama's defaults filled in. Models are listed from the
+//! native `/api/tags` endpoint, which every Ollama version serves.
+
+use serde::Deserialize;
+use reqwest::Client;
+
+use crate::config::ProviderConfig;
+use crate::error::{Result, SageError};
+use crate::http::request_error;
+
+pub const DEFAULT_MODEL: &str = "llama3.2";
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+
+#[derive(Deserialize, Debug)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaModel {
+    name: String,
+}
+
+/// Provider settings with Ollama's base URL and model filled in, for the
+/// OpenAI client
+pub fn with_defaults(provider_config: &ProviderConfig) -> ProviderConfig {
+    let mut config = provider_config.clone();
+    config.base_url.get_or_insert_with(|| DEFAULT_BASE_URL.to_string());
+    config.model.get_or_insert_with(|| DEFAULT_MODEL.to_string());
+    config
+}
+
+/// Server root: the configured base URL without the OpenAI-compatible `/v1`
+fn server_url(provider_config: &ProviderConfig) -> &str {
+    let base_url = provider_config.base_url.as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/');
+    base_url.strip_suffix("/v1").unwrap_or(base_url)
+}
+
+/// Model names from an `/api/tags` response; `:latest` is dropped, as
+/// Ollama uses it when no tag is given
+fn parse_model_list(body: &str) -> Result<Vec<String>> {
+    let tags: OllamaTags = serde_json::from_str(body)
+        .map_err(|e| SageError::ApiResponseError {
+            provider: "Ollama".to_string(),
+            details: format!("Failed to parse model list: {}", e),
+        })?;
+
+    Ok(tags.models.into_iter()
+        .map(|m| m.name.strip_suffix(":latest").map(str::to_string).unwrap_or(m.name))
+        .collect())
+}
+
+pub async fn list_ollama_models(client: &Client, provider_config: &ProviderConfig) -> Result<Vec<String>> {
+    let response = client.get(format!("{}/api/tags", server_url(provider_config)))
+        .send()
+        .await
+        .map_err(|e| request_error("Ollama", e))?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(SageError::ApiResponseError {
+            provider: "Ollama".to_string(),
+            details: format!("{} {}", status.as_u16(), body.trim()),
+        });
+    }
+
+    parse_model_list(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = r#"{"models": [
+            {"name": "llama3.2:latest", "model": "llama3.2:latest", "size": 2019393189},
+            {"name": "qwen2.5-coder:7b", "model": "qwen2.5-coder:7b", "size": 4683087332}
+        ]}"#;
+        assert_eq!(parse_model_list(body).unwrap(), vec!["llama3.2", "qwen2.5-coder:7b"]);
+        assert!(parse_model_list(r#"{"data": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_server_url() {
+        let mut config = ProviderConfig::default();
+        assert_eq!(server_url(&config), "http://localhost:11434");
+        config.base_url = Some("http://gpu-box:11434/v1/".to_string());
+        assert_eq!(server_url(&config), "http://gpu-box:11434");
+    }
+}
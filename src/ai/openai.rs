@@ -1,23 +1,82 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use reqwest::Client;
 
 use crate::config::ProviderConfig;
 use crate::error::{Result, SageError};
-use crate::prompts;
-use super::{sanitize_commit_message, AiResponse, TokenUsage};
+use crate::http::request_error;
+use crate::prompts::Prompt;
+use super::tools::{tool_specs, ToolRunner};
+use super::{sanitize_commit_message, AiResponse, TokenUsage, DEFAULT_TEMPERATURE};
+
+pub const DEFAULT_MODEL: &str = "gpt-4-turbo";
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
 #[derive(Serialize, Deserialize, Debug)]
 struct OpenAIRequest {
     model: String,
     messages: Vec<OpenAIMessage>,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
     max_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAITool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct OpenAIMessage {
     role: String,
-    content: String,
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OpenAIToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl OpenAIMessage {
+    fn text(role: &str, content: &str) -> Self {
+        OpenAIMessage {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAITool {
+    #[serde(rename = "type")]
+    tool_type: String,
+    function: OpenAIFunction,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIFunction {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    call_type: String,
+    function: OpenAIFunctionCall,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct OpenAIFunctionCall {
+    name: String,
+    arguments: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -29,6 +88,7 @@ struct OpenAIResponse {
 #[derive(Serialize, Deserialize, Debug)]
 struct OpenAIChoice {
     message: OpenAIMessage,
+    finish_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -36,77 +96,291 @@ struct OpenAIUsage {
     prompt_tokens: usize,
     completion_tokens: usize,
     total_tokens: usize,
+    prompt_tokens_details: Option<OpenAIPromptTokensDetails>,
 }
 
-pub async fn call_openai_api(provider_config: &ProviderConfig, prompt: &str, max_tokens: Option<usize>) -> Result<AiResponse> {
-    let client = Client::new();
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIPromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: usize,
+}
 
-    let model = provider_config.model.clone().unwrap_or_else(|| "gpt-4-turbo".to_string());
+impl From<OpenAIUsage> for TokenUsage {
+    fn from(usage: OpenAIUsage) -> Self {
+        TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            cache_read_tokens: usage.prompt_tokens_details
+                .map(|details| details.cached_tokens)
+                .unwrap_or(0),
+            cache_write_tokens: 0,
+        }
+    }
+}
 
-    let request = OpenAIRequest {
-        model,
-        messages: vec![
-            OpenAIMessage {
-                role: "system".to_string(),
-                content: prompts::OPENAI_SYSTEM_PROMPT.to_string(),
+fn build_tools() -> Vec<OpenAITool> {
+    tool_specs()
+        .into_iter()
+        .map(|spec| OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: spec.name.to_string(),
+                description: spec.description.to_string(),
+                parameters: spec.parameters,
             },
-            OpenAIMessage {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            },
-        ],
-        temperature: 0.7,
-        max_tokens,
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIModelList {
+    data: Vec<OpenAIModel>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIModel {
+    id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIErrorBody {
+    error: OpenAIError,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenAIError {
+    message: String,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+    code: Option<String>,
+}
+
+/// Map an OpenAI-style error response from `provider` onto the most specific `SageError`
+fn parse_error(provider: &str, status: u16, body: &str, model: &str) -> SageError {
+    let provider = provider.to_string();
+
+    if status == 401 || status == 403 {
+        return SageError::ApiAuthError { provider };
+    }
+
+    let error = match serde_json::from_str::<OpenAIErrorBody>(body) {
+        Ok(parsed) => parsed.error,
+        Err(_) if status == 503 => return SageError::ApiOverloaded { provider },
+        Err(_) => return SageError::ApiResponseError { provider, details: body.to_string() },
     };
 
-    let response = client.post("https://api.openai.com/v1/chat/completions")
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", &provider_config.api_key))
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| SageError::ApiNetworkError {
-            provider: "OpenAI".to_string(),
-            details: e.to_string(),
-        })?;
+    let is = |kind: &str| {
+        error.code.as_deref() == Some(kind) || error.error_type.as_deref() == Some(kind)
+    };
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
+    if is("context_length_exceeded") || is("string_above_max_length") {
+        SageError::ApiContextLengthExceeded { provider, details: error.message }
+    } else if is("model_not_found") {
+        SageError::ApiModelNotFound { provider, model: model.to_string() }
+    } else if is("insufficient_quota") {
+        SageError::ApiQuotaExceeded { provider, details: error.message }
+    } else if is("content_filter") || is("content_policy_violation") {
+        SageError::ApiContentFiltered { provider, details: error.message }
+    } else if is("server_overloaded") || is("engine_overloaded") || status == 503 {
+        SageError::ApiOverloaded { provider }
+    } else {
+        SageError::ApiResponseError { provider, details: error.message }
+    }
+}
+
+fn base_url(provider_config: &ProviderConfig) -> &str {
+    provider_config.base_url.as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/')
+}
+
+/// Run a chat completion; `provider` names the service in errors, since
+/// OpenAI-compatible servers such as Ollama share this client
+pub async fn call_openai_api(
+    client: &Client,
+    provider: &str,
+    provider_config: &ProviderConfig,
+    prompt: &Prompt,
+    max_tokens: Option<usize>,
+    mut tools: Option<&mut ToolRunner>,
+) -> Result<AiResponse> {
+    let model = provider_config.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+    let mut messages = vec![
+        OpenAIMessage::text("system", prompt.system()),
+        // Instructions lead the user message so OpenAI's automatic prefix caching can reuse them
+        OpenAIMessage::text("user", &prompt.to_string()),
+    ];
+    let mut usage = TokenUsage::default();
+
+    loop {
+        let offer_tools = tools.as_ref().is_some_and(|runner| runner.can_continue());
+
+        let request = OpenAIRequest {
+            model: model.clone(),
+            messages,
+            temperature: provider_config.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            top_p: provider_config.top_p,
+            seed: provider_config.seed,
+            stop: provider_config.stop.clone(),
+            max_tokens,
+            tools: tools.is_some().then(build_tools),
+            // Once the budget is spent the tools stay declared but the model must answer
+            tool_choice: (tools.is_some() && !offer_tools).then(|| "none".to_string()),
+        };
+
+        let response = client.post(format!("{}/chat/completions", base_url(provider_config)))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", &provider_config.api_key))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| request_error(provider, e))?;
+        messages = request.messages;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(parse_error(provider, status, &error_text, &model));
+        }
+
+        let response_data: OpenAIResponse = response.json().await
+            .map_err(|e| SageError::ApiResponseError {
+                provider: provider.to_string(),
+                details: format!("Failed to parse response: {}", e),
+            })?;
+
+        usage += response_data.usage.into();
+
+        let Some(choice) = response_data.choices.into_iter().next() else {
+            return Err(SageError::ApiNoResponse {
+                provider: provider.to_string(),
+            });
+        };
 
-        if status == 401 || status == 403 {
-            return Err(SageError::ApiAuthError {
-                provider: "OpenAI".to_string(),
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(SageError::ApiContentFiltered {
+                provider: provider.to_string(),
+                details: "the response was withheld".to_string(),
             });
         }
 
-        return Err(SageError::ApiResponseError {
-            provider: "OpenAI".to_string(),
-            details: error_text,
+        let tool_calls = choice.message.tool_calls.clone().unwrap_or_default();
+        if let (true, Some(runner)) = (offer_tools && !tool_calls.is_empty(), tools.as_deref_mut()) {
+            runner.start_round();
+            messages.push(choice.message);
+
+            for call in tool_calls {
+                let input = serde_json::from_str(&call.function.arguments).unwrap_or(Value::Null);
+                let output = runner.run(&call.function.name, &input);
+                messages.push(OpenAIMessage {
+                    role: "tool".to_string(),
+                    content: Some(output),
+                    tool_calls: None,
+                    tool_call_id: Some(call.id),
+                });
+            }
+            continue;
+        }
+
+        let Some(message) = choice.message.content else {
+            return Err(SageError::ApiNoResponse {
+                provider: provider.to_string(),
+            });
+        };
+
+        return Ok(AiResponse {
+            message: if prompt.markdown {
+                message.trim().to_string()
+            } else {
+                sanitize_commit_message(message.trim())
+            },
+            usage,
         });
     }
+}
 
-    let response_data: OpenAIResponse = response.json().await
+/// Model IDs from a `/models` response
+fn parse_model_list(body: &str) -> Result<Vec<String>> {
+    let model_list: OpenAIModelList = serde_json::from_str(body)
         .map_err(|e| SageError::ApiResponseError {
             provider: "OpenAI".to_string(),
-            details: format!("Failed to parse response: {}", e),
+            details: format!("Failed to parse model list: {}", e),
         })?;
 
-    if let Some(choice) = response_data.choices.first() {
-        let message = choice.message.content.trim().to_string();
-        let sanitized_message = sanitize_commit_message(&message);
+    Ok(model_list.data.into_iter().map(|m| m.id).collect())
+}
 
-        Ok(AiResponse {
-            message: sanitized_message,
-            usage: TokenUsage {
-                input_tokens: response_data.usage.prompt_tokens,
-                output_tokens: response_data.usage.completion_tokens,
-                total_tokens: response_data.usage.total_tokens,
-            },
-        })
-    } else {
-        Err(SageError::ApiNoResponse {
-            provider: "OpenAI".to_string(),
-        })
+pub async fn list_openai_models(client: &Client, provider_config: &ProviderConfig) -> Result<Vec<String>> {
+    let response = client.get(format!("{}/models", base_url(provider_config)))
+        .header("Authorization", format!("Bearer {}", &provider_config.api_key))
+        .send()
+        .await
+        .map_err(|e| request_error("OpenAI", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        let error_text = response.text().await.unwrap_or_default();
+        let model = provider_config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+        return Err(parse_error("OpenAI", status, &error_text, model));
+    }
+
+    let body = response.text().await.map_err(|e| request_error("OpenAI", e))?;
+    parse_model_list(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_body(code: &str, message: &str) -> String {
+        format!(r#"{{"error": {{"message": "{}", "type": "invalid_request_error", "param": null, "code": "{}"}}}}"#, message, code)
+    }
+
+    #[test]
+    fn test_parse_context_length_error() {
+        let body = error_body("context_length_exceeded", "This model's maximum context length is 8192 tokens");
+        assert!(matches!(parse_error("OpenAI", 400, &body, "gpt-4"), SageError::ApiContextLengthExceeded { .. }));
+    }
+
+    #[test]
+    fn test_parse_model_not_found_error() {
+        let body = error_body("model_not_found", "The model `gpt-9` does not exist");
+        match parse_error("OpenAI", 404, &body, "gpt-9") {
+            SageError::ApiModelNotFound { model, .. } => assert_eq!(model, "gpt-9"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // Ollama shares this client, and its errors must point at Ollama
+        let error = parse_error("Ollama", 404, &body, "llama9");
+        assert!(error.to_string().contains("not available on Ollama"));
+        assert!(error.to_string().contains("sage models -p ollama --select"));
+    }
+
+    #[test]
+    fn test_parse_quota_and_overloaded_errors() {
+        let body = r#"{"error": {"message": "You exceeded your current quota", "type": "insufficient_quota", "param": null, "code": "insufficient_quota"}}"#;
+        assert!(matches!(parse_error("OpenAI", 429, body, "gpt-4"), SageError::ApiQuotaExceeded { .. }));
+        assert!(matches!(parse_error("OpenAI", 503, "Service Unavailable", "gpt-4"), SageError::ApiOverloaded { .. }));
+    }
+
+    #[test]
+    fn test_parse_unknown_error_keeps_message() {
+        let body = error_body("something_else", "Something odd happened");
+        match parse_error("OpenAI", 400, &body, "gpt-4") {
+            SageError::ApiResponseError { details, .. } => assert_eq!(details, "Something odd happened"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = r#"{"object": "list", "data": [
+            {"id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system"},
+            {"id": "gpt-4-turbo", "object": "model", "created": 1712361441, "owned_by": "system"}
+        ]}"#;
+        assert_eq!(parse_model_list(body).unwrap(), vec!["gpt-4o", "gpt-4-turbo"]);
+        assert!(parse_model_list(r#"{"models": []}"#).is_err());
     }
 }
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::ProviderConfig;
+use crate::error::{Result, SageError};
+use super::{AiResponse, TokenUsage};
+
+/// Fixture directory used by the replay provider when none is configured
+pub const DEFAULT_FIXTURES_DIR: &str = ".sage-fixtures";
+
+/// A recorded request/response pair, stored as `<prompt-hash>.json`
+#[derive(Serialize, Deserialize, Debug)]
+struct Fixture {
+    provider: String,
+    model: Option<String>,
+    prompt: String,
+    message: String,
+    usage: TokenUsage,
+}
+
+/// Stable FNV-1a hash of the prompt, used as the fixture file name
+pub fn fixture_key(prompt: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in prompt.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn fixture_path(dir: &str, prompt: &str) -> PathBuf {
+    Path::new(dir).join(format!("{}.json", fixture_key(prompt)))
+}
+
+pub fn record_response(
+    dir: &str,
+    provider: &str,
+    model: Option<&str>,
+    prompt: &str,
+    response: &AiResponse,
+) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let fixture = Fixture {
+        provider: provider.to_string(),
+        model: model.map(|m| m.to_string()),
+        prompt: prompt.to_string(),
+        message: response.message.clone(),
+        usage: response.usage.clone(),
+    };
+
+    fs::write(fixture_path(dir, prompt), serde_json::to_string_pretty(&fixture)?)?;
+    Ok(())
+}
+
+pub fn replay_response(provider_config: &ProviderConfig, prompt: &str) -> Result<AiResponse> {
+    let dir = provider_config.fixtures_dir.as_deref().unwrap_or(DEFAULT_FIXTURES_DIR);
+    let path = fixture_path(dir, prompt);
+
+    let miss = || SageError::ApiReplayMiss {
+        key: fixture_key(prompt),
+        dir: dir.to_string(),
+    };
+
+    if !path.exists() {
+        return Err(miss());
+    }
+
+    let fixture: Fixture = serde_json::from_str(&fs::read_to_string(&path)?)
+        .map_err(|e| SageError::ApiResponseError {
+            provider: "Replay".to_string(),
+            details: format!("Invalid fixture {}: {}", path.display(), e),
+        })?;
+
+    // Guard against hash collisions and hand-edited fixtures
+    if fixture.prompt != prompt {
+        return Err(miss());
+    }
+
+    Ok(AiResponse {
+        message: fixture.message,
+        usage: fixture.usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay_config(dir: &str) -> ProviderConfig {
+        ProviderConfig {
+            fixtures_dir: Some(dir.to_string()),
+            ..ProviderConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_fixture_key_is_stable() {
+        assert_eq!(fixture_key(""), "cbf29ce484222325");
+        assert_eq!(fixture_key("prompt"), fixture_key("prompt"));
+        assert_ne!(fixture_key("prompt a"), fixture_key("prompt b"));
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_str = dir.path().to_str().unwrap();
+
+        let response = AiResponse {
+            message: "feat: add replay provider".to_string(),
+            usage: TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+                total_tokens: 15,
+                cache_read_tokens: 0,
+                cache_write_tokens: 0,
+            },
+        };
+        record_response(dir_str, "openai", Some("gpt-4-turbo"), "the prompt", &response).unwrap();
+
+        let replayed = replay_response(&replay_config(dir_str), "the prompt").unwrap();
+        assert_eq!(replayed.message, "feat: add replay provider");
+        assert_eq!(replayed.usage.total_tokens, 15);
+    }
+
+    #[test]
+    fn test_replay_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_str = dir.path().to_str().unwrap();
+
+        match replay_response(&replay_config(dir_str), "unknown prompt") {
+            Err(SageError::ApiReplayMiss { key, .. }) => assert_eq!(key, fixture_key("unknown prompt")),
+            _ => panic!("expected a replay miss"),
+        }
+    }
+}
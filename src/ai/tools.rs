@@ -0,0 +1,179 @@
+//! Read-only repository tools for tool-calling mode
+//!
+//! The model may ask for file contents, the symbols touched by the staged
+//! hunks, or the history of a path. Every call is capped in rounds and bytes
+//! so a chatty model cannot blow up the request size.
+
+use serde_json::{json, Value};
+
+use crate::git::{get_changed_symbols, get_path_log, show_file, FileSource};
+
+/// Maximum number of request/response rounds in which tools may be called
+pub const MAX_TOOL_ROUNDS: usize = 4;
+/// Maximum bytes returned by a single tool call
+const MAX_RESULT_BYTES: usize = 8_000;
+/// Maximum bytes returned by all tool calls together
+const MAX_TOTAL_BYTES: usize = 24_000;
+const DEFAULT_LOG_LIMIT: usize = 10;
+
+/// Provider-neutral description of a tool
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+}
+
+pub fn tool_specs() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec {
+            name: "read_file",
+            description: "Read a file from the repository, either as committed at HEAD or as staged in the index.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path relative to the repository root" },
+                    "source": { "type": "string", "enum": ["head", "index"], "description": "Read the committed (head) or staged (index) version" }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolSpec {
+            name: "list_changed_symbols",
+            description: "List the functions or sections that contain each staged change.",
+            parameters: json!({ "type": "object", "properties": {} }),
+        },
+        ToolSpec {
+            name: "git_log",
+            description: "Show recent commit subjects that touched a path.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path relative to the repository root" },
+                    "limit": { "type": "integer", "description": "Number of commits to show (default 10, max 30)" }
+                },
+                "required": ["path"]
+            }),
+        },
+    ]
+}
+
+/// Executes tool calls while enforcing the round and byte budgets
+#[derive(Default)]
+pub struct ToolRunner {
+    rounds: usize,
+    bytes: usize,
+}
+
+impl ToolRunner {
+    pub fn new() -> Self {
+        ToolRunner::default()
+    }
+
+    /// Whether the model may still be offered tools for another round
+    pub fn can_continue(&self) -> bool {
+        self.rounds < MAX_TOOL_ROUNDS && self.bytes < MAX_TOTAL_BYTES
+    }
+
+    pub fn start_round(&mut self) {
+        self.rounds += 1;
+    }
+
+    /// Run a tool and return its output; failures are reported to the model as text
+    pub fn run(&mut self, name: &str, input: &Value) -> String {
+        let remaining = MAX_TOTAL_BYTES.saturating_sub(self.bytes);
+        if remaining == 0 {
+            return "Tool budget exhausted. Answer with the information you already have.".to_string();
+        }
+
+        let output = match execute(name, input) {
+            Ok(output) if output.trim().is_empty() => "(no output)".to_string(),
+            Ok(output) => output,
+            Err(error) => format!("Error: {}", error),
+        };
+
+        let output = truncate(&output, MAX_RESULT_BYTES.min(remaining));
+        self.bytes += output.len();
+        output
+    }
+}
+
+fn execute(name: &str, input: &Value) -> std::result::Result<String, String> {
+    let path = || {
+        input.get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "missing 'path' argument".to_string())
+    };
+
+    match name {
+        "read_file" => {
+            let source = match input.get("source").and_then(Value::as_str) {
+                Some("head") => FileSource::Head,
+                _ => FileSource::Index,
+            };
+            show_file(source, path()?).map_err(|e| first_line(&e.to_string()))
+        },
+        "list_changed_symbols" => get_changed_symbols().map_err(|e| first_line(&e.to_string())),
+        "git_log" => {
+            let limit = input.get("limit")
+                .and_then(Value::as_u64)
+                .map(|l| (l as usize).clamp(1, 30))
+                .unwrap_or(DEFAULT_LOG_LIMIT);
+            get_path_log(path()?, limit).map_err(|e| first_line(&e.to_string()))
+        },
+        _ => Err(format!("unknown tool '{}'", name)),
+    }
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+/// Cut `text` to at most `max_bytes`, marking where it was cut
+pub fn truncate(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n... [truncated]", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_tool_and_missing_arguments() {
+        let mut runner = ToolRunner::new();
+        assert_eq!(runner.run("rm_rf", &json!({})), "Error: unknown tool 'rm_rf'");
+        assert_eq!(runner.run("read_file", &json!({})), "Error: missing 'path' argument");
+    }
+
+    #[test]
+    fn test_paths_outside_repository_are_rejected() {
+        let mut runner = ToolRunner::new();
+        let output = runner.run("read_file", &json!({ "path": "../secrets.txt" }));
+        assert!(output.starts_with("Error: Invalid input"));
+    }
+
+    #[test]
+    fn test_round_budget() {
+        let mut runner = ToolRunner::new();
+        for _ in 0..MAX_TOOL_ROUNDS {
+            assert!(runner.can_continue());
+            runner.start_round();
+        }
+        assert!(!runner.can_continue());
+    }
+
+    #[test]
+    fn test_truncate_output() {
+        let text = "a".repeat(MAX_RESULT_BYTES * 2);
+        let truncated = truncate(&text, MAX_RESULT_BYTES);
+        assert!(truncated.ends_with("[truncated]"));
+        assert!(truncated.len() < text.len());
+    }
+}
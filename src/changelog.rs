@@ -0,0 +1,407 @@
+//! Changelog sections from commit history (`sage changelog`)
+//!
+//! Commits are grouped by their Conventional Commits type into a Keep a
+//! Changelog or conventional-changelog section, optionally reworded into
+//! user-facing release notes by the AI, and inserted above the newest section
+//! of an existing changelog file.
+
+use std::fs;
+use std::path::Path;
+use colored::Colorize;
+use regex::Regex;
+
+use crate::ai::call_ai;
+use crate::cli::{ChangelogArgs, ChangelogFormat, Task};
+use crate::config::Config;
+use crate::error::{Result, SageError};
+use crate::git::{get_commit_date, get_commits, get_history, get_last_tag, get_tag_at, CommitInfo};
+use crate::lint::{parse_conventional, ConventionalCommit};
+use crate::progress::spinner;
+use crate::prompts;
+
+/// Heading of the section for changes that are not released yet
+const UNRELEASED: &str = "Unreleased";
+
+const KEEP_A_CHANGELOG_PREAMBLE: &str = "\
+# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+";
+
+const CONVENTIONAL_PREAMBLE: &str = "# Changelog\n";
+
+/// Group headings in the order they are written
+const KEEP_A_CHANGELOG_GROUPS: &[&str] = &["Added", "Changed", "Fixed"];
+const CONVENTIONAL_GROUPS: &[&str] = &[
+    "⚠ BREAKING CHANGES",
+    "Features",
+    "Bug Fixes",
+    "Performance Improvements",
+    "Reverts",
+];
+
+/// One line of the changelog
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub sha: String,
+    /// First line of the commit message
+    pub header: String,
+    pub commit: Option<ConventionalCommit>,
+    /// Text shown for the entry: the subject, or a rewritten release note
+    pub text: String,
+}
+
+impl Entry {
+    fn new(info: &CommitInfo) -> Entry {
+        let header = info.message.lines().next().unwrap_or("").trim().to_string();
+        let commit = parse_conventional(&info.message);
+        let text = commit.as_ref().map(|commit| commit.subject.clone()).unwrap_or_else(|| header.clone());
+
+        Entry { sha: info.sha.clone(), header, commit, text }
+    }
+
+    fn commit_type(&self) -> Option<&str> {
+        self.commit.as_ref().map(|commit| commit.commit_type.as_str())
+    }
+
+    fn breaking(&self) -> bool {
+        self.commit.as_ref().is_some_and(|commit| commit.breaking)
+    }
+
+    fn scope(&self) -> Option<&str> {
+        self.commit.as_ref().and_then(|commit| commit.scope.as_deref())
+    }
+}
+
+/// Entries under one heading
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub heading: &'static str,
+    pub entries: Vec<Entry>,
+}
+
+/// Heading an entry belongs under; None leaves it out of the changelog
+/// (docs, tests, chores and other changes users do not see)
+fn heading_for(format: ChangelogFormat, entry: &Entry) -> Option<&'static str> {
+    match format {
+        ChangelogFormat::KeepAChangelog => match entry.commit_type() {
+            Some("feat") => Some("Added"),
+            Some("fix") => Some("Fixed"),
+            Some("docs" | "style" | "test" | "chore" | "ci" | "build") if !entry.breaking() => None,
+            _ => Some("Changed"),
+        },
+        ChangelogFormat::Conventional => match entry.commit_type()? {
+            "feat" => Some("Features"),
+            "fix" => Some("Bug Fixes"),
+            "perf" => Some("Performance Improvements"),
+            "revert" => Some("Reverts"),
+            _ => None,
+        },
+    }
+}
+
+/// Group commits (newest first, as git lists them) into headings, oldest
+/// entry first within each heading. Merge commits are skipped.
+pub fn build_groups(commits: &[CommitInfo], format: ChangelogFormat) -> Vec<Group> {
+    let headings = match format {
+        ChangelogFormat::KeepAChangelog => KEEP_A_CHANGELOG_GROUPS,
+        ChangelogFormat::Conventional => CONVENTIONAL_GROUPS,
+    };
+    let mut groups: Vec<Group> = headings.iter()
+        .map(|heading| Group { heading, entries: Vec::new() })
+        .collect();
+
+    for info in commits.iter().rev().filter(|info| !info.message.starts_with("Merge ")) {
+        let entry = Entry::new(info);
+
+        if format == ChangelogFormat::Conventional && entry.breaking() {
+            let mut breaking = entry.clone();
+            if let Some(note) = entry.commit.as_ref().and_then(|commit| commit.breaking_note.clone()) {
+                breaking.text = note;
+            }
+            groups[0].entries.push(breaking);
+        }
+
+        if let Some(heading) = heading_for(format, &entry) {
+            if let Some(group) = groups.iter_mut().find(|group| group.heading == heading) {
+                group.entries.push(entry);
+            }
+        }
+    }
+
+    groups.retain(|group| !group.entries.is_empty());
+    groups
+}
+
+/// Version named by a tag: "v1.2.0" is 1.2.0
+pub fn version_from_tag(tag: &str) -> &str {
+    tag.strip_prefix('v')
+        .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(tag)
+}
+
+fn short(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+/// Heading prefixes that mark an existing section for `version`
+fn section_keys(format: ChangelogFormat, version: Option<&str>) -> Vec<String> {
+    let name = version.unwrap_or(UNRELEASED);
+    match format {
+        ChangelogFormat::KeepAChangelog => vec![format!("## [{}]", name)],
+        ChangelogFormat::Conventional => vec![format!("## {} ", name), format!("## [{}](", name)],
+    }
+}
+
+/// Render a changelog section
+pub fn render(format: ChangelogFormat, version: Option<&str>, date: &str, groups: &[Group]) -> String {
+    let mut out = match (format, version) {
+        (ChangelogFormat::KeepAChangelog, Some(version)) => format!("## [{}] - {}\n", version, date),
+        (ChangelogFormat::KeepAChangelog, None) => format!("## [{}]\n", UNRELEASED),
+        (ChangelogFormat::Conventional, version) => format!("## {} ({})\n", version.unwrap_or(UNRELEASED), date),
+    };
+
+    for group in groups {
+        out.push_str(&format!("\n### {}\n\n", group.heading));
+        for entry in &group.entries {
+            let scope = entry.scope().map(|scope| format!("**{}:** ", scope)).unwrap_or_default();
+            match format {
+                ChangelogFormat::KeepAChangelog => {
+                    let breaking = if entry.breaking() { "**BREAKING:** " } else { "" };
+                    out.push_str(&format!("- {}{}{}\n", breaking, scope, entry.text));
+                },
+                ChangelogFormat::Conventional => {
+                    out.push_str(&format!("* {}{} ({})\n", scope, entry.text, short(&entry.sha)));
+                },
+            }
+        }
+    }
+
+    out
+}
+
+/// Insert `section` above the newest section of `existing` (a whole changelog
+/// file), keeping its title and introduction. Refuses to add a second
+/// section for the same version.
+fn insert_section(existing: &str, section: &str, keys: &[String], format: ChangelogFormat) -> Result<String> {
+    if existing.trim().is_empty() {
+        let preamble = match format {
+            ChangelogFormat::KeepAChangelog => KEEP_A_CHANGELOG_PREAMBLE,
+            ChangelogFormat::Conventional => CONVENTIONAL_PREAMBLE,
+        };
+        return Ok(format!("{}\n{}", preamble, section));
+    }
+
+    if let Some(line) = existing.lines().find(|line| keys.iter().any(|key| line.starts_with(key.as_str()))) {
+        return Err(SageError::InvalidInput(format!(
+            "The changelog already has this section: '{}'. Remove it first or pass a different --version", line
+        )));
+    }
+
+    // Byte offset of the first version heading
+    let mut offset = 0;
+    for line in existing.split_inclusive('\n') {
+        if line.starts_with("## ") {
+            return Ok(format!("{}{}\n{}", &existing[..offset], section, &existing[offset..]));
+        }
+        offset += line.len();
+    }
+
+    let separator = if existing.ends_with("\n\n") { "" } else if existing.ends_with('\n') { "\n" } else { "\n\n" };
+    Ok(format!("{}{}{}", existing, separator, section))
+}
+
+/// Replace entry texts with the numbered notes in the AI's answer; entries it
+/// skipped keep their subject
+fn apply_notes(entries: &mut [&mut Entry], answer: &str) {
+    let note_re = Regex::new(r"^\s*(\d+)[.)]\s+(.+)$").unwrap();
+    for line in answer.lines() {
+        let Some(captures) = note_re.captures(line) else {
+            continue;
+        };
+        let Ok(number) = captures[1].parse::<usize>() else {
+            continue;
+        };
+        if let Some(entry) = number.checked_sub(1).and_then(|index| entries.get_mut(index)) {
+            entry.text = captures[2].trim().trim_end_matches('.').to_string();
+        }
+    }
+}
+
+/// Rewrite entry texts into user-facing release notes
+pub async fn rewrite_entries(config: &Config, groups: &mut [Group]) -> Result<()> {
+    let mut entries: Vec<&mut Entry> = groups.iter_mut()
+        .flat_map(|group| group.entries.iter_mut())
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    // Breaking-change entries show the footer's note, so the model gets it too
+    let headers: Vec<String> = entries.iter()
+        .map(|entry| match entry.commit.as_ref().and_then(|commit| commit.breaking_note.as_deref()) {
+            Some(note) if entry.text == note => format!("{} (breaking change: {})", entry.header, note),
+            _ => entry.header.clone(),
+        })
+        .collect();
+
+    let spinner = spinner(format!("Writing release notes for {} entries using AI...", entries.len()));
+
+    let result = call_ai(config, Task::Summarize, &prompts::build_release_notes_prompt(&headers)).await;
+    spinner.finish_and_clear();
+
+    apply_notes(&mut entries, &result?.message);
+    Ok(())
+}
+
+/// Commits between `from` (exclusive, defaulting to the last tag before `to`)
+/// and `to`, newest first
+pub fn commits_since(from: Option<&str>, to: &str) -> Result<(Option<String>, Vec<CommitInfo>)> {
+    let from = match from {
+        Some(from) => Some(from.to_string()),
+        // Start below `to` so that a tagged `to` is compared with the tag before it
+        None => get_last_tag(&format!("{}^", to))?,
+    };
+
+    let commits = match &from {
+        Some(from) => get_commits(&format!("{}..{}", from, to))?,
+        None => get_history(to)?,
+    };
+
+    Ok((from, commits))
+}
+
+/// `sage changelog`: print the section, or insert it into a changelog file
+pub async fn run_changelog(config: &Config, args: &ChangelogArgs) -> Result<()> {
+    let (from, commits) = commits_since(args.from.as_deref(), &args.to)?;
+    if commits.is_empty() {
+        return Err(SageError::InvalidInput(format!(
+            "No commits between {} and {}", from.as_deref().unwrap_or("the first commit"), args.to
+        )));
+    }
+
+    let mut groups = build_groups(&commits, args.format);
+    if groups.is_empty() {
+        println!("{}", "No user-facing changes in this range (only docs, tests, chores and the like).".yellow());
+        return Ok(());
+    }
+
+    if args.ai {
+        rewrite_entries(config, &mut groups).await?;
+    }
+
+    // A tagged end of range is that release, not unreleased work
+    let version = match &args.version {
+        Some(version) => Some(version.clone()),
+        None => get_tag_at(&args.to)?.map(|tag| version_from_tag(&tag).to_string()),
+    };
+
+    let section = render(args.format, version.as_deref(), &get_commit_date(&args.to)?, &groups);
+
+    let Some(path) = &args.output else {
+        print!("{}", section);
+        return Ok(());
+    };
+
+    let existing = if Path::new(path).exists() { fs::read_to_string(path)? } else { String::new() };
+    let keys = section_keys(args.format, version.as_deref());
+    fs::write(path, insert_section(&existing, &section, &keys, args.format)?)?;
+
+    let heading = section.lines().next().unwrap_or("").trim_start_matches("## ");
+    println!("{}", format!("Added {} to {}", heading, path).green());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commits(messages: &[&str]) -> Vec<CommitInfo> {
+        // Newest first, as git log lists them
+        messages.iter()
+            .enumerate()
+            .rev()
+            .map(|(index, message)| CommitInfo {
+                sha: format!("{:07}aaaa", index),
+                message: message.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let commits = commits(&[
+            "feat(api): add pagination",
+            "docs: fix typo",
+            "fix: handle empty input",
+            "Merge branch 'x'",
+            "refactor!: rename config keys\n\nBREAKING CHANGE: keys are snake_case now",
+            "tweak the logo",
+        ]);
+
+        let groups = build_groups(&commits, ChangelogFormat::KeepAChangelog);
+        assert_eq!(render(ChangelogFormat::KeepAChangelog, Some("1.2.0"), "2024-05-01", &groups), "\
+## [1.2.0] - 2024-05-01
+
+### Added
+
+- **api:** add pagination
+
+### Changed
+
+- **BREAKING:** rename config keys
+- tweak the logo
+
+### Fixed
+
+- handle empty input
+");
+
+        let groups = build_groups(&commits, ChangelogFormat::Conventional);
+        assert_eq!(render(ChangelogFormat::Conventional, None, "2024-05-01", &groups), "\
+## Unreleased (2024-05-01)
+
+### ⚠ BREAKING CHANGES
+
+* keys are snake_case now (0000004)
+
+### Features
+
+* **api:** add pagination (0000000)
+
+### Bug Fixes
+
+* handle empty input (0000002)
+");
+    }
+
+    #[test]
+    fn test_insert_section() {
+        let format = ChangelogFormat::KeepAChangelog;
+        let keys = section_keys(format, Some("1.1.0"));
+        let section = "## [1.1.0] - 2024-05-01\n\n### Fixed\n\n- b\n";
+
+        let created = insert_section("", section, &keys, format).unwrap();
+        assert!(created.starts_with(KEEP_A_CHANGELOG_PREAMBLE));
+        assert!(created.ends_with(section));
+
+        let existing = "# Changelog\n\nIntro.\n\n## [1.0.0] - 2024-01-01\n\n- a\n";
+        let updated = insert_section(existing, section, &keys, format).unwrap();
+        assert_eq!(updated, format!("# Changelog\n\nIntro.\n\n{}\n## [1.0.0] - 2024-01-01\n\n- a\n", section));
+
+        assert!(insert_section(&updated, section, &keys, format).is_err());
+    }
+
+    #[test]
+    fn test_apply_notes() {
+        let mut first = Entry::new(&CommitInfo { sha: "1".to_string(), message: "feat: add x".to_string() });
+        let mut second = Entry::new(&CommitInfo { sha: "2".to_string(), message: "fix: y".to_string() });
+        let mut entries = vec![&mut first, &mut second];
+
+        apply_notes(&mut entries, "Here you go:\n2) Fixed a crash when `y` is empty.\n9. Unknown");
+        assert_eq!(entries[0].text, "add x");
+        assert_eq!(entries[1].text, "Fixed a crash when `y` is empty");
+    }
+}
@@ -25,6 +25,10 @@ pub struct Cli {
     #[arg(short, long)]
     pub all: bool,
 
+    /// Choose unstaged hunks to stage before generating (limited to FILES if given)
+    #[arg(short, long, conflicts_with = "all")]
+    pub interactive: bool,
+
     /// Commit message to use (skips AI generation)
     #[arg(short, long)]
     pub message: Option<String>,
@@ -57,9 +61,161 @@ pub struct Cli {
     #[arg(short = 'y', long = "yes")]
     pub yes: bool,
 
+    /// Add a Signed-off-by trailer to the commit
+    #[arg(long)]
+    pub signoff: bool,
+
+    /// Sign the commit (GPG or SSH per gpg.format); use -S=KEYID for a specific key
+    #[arg(short = 'S', long, value_name = "KEYID", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    pub gpg_sign: Option<String>,
+
+    /// Override the commit author ("Name <email>")
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Override the author date (any format git accepts)
+    #[arg(long)]
+    pub date: Option<String>,
+
+    /// Skip the pre-commit and commit-msg hooks
+    #[arg(long)]
+    pub no_verify: bool,
+
     /// Style of commit message to generate
     #[arg(short = 't', long, value_enum)]
     pub style: Option<CommitStyle>,
+
+    /// Let the AI read files, changed symbols and history from the repository
+    #[arg(long)]
+    pub tools: bool,
+
+    /// Review the staged changes with AI before committing
+    #[arg(long)]
+    pub review: bool,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+
+    /// Record AI requests and responses as replay fixtures in this directory
+    #[arg(long, global = true, value_name = "DIR")]
+    pub record: Option<String>,
+}
+
+/// Per-run sampling overrides, applied on top of the provider configuration
+#[derive(Args, Debug, Clone, Default)]
+pub struct SamplingArgs {
+    /// Sampling temperature for this run (0-2)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling probability for this run (0-1)
+    #[arg(long)]
+    pub top_p: Option<f32>,
+
+    /// Seed for reproducible sampling (where the provider supports it)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Stop sequence for this run (repeatable)
+    #[arg(long)]
+    pub stop: Vec<String>,
+
+    /// Pin temperature to 0 and use a fixed seed for stable output
+    #[arg(long)]
+    pub deterministic: bool,
+}
+
+/// Kinds of AI work that can be routed to their own provider/model
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Task {
+    /// Commit message generation
+    Commit,
+    /// Branch name generation
+    Branch,
+    /// Summaries of individual files or commits
+    Summarize,
+    /// Code review
+    Review,
+    /// Pull request titles and descriptions
+    Pr,
+}
+
+impl Task {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Task::Commit => "commit",
+            Task::Branch => "branch",
+            Task::Summarize => "summarize",
+            Task::Review => "review",
+            Task::Pr => "pr",
+        }
+    }
+}
+
+/// Layouts `sage changelog` can write
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ChangelogFormat {
+    /// Keep a Changelog: Added, Changed and Fixed sections
+    KeepAChangelog,
+    /// conventional-changelog: Features, Bug Fixes and breaking changes with commit hashes
+    Conventional,
+}
+
+/// Output formats for `sage review`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ReviewFormat {
+    /// Findings for the terminal
+    Text,
+    /// JSON object with a "findings" list
+    Json,
+    /// SARIF 2.1.0, for code scanning tools
+    Sarif,
+}
+
+/// Semantic version components `sage release` can increment
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        }
+    }
+}
+
+/// Code hosting services `sage pr --create` can open pull requests on
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Forge {
+    /// GitHub or GitHub Enterprise
+    #[value(name = "github")]
+    GitHub,
+    /// GitLab.com or self-hosted GitLab
+    #[value(name = "gitlab")]
+    GitLab,
+}
+
+impl Forge {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "github",
+            Forge::GitLab => "gitlab",
+        }
+    }
+
+    /// Display name used in messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "GitHub",
+            Forge::GitLab => "GitLab",
+        }
+    }
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
@@ -77,11 +233,11 @@ pub enum CommitStyle {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Configure API settings
-    Config(ConfigArgs),
+    Config(Box<ConfigArgs>),
 
     /// Switch between configured providers
     Use {
-        /// Provider to switch to (e.g., "openai", "claude")
+        /// Provider to switch to (e.g., "openai", "claude", "ollama")
         provider: String,
     },
 
@@ -117,6 +273,112 @@ pub enum Commands {
         /// Be more verbose about what's happening
         #[arg(short, long)]
         verbose: bool,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// List available models for configured providers
+    Models {
+        /// Only list models for this provider
+        #[arg(short, long)]
+        provider: Option<String>,
+
+        /// Interactively pick the model to use for the provider
+        #[arg(short, long)]
+        select: bool,
+    },
+
+    /// Diagnose configuration, git and provider problems
+    Doctor,
+
+    /// Check commit messages against Conventional Commits
+    Lint {
+        /// Commit or range to lint (e.g. origin/main..HEAD); defaults to HEAD
+        range: Option<String>,
+
+        /// Lint a commit message file instead (used by the commit-msg hook)
+        #[arg(long, conflicts_with = "range")]
+        file: Option<String>,
+
+        /// Suggest an AI-rewritten message for messages with errors
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Split the staged changes into several commits grouped by the AI
+    Split {
+        /// Additional context to help AI group the changes
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Style of the generated commit messages
+        #[arg(short = 't', long, value_enum)]
+        style: Option<CommitStyle>,
+
+        /// Show the proposed commits without creating them
+        #[arg(short, long)]
+        dry_run: bool,
+
+        /// Apply the proposed plan without asking
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Regenerate the messages of existing commits and rewrite history
+    Reword {
+        /// Commits to reword (e.g. origin/main..HEAD, or a single commit)
+        range: String,
+
+        /// Additional context to help AI generate better messages
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Style of the generated commit messages
+        #[arg(short = 't', long, value_enum)]
+        style: Option<CommitStyle>,
+
+        /// Rewrite commits that are already on the upstream branch
+        #[arg(long)]
+        force: bool,
+
+        /// Show the new messages without rewriting history
+        #[arg(short, long)]
+        dry_run: bool,
+
+        /// Rewrite without asking
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Generate one message for the commits since a base, optionally squashing them
+    Squash(SquashArgs),
+
+    /// Generate a pull request title and description for the current branch
+    Pr(PrArgs),
+
+    /// Write a changelog section for the commits since the last tag
+    Changelog(ChangelogArgs),
+
+    /// Tag the next semantic version with an AI-written release summary
+    Release(ReleaseArgs),
+
+    /// Review the staged changes, or a branch against its base, with AI
+    Review(ReviewArgs),
+
+    /// Explain in plain language what an existing commit or range does
+    Explain(ExplainArgs),
+
+    /// Install git hooks that generate or lint commit messages
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
     },
 
     /// Generate shell completion scripts
@@ -127,9 +389,212 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum HookAction {
+    /// Install a hook into the repository's hooks directory (honors core.hooksPath)
+    Install {
+        #[arg(value_enum, default_value = "prepare-commit-msg")]
+        hook: HookKind,
+    },
+
+    /// Remove a sage hook, restoring any hook it replaced
+    Uninstall {
+        #[arg(value_enum, default_value = "prepare-commit-msg")]
+        hook: HookKind,
+    },
+
+    /// prepare-commit-msg entrypoint called by git
+    Run {
+        /// File holding the commit message
+        msg_file: String,
+
+        /// Message source (message, template, merge, squash or commit)
+        source: Option<String>,
+
+        /// Commit SHA, given for amends
+        sha: Option<String>,
+    },
+}
+
+/// Git hooks that sage can install
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum HookKind {
+    /// Generate a message for plain `git commit`
+    PrepareCommitMsg,
+    /// Reject messages that fail `sage lint`
+    CommitMsg,
+}
+
+#[derive(Args, Debug)]
+pub struct SquashArgs {
+    /// Branch or commit the squashed commit goes on top of (e.g. main)
+    pub base: String,
+
+    /// Squash the commits into one with the generated message
+    #[arg(long)]
+    pub commit: bool,
+
+    /// Additional context to help AI generate a better message
+    #[arg(short, long)]
+    pub context: Option<String>,
+
+    /// Style of the generated commit message
+    #[arg(short = 't', long, value_enum)]
+    pub style: Option<CommitStyle>,
+
+    /// Squash commits that are already on the upstream branch
+    #[arg(long)]
+    pub force: bool,
+
+    /// Skip confirmation prompt (with --commit)
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct PrArgs {
+    /// Branch the pull request merges into (defaults to the configured PR base,
+    /// then the remote's default branch)
+    #[arg(short, long)]
+    pub base: Option<String>,
+
+    /// Additional context to help AI describe the changes
+    #[arg(short, long)]
+    pub context: Option<String>,
+
+    /// Write the title and description to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    /// Push the branch and open the pull request (or update the open one)
+    #[arg(long)]
+    pub create: bool,
+
+    /// Forge to open the pull request on (detected from the remote URL by default)
+    #[arg(long, value_enum, requires = "create")]
+    pub forge: Option<Forge>,
+
+    /// Skip confirmation prompt (with --create)
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ChangelogArgs {
+    /// Start of the range, exclusive (defaults to the last tag)
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// End of the range
+    #[arg(long, default_value = "HEAD")]
+    pub to: String,
+
+    /// Version for the section heading (defaults to the tag at --to, else "Unreleased")
+    #[arg(long)]
+    pub version: Option<String>,
+
+    /// Changelog layout
+    #[arg(long, value_enum, default_value = "keep-a-changelog")]
+    pub format: ChangelogFormat,
+
+    /// Rewrite commit subjects into user-facing release notes with AI
+    #[arg(long)]
+    pub ai: bool,
+
+    /// Insert the section at the top of this changelog file (e.g. CHANGELOG.md)
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ReleaseArgs {
+    /// Increment this component instead of the one the commits call for
+    #[arg(long, value_enum, conflicts_with = "version")]
+    pub bump: Option<Bump>,
+
+    /// Release this exact version (e.g. 2.0.0-rc.1)
+    #[arg(long)]
+    pub version: Option<String>,
+
+    /// Also write the version into Cargo.toml and package.json and commit them
+    #[arg(long)]
+    pub write_version: bool,
+
+    /// Additional context for the release summary
+    #[arg(short, long)]
+    pub context: Option<String>,
+
+    /// Print the next version and release notes without tagging
+    #[arg(short, long)]
+    pub dry_run: bool,
+
+    /// Skip confirmation prompt
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ReviewArgs {
+    /// Review the current branch against this base instead of the staged changes
+    #[arg(short, long)]
+    pub base: Option<String>,
+
+    /// Additional context for the review
+    #[arg(short, long)]
+    pub context: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: ReviewFormat,
+
+    /// Write the review to this file instead of printing it
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ExplainArgs {
+    /// Commit or range to explain (e.g. HEAD~2, v1.2.0..v1.3.0, main...feature)
+    pub rev: String,
+
+    /// Also send the contents of the changed files, for context around the diff
+    #[arg(long)]
+    pub files: bool,
+
+    /// Additional context for the explanation
+    #[arg(short, long)]
+    pub context: Option<String>,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
 #[derive(Args, Debug)]
 pub struct ConfigArgs {
-    /// Set API provider (openai, claude, etc.)
+    /// Apply --provider/--model to this task's route instead of the defaults
+    #[arg(long, value_enum)]
+    pub task: Option<Task>,
+
+    /// Remove the provider/model route for a task
+    #[arg(long, value_enum)]
+    pub clear_task: Option<Task>,
+
+    /// Set API provider (openai, claude, ollama, etc.)
     #[arg(short, long)]
     pub provider: Option<String>,
 
@@ -149,6 +614,74 @@ pub struct ConfigArgs {
     #[arg(long)]
     pub model: Option<String>,
 
+    /// Set API base URL for the provider (OpenAI-compatible endpoints, proxies)
+    #[arg(long)]
+    pub base_url: Option<String>,
+
+    /// Set fixture directory for the replay provider
+    #[arg(long)]
+    pub fixtures_dir: Option<String>,
+
+    /// Set sampling temperature for the provider (0-2)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Set nucleus sampling probability for the provider (0-1)
+    #[arg(long)]
+    pub top_p: Option<f32>,
+
+    /// Set sampling seed for the provider
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Set stop sequences for the provider (repeatable)
+    #[arg(long)]
+    pub stop: Vec<String>,
+
+    /// Set HTTPS proxy URL for API calls (defaults to HTTPS_PROXY)
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// Set hosts that bypass the proxy, comma-separated (defaults to NO_PROXY)
+    #[arg(long)]
+    pub no_proxy: Option<String>,
+
+    /// Add a PEM CA certificate bundle to trust (repeatable)
+    #[arg(long)]
+    pub ca_cert: Vec<String>,
+
+    /// Set connect timeout for API calls in seconds
+    #[arg(long)]
+    pub connect_timeout: Option<u64>,
+
+    /// Set read timeout for API calls in seconds
+    #[arg(long)]
+    pub read_timeout: Option<u64>,
+
+    /// Set default commit author override ("Name <email>")
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Set git backend: libgit2 (in-process) or cli (runs the git executable)
+    #[arg(long)]
+    pub git_backend: Option<String>,
+
+    /// Apply --forge-token/--forge-api-url to this forge
+    #[arg(long, value_enum)]
+    pub forge: Option<Forge>,
+
+    /// Set the API token for --forge
+    #[arg(long, requires = "forge")]
+    pub forge_token: Option<String>,
+
+    /// Set the API base URL for --forge (GitHub Enterprise, self-hosted GitLab)
+    #[arg(long, requires = "forge")]
+    pub forge_api_url: Option<String>,
+
+    /// Set the base branch `sage pr` compares against (e.g. main)
+    #[arg(long)]
+    pub pr_base: Option<String>,
+
     /// Set maximum tokens for responses
     #[arg(long)]
     pub max_tokens: Option<usize>,
@@ -157,7 +690,7 @@ pub struct ConfigArgs {
     #[arg(short, long)]
     pub wizard: bool,
 
-    /// Set preference: auto-push, auto-stage-all, show-diff, skip-confirmation, verbose
+    /// Set preference: auto-push, auto-stage-all, show-diff, skip-confirmation, verbose, signoff, sign-commits, no-verify
     #[arg(long)]
     pub set_pref: Option<String>,
 
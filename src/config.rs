@@ -3,16 +3,60 @@ use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::process::Command;
 use colored::Colorize;
 
+use crate::cli::{CommitStyle, Forge, Task};
 use crate::error::{Result, SageError};
+use crate::git::BACKEND_NAMES;
 
 const CONFIG_FILE: &str = ".sage-config.json";
 
+/// Providers that work without an API key
+pub const KEYLESS_PROVIDERS: &[&str] = &["replay", "ollama"];
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ProviderConfig {
     pub api_key: String,
     pub model: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub fixtures_dir: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+}
+
+/// Provider/model override for one task; unset fields fall back to the active provider
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TaskRoute {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Proxy, TLS and timeout settings shared by all HTTP calls
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NetworkConfig {
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    #[serde(default)]
+    pub ca_certs: Vec<String>,
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+}
+
+/// API token and endpoint for a forge; unset fields fall back to the
+/// environment (GITHUB_TOKEN, GITLAB_TOKEN) and the remote's host
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ForgeConfig {
+    pub token: Option<String>,
+    pub api_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -23,6 +67,28 @@ pub struct Config {
     pub default_style: Option<String>,
     #[serde(default)]
     pub preferences: Preferences,
+    #[serde(default)]
+    pub task_routes: HashMap<String, TaskRoute>,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Commit author override, "Name <email>"
+    #[serde(default)]
+    pub commit_author: Option<String>,
+    /// "libgit2" or "cli"; unset uses libgit2 when available
+    #[serde(default)]
+    pub git_backend: Option<String>,
+    /// Branch `sage pr` compares against; unset uses the remote's default branch
+    #[serde(default)]
+    pub pr_base: Option<String>,
+    /// Forge settings keyed by forge name ("github", "gitlab")
+    #[serde(default)]
+    pub forges: HashMap<String, ForgeConfig>,
+    /// Directory to record AI request/response fixtures into (set per run, never saved)
+    #[serde(skip)]
+    pub record_dir: Option<String>,
+    /// Let the model call read-only repository tools (set per run, never saved)
+    #[serde(skip)]
+    pub use_tools: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -32,6 +98,12 @@ pub struct Preferences {
     pub show_diff: Option<bool>,
     pub skip_confirmation: Option<bool>,
     pub verbose: Option<bool>,
+    #[serde(default)]
+    pub signoff: Option<bool>,
+    #[serde(default)]
+    pub sign_commits: Option<bool>,
+    #[serde(default)]
+    pub no_verify: Option<bool>,
 }
 
 impl Default for Config {
@@ -45,30 +117,86 @@ impl Default for Config {
             max_tokens: Some(300),
             default_style: None,
             preferences: Preferences::default(),
+            task_routes: HashMap::new(),
+            network: NetworkConfig::default(),
+            commit_author: None,
+            git_backend: None,
+            pr_base: None,
+            forges: HashMap::new(),
+            record_dir: None,
+            use_tools: false,
         }
     }
 }
 
 impl Config {
-    pub fn get_active_provider_config(&self) -> Result<(&String, &ProviderConfig)> {
-        let provider = &self.active_provider;
+    pub fn get_provider_config(&self, provider: &str) -> Result<&ProviderConfig> {
         let config = self.providers.get(provider)
             .ok_or_else(|| SageError::ConfigProviderNotFound {
-                provider: provider.clone()
+                provider: provider.to_string()
             })?;
 
-        if config.api_key.is_empty() {
+        if config.api_key.is_empty() && !KEYLESS_PROVIDERS.contains(&provider) {
             return Err(SageError::ConfigApiKeyNotSet {
-                provider: provider.clone()
+                provider: provider.to_string()
             });
         }
 
+        Ok(config)
+    }
+
+    /// Commit style saved with `sage config --wizard`, if any
+    pub fn default_commit_style(&self) -> Option<CommitStyle> {
+        match self.default_style.as_deref()? {
+            "standard" => Some(CommitStyle::Standard),
+            "detailed" => Some(CommitStyle::Detailed),
+            "short" => Some(CommitStyle::Short),
+            _ => None,
+        }
+    }
+
+    /// Provider name and settings to use for a task, applying its route if one is configured
+    pub fn get_task_provider_config(&self, task: Task) -> Result<(String, ProviderConfig)> {
+        let route = self.task_routes.get(task.as_str()).cloned().unwrap_or_default();
+        let provider = route.provider.unwrap_or_else(|| self.active_provider.clone());
+
+        let mut config = self.get_provider_config(&provider)?.clone();
+        if route.model.is_some() {
+            config.model = route.model;
+        }
+
         Ok((provider, config))
     }
 
+    pub fn set_task_route(&mut self, task: Task, provider: Option<String>, model: Option<String>) -> Result<()> {
+        if let Some(p) = &provider {
+            if !self.providers.contains_key(p) {
+                return Err(SageError::ConfigProviderNotConfigured {
+                    provider: p.clone()
+                });
+            }
+        }
+
+        let route = self.task_routes.entry(task.as_str().to_string())
+            .or_default();
+
+        if provider.is_some() {
+            route.provider = provider;
+        }
+        if model.is_some() {
+            route.model = model;
+        }
+        Ok(())
+    }
+
+    pub fn clear_task_route(&mut self, task: Task) -> Result<()> {
+        self.task_routes.remove(task.as_str());
+        Ok(())
+    }
+
     pub fn set_provider(&mut self, provider: &str, api_key: Option<String>, model: Option<String>) -> Result<()> {
         let config = self.providers.entry(provider.to_string())
-            .or_insert_with(ProviderConfig::default);
+            .or_default();
 
         if let Some(key) = api_key {
             config.api_key = key;
@@ -82,19 +210,174 @@ impl Config {
         Ok(())
     }
 
+    pub fn set_model(&mut self, provider: &str, model: &str) -> Result<()> {
+        let config = self.providers.get_mut(provider)
+            .ok_or_else(|| SageError::ConfigProviderNotConfigured {
+                provider: provider.to_string()
+            })?;
+
+        config.model = Some(model.to_string());
+        Ok(())
+    }
+
+    pub fn set_base_url(&mut self, provider: &str, url: &str) -> Result<()> {
+        let config = self.providers.entry(provider.to_string())
+            .or_default();
+
+        config.base_url = Some(url.to_string());
+        Ok(())
+    }
+
+    pub fn set_sampling(
+        &mut self,
+        provider: &str,
+        temperature: Option<f32>,
+        top_p: Option<f32>,
+        seed: Option<u64>,
+        stop: &[String],
+    ) -> Result<()> {
+        if let Some(t) = temperature {
+            if !(0.0..=2.0).contains(&t) {
+                return Err(SageError::InvalidInput(format!("Temperature must be between 0 and 2, got {}", t)));
+            }
+        }
+        if let Some(p) = top_p {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(SageError::InvalidInput(format!("top_p must be between 0 and 1, got {}", p)));
+            }
+        }
+
+        let config = self.providers.entry(provider.to_string())
+            .or_default();
+
+        if temperature.is_some() {
+            config.temperature = temperature;
+        }
+        if top_p.is_some() {
+            config.top_p = top_p;
+        }
+        if seed.is_some() {
+            config.seed = seed;
+        }
+        if !stop.is_empty() {
+            config.stop = Some(stop.to_vec());
+        }
+        Ok(())
+    }
+
     pub fn update_key(&mut self, provider: &str, api_key: &str) -> Result<()> {
         let config = self.providers.entry(provider.to_string())
-            .or_insert_with(ProviderConfig::default);
+            .or_default();
 
         config.api_key = api_key.to_string();
         Ok(())
     }
 
+    pub fn set_fixtures_dir(&mut self, dir: &str) -> Result<()> {
+        let config = self.providers.entry("replay".to_string())
+            .or_default();
+
+        config.fixtures_dir = Some(dir.to_string());
+        Ok(())
+    }
+
+    pub fn set_network(
+        &mut self,
+        proxy: Option<String>,
+        no_proxy: Option<String>,
+        ca_certs: &[String],
+        connect_timeout_secs: Option<u64>,
+        read_timeout_secs: Option<u64>,
+    ) -> Result<()> {
+        for cert in ca_certs {
+            if !Path::new(cert).is_file() {
+                return Err(SageError::InvalidInput(format!("CA certificate not found: {}", cert)));
+            }
+        }
+
+        if proxy.is_some() {
+            self.network.proxy = proxy;
+        }
+        if no_proxy.is_some() {
+            self.network.no_proxy = no_proxy;
+        }
+        for cert in ca_certs {
+            if !self.network.ca_certs.contains(cert) {
+                self.network.ca_certs.push(cert.clone());
+            }
+        }
+        if connect_timeout_secs.is_some() {
+            self.network.connect_timeout_secs = connect_timeout_secs;
+        }
+        if read_timeout_secs.is_some() {
+            self.network.read_timeout_secs = read_timeout_secs;
+        }
+        Ok(())
+    }
+
+    pub fn set_commit_author(&mut self, author: &str) -> Result<()> {
+        if !(author.contains('<') && author.ends_with('>')) {
+            return Err(SageError::InvalidInput(
+                format!("Invalid author '{}'. Use the form \"Name <email>\"", author)
+            ));
+        }
+        self.commit_author = Some(author.to_string());
+        Ok(())
+    }
+
+    pub fn set_git_backend(&mut self, backend: &str) -> Result<()> {
+        if !BACKEND_NAMES.contains(&backend) {
+            return Err(SageError::InvalidInput(format!(
+                "Unknown git backend '{}'. Use one of: {}", backend, BACKEND_NAMES.join(", ")
+            )));
+        }
+        self.git_backend = Some(backend.to_string());
+        Ok(())
+    }
+
+    pub fn set_pr_base(&mut self, base: &str) -> Result<()> {
+        if base.is_empty() || base.starts_with('-') || base.contains(char::is_whitespace) {
+            return Err(SageError::InvalidInput(format!("Invalid base branch: '{}'", base)));
+        }
+        self.pr_base = Some(base.to_string());
+        Ok(())
+    }
+
+    pub fn set_forge(&mut self, forge: Forge, token: Option<String>, api_url: Option<String>) -> Result<()> {
+        if let Some(url) = &api_url {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err(SageError::InvalidInput(format!("Invalid API URL '{}': use http:// or https://", url)));
+            }
+        }
+
+        let config = self.forges.entry(forge.as_str().to_string())
+            .or_default();
+
+        if token.is_some() {
+            config.token = token;
+        }
+        if api_url.is_some() {
+            config.api_url = api_url;
+        }
+        Ok(())
+    }
+
     pub fn set_max_tokens(&mut self, tokens: usize) -> Result<()> {
         self.max_tokens = Some(tokens);
         Ok(())
     }
 
+    /// Copy of the config whose token limit is at least `min_tokens`
+    ///
+    /// `max_tokens` is sized for commit messages. Pull request descriptions,
+    /// release summaries, explanations and review JSON run longer and would be
+    /// cut off, so those commands raise the limit for their own calls.
+    pub fn with_min_tokens(&self, min_tokens: usize) -> Config {
+        let mut config = self.clone();
+        config.max_tokens = Some(config.max_tokens.unwrap_or(0).max(min_tokens));
+        config
+    }
+
     pub fn set_preference(&mut self, key: &str, value: bool) -> Result<()> {
         match key {
             "auto_push" => self.preferences.auto_push = Some(value),
@@ -102,11 +385,15 @@ impl Config {
             "show_diff" => self.preferences.show_diff = Some(value),
             "skip_confirmation" => self.preferences.skip_confirmation = Some(value),
             "verbose" => self.preferences.verbose = Some(value),
+            "signoff" => self.preferences.signoff = Some(value),
+            "sign_commits" => self.preferences.sign_commits = Some(value),
+            "no_verify" => self.preferences.no_verify = Some(value),
             _ => return Err(SageError::InvalidInput(format!("Unknown preference: {}", key))),
         }
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub fn set_default_style(&mut self, style: &str) -> Result<()> {
         match style {
             "standard" | "conventional" | "detailed" | "short" => {
@@ -141,12 +428,80 @@ impl Config {
             } else {
                 println!("    Model: Default");
             }
+            if let Some(url) = &provider_config.base_url {
+                println!("    Base URL: {}", url);
+            }
+            if let Some(dir) = &provider_config.fixtures_dir {
+                println!("    Fixtures: {}", dir);
+            }
+            if let Some(temperature) = provider_config.temperature {
+                println!("    Temperature: {}", temperature);
+            }
+            if let Some(top_p) = provider_config.top_p {
+                println!("    Top p: {}", top_p);
+            }
+            if let Some(seed) = provider_config.seed {
+                println!("    Seed: {}", seed);
+            }
+            if let Some(stop) = &provider_config.stop {
+                println!("    Stop sequences: {:?}", stop);
+            }
+        }
+
+        if !self.task_routes.is_empty() {
+            println!("\n  Task routes:");
+            let mut tasks: Vec<&String> = self.task_routes.keys().collect();
+            tasks.sort();
+            for task in tasks {
+                let route = &self.task_routes[task];
+                println!("    {}: {} / {}",
+                         task,
+                         route.provider.as_deref().unwrap_or("active provider"),
+                         route.model.as_deref().unwrap_or("provider model")
+                );
+            }
         }
 
         if let Some(style) = &self.default_style {
             println!("\n  Default commit style: {}", style);
         }
 
+        if let Some(proxy) = &self.network.proxy {
+            println!("  Proxy: {}", proxy);
+        }
+        if let Some(no_proxy) = &self.network.no_proxy {
+            println!("  No proxy: {}", no_proxy);
+        }
+        for cert in &self.network.ca_certs {
+            println!("  CA certificate: {}", cert);
+        }
+        if let Some(secs) = self.network.connect_timeout_secs {
+            println!("  Connect timeout: {}s", secs);
+        }
+        if let Some(secs) = self.network.read_timeout_secs {
+            println!("  Read timeout: {}s", secs);
+        }
+
+        if let Some(author) = &self.commit_author {
+            println!("  Commit author: {}", author);
+        }
+        if let Some(backend) = &self.git_backend {
+            println!("  Git backend: {}", backend);
+        }
+        if let Some(base) = &self.pr_base {
+            println!("  PR base branch: {}", base);
+        }
+        let mut forges: Vec<&String> = self.forges.keys().collect();
+        forges.sort();
+        for forge in forges {
+            let forge_config = &self.forges[forge];
+            println!("  Forge {}: token {}{}",
+                     forge,
+                     if forge_config.token.is_some() { "set (hidden)".green().to_string() } else { "not set".yellow().to_string() },
+                     forge_config.api_url.as_deref().map(|url| format!(", API {}", url)).unwrap_or_default()
+            );
+        }
+
         println!("  Max tokens: {}", self.max_tokens.unwrap_or(300));
 
         println!("\nPreferences:");
@@ -155,6 +510,9 @@ impl Config {
         println!("  Show diff: {}", format_bool_pref(self.preferences.show_diff));
         println!("  Skip confirmation: {}", format_bool_pref(self.preferences.skip_confirmation));
         println!("  Verbose: {}", format_bool_pref(self.preferences.verbose));
+        println!("  Sign-off: {}", format_bool_pref(self.preferences.signoff));
+        println!("  Sign commits: {}", format_bool_pref(self.preferences.sign_commits));
+        println!("  Skip hooks (no-verify): {}", format_bool_pref(self.preferences.no_verify));
     }
 }
 
@@ -166,6 +524,42 @@ fn format_bool_pref(value: Option<bool>) -> String {
     }
 }
 
+/// Editor used to edit generated messages: $EDITOR, falling back to vim
+pub fn resolve_editor() -> String {
+    env::var("EDITOR").unwrap_or_else(|_| "vim".to_string())
+}
+
+/// Command that starts the editor; the setting may carry arguments, as in
+/// "code --wait", so it is split on whitespace. Callers add the file path.
+pub fn editor_command() -> Command {
+    let editor = resolve_editor();
+    let mut parts = editor.split_whitespace();
+    let mut command = Command::new(parts.next().unwrap_or_default());
+    command.args(parts);
+    command
+}
+
+/// Open `text` in the editor and return the saved result. The temp file is
+/// private (mode 0600, unpredictable name) and removed on drop; `suffix` lets
+/// the editor pick a syntax, e.g. ".md".
+pub fn edit_text(prefix: &str, suffix: &str, text: &str) -> Result<String> {
+    let temp_file = tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(suffix)
+        .tempfile()?;
+    fs::write(temp_file.path(), text)?;
+
+    let status = editor_command()
+        .arg(temp_file.path())
+        .status()?;
+
+    if !status.success() {
+        return Err(SageError::EditorFailed);
+    }
+
+    Ok(fs::read_to_string(temp_file.path())?)
+}
+
 pub fn get_config_path() -> Result<String> {
     let home_dir = env::var("HOME").map_err(|_| SageError::ConfigHomeDirNotFound)?;
     Ok(Path::new(&home_dir).join(CONFIG_FILE).to_string_lossy().to_string())
@@ -188,3 +582,72 @@ pub fn save_config(config: &Config, config_path: &str) -> Result<()> {
     fs::write(config_path, config_json)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_providers() -> Config {
+        let mut config = Config::default();
+        config.set_provider("claude", Some("sk-ant".to_string()), Some("claude-3-opus-20240229".to_string())).unwrap();
+        config.set_provider("openai", Some("sk-openai".to_string()), None).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_task_without_route_uses_active_provider() {
+        let config = config_with_providers();
+        let (provider, provider_config) = config.get_task_provider_config(Task::Commit).unwrap();
+        assert_eq!(provider, "openai");
+        assert_eq!(provider_config.model, None);
+    }
+
+    #[test]
+    fn test_task_route_overrides_provider_and_model() {
+        let mut config = config_with_providers();
+        config.set_task_route(Task::Branch, Some("claude".to_string()), Some("claude-3-haiku-20240307".to_string())).unwrap();
+
+        let (provider, provider_config) = config.get_task_provider_config(Task::Branch).unwrap();
+        assert_eq!(provider, "claude");
+        assert_eq!(provider_config.model.as_deref(), Some("claude-3-haiku-20240307"));
+
+        // Model-only routes stay on the active provider
+        config.set_task_route(Task::Summarize, None, Some("gpt-4o-mini".to_string())).unwrap();
+        let (provider, provider_config) = config.get_task_provider_config(Task::Summarize).unwrap();
+        assert_eq!(provider, "openai");
+        assert_eq!(provider_config.model.as_deref(), Some("gpt-4o-mini"));
+
+        config.clear_task_route(Task::Branch).unwrap();
+        assert_eq!(config.get_task_provider_config(Task::Branch).unwrap().0, "openai");
+    }
+
+    #[test]
+    fn test_task_route_requires_configured_provider() {
+        let mut config = config_with_providers();
+        assert!(config.set_task_route(Task::Review, Some("missing".to_string()), None).is_err());
+    }
+
+    #[test]
+    fn test_with_min_tokens_only_raises_the_limit() {
+        let mut config = Config::default();
+        assert_eq!(config.with_min_tokens(1024).max_tokens, Some(1024));
+        config.max_tokens = Some(4096);
+        assert_eq!(config.with_min_tokens(1024).max_tokens, Some(4096));
+        config.max_tokens = None;
+        assert_eq!(config.with_min_tokens(1024).max_tokens, Some(1024));
+    }
+
+    #[test]
+    fn test_editor_command_splits_arguments() {
+        env::set_var("EDITOR", "code --wait");
+        let command = editor_command();
+        assert_eq!(command.get_program(), "code");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), vec!["--wait"]);
+
+        // Same test, since both depend on EDITOR
+        env::set_var("EDITOR", "sed -i s/draft/final/");
+        assert_eq!(edit_text("sage-test-", ".txt", "draft message\n").unwrap(), "final message\n");
+        env::set_var("EDITOR", "false");
+        assert!(matches!(edit_text("sage-test-", "", "text"), Err(SageError::EditorFailed)));
+    }
+}
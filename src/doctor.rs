@@ -0,0 +1,312 @@
+//! Diagnostics for `sage doctor`
+//!
+//! Each check reports pass, warn or fail. Failures reuse the `SageError`
+//! messages so the remediation tips match what the failing command would print.
+
+use std::env;
+use std::path::Path;
+use colored::Colorize;
+
+use crate::ai::{default_model, list_models};
+use crate::config::{editor_command, get_config_path, load_config, resolve_editor, Config, KEYLESS_PROVIDERS};
+use crate::error::{Result, SageError};
+use crate::hook::installed_hooks;
+use crate::git::{backend, get_config_value, get_git_version, get_hooks_dir, get_upstream_branch};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Check { name: name.to_string(), status: Status::Pass, detail: detail.into() }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>) -> Self {
+        Check { name: name.to_string(), status: Status::Warn, detail: detail.into() }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>) -> Self {
+        Check { name: name.to_string(), status: Status::Fail, detail: detail.into() }
+    }
+
+    fn print(&self) {
+        let marker = match self.status {
+            Status::Pass => "✓ pass".green(),
+            Status::Warn => "! warn".yellow(),
+            Status::Fail => "✗ fail".red(),
+        };
+
+        let mut lines = self.detail.lines();
+        println!("{}  {}: {}", marker, self.name.bold(), lines.next().unwrap_or_default());
+        for line in lines {
+            if line.is_empty() {
+                println!();
+            } else {
+                println!("        {}", line);
+            }
+        }
+    }
+}
+
+pub async fn run_doctor() -> Result<()> {
+    println!("{}", "Running sage diagnostics...".blue().bold());
+    println!();
+
+    let mut checks = Vec::new();
+
+    let config = check_config(&mut checks);
+    if let Some(config) = &config {
+        check_providers(config, &mut checks).await;
+    }
+
+    check_git(&mut checks);
+    check_editor(&mut checks);
+
+    let failures = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warnings = checks.iter().filter(|c| c.status == Status::Warn).count();
+
+    println!();
+    println!("{} passed, {} warning(s), {} failed",
+        checks.len() - failures - warnings, warnings, failures);
+
+    if failures > 0 {
+        return Err(SageError::DoctorChecksFailed { failures });
+    }
+
+    Ok(())
+}
+
+fn record(checks: &mut Vec<Check>, check: Check) {
+    check.print();
+    checks.push(check);
+}
+
+fn check_config(checks: &mut Vec<Check>) -> Option<Config> {
+    let config_path = match get_config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            record(checks, Check::fail("Config file", e.to_string()));
+            return None;
+        }
+    };
+
+    if !Path::new(&config_path).exists() {
+        record(checks, Check::warn("Config file",
+            format!("{} does not exist, using defaults\n\nTip: Run 'sage config -w' to create it", config_path)));
+        return Some(Config::default());
+    }
+
+    let config = match load_config(&config_path) {
+        Ok(config) => {
+            record(checks, Check::pass("Config file", format!("{} parsed", config_path)));
+            config
+        },
+        Err(e) => {
+            record(checks, Check::fail("Config file", e.to_string()));
+            return None;
+        }
+    };
+
+    check_config_permissions(&config_path, checks);
+    Some(config)
+}
+
+#[cfg(unix)]
+fn check_config_permissions(config_path: &str, checks: &mut Vec<Check>) {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(config_path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o077 != 0 => {
+            record(checks, Check::warn("Config permissions",
+                format!("{} is readable by other users and contains API keys\n\nTip: Run 'chmod 600 {}'", config_path, config_path)));
+        },
+        Ok(_) => record(checks, Check::pass("Config permissions", "Only readable by you")),
+        Err(e) => record(checks, Check::fail("Config permissions", SageError::from(e).to_string())),
+    }
+}
+
+#[cfg(not(unix))]
+fn check_config_permissions(_config_path: &str, _checks: &mut Vec<Check>) {}
+
+async fn check_providers(config: &Config, checks: &mut Vec<Check>) {
+    if !config.providers.contains_key(&config.active_provider) {
+        record(checks, Check::fail("Active provider", SageError::ConfigProviderNotFound {
+            provider: config.active_provider.clone()
+        }.to_string()));
+    }
+
+    let mut names: Vec<&String> = config.providers.keys().collect();
+    names.sort();
+
+    for name in names {
+        let provider_config = &config.providers[name];
+        let label = format!("Provider {}", name);
+
+        // Providers without a model listing endpoint (e.g. replay) have nothing to ping
+        let Some(fallback_model) = default_model(name) else {
+            record(checks, Check::pass(&label, "No network access required"));
+            continue;
+        };
+
+        if provider_config.api_key.is_empty() && !KEYLESS_PROVIDERS.contains(&name.as_str()) {
+            let status = if name == &config.active_provider { Check::fail } else { Check::warn };
+            record(checks, status(&label, SageError::ConfigApiKeyNotSet {
+                provider: name.clone()
+            }.to_string()));
+            continue;
+        }
+
+        let models = match list_models(config, name).await {
+            Ok(models) => {
+                let detail = if provider_config.api_key.is_empty() { "Server reachable" } else { "API key accepted" };
+                record(checks, Check::pass(&label, detail));
+                models
+            },
+            Err(e) => {
+                record(checks, Check::fail(&label, e.to_string()));
+                continue;
+            }
+        };
+
+        let model = provider_config.model.as_deref().unwrap_or(fallback_model);
+        record(checks, model_check(name, model, &models));
+    }
+}
+
+/// Warn when `model` is not among the models the provider offers
+fn model_check(provider: &str, model: &str, models: &[String]) -> Check {
+    let name = format!("Model {}", provider);
+    if models.iter().any(|m| m == model) {
+        Check::pass(&name, model.to_string())
+    } else {
+        Check::warn(&name, format!(
+            "'{}' is not offered by {}\n\nTip: Run 'sage models -p {} --select' to pick a valid model", model, provider, provider
+        ))
+    }
+}
+
+fn check_git(checks: &mut Vec<Check>) {
+    match get_git_version() {
+        Ok(version) => record(checks, Check::pass("Git", format!("version {} ({} backend)", version, backend().name()))),
+        Err(e) => {
+            record(checks, Check::fail("Git", e.to_string()));
+            return;
+        }
+    }
+
+    for key in ["user.name", "user.email"] {
+        record(checks, identity_check(key, get_config_value(key).ok().flatten()));
+    }
+
+    record(checks, upstream_check(get_upstream_branch().ok().flatten()));
+
+    check_hooks(checks);
+}
+
+/// Fail without a commit identity: git refuses to commit
+fn identity_check(key: &str, value: Option<String>) -> Check {
+    match value {
+        Some(value) => Check::pass(key, value),
+        None => Check::fail(key, SageError::GitIdentityNotSet { key: key.to_string() }.to_string()),
+    }
+}
+
+/// Warn without an upstream: only pushing needs one
+fn upstream_check(upstream: Option<String>) -> Check {
+    match upstream {
+        Some(upstream) => Check::pass("Upstream", upstream),
+        None => Check::warn("Upstream", SageError::GitNoUpstream.to_string()),
+    }
+}
+
+fn check_hooks(checks: &mut Vec<Check>) {
+    let hooks_dir = match get_hooks_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            record(checks, Check::warn("Git hooks", e.to_string()));
+            return;
+        }
+    };
+
+    record(checks, hooks_check(&hooks_dir));
+}
+
+fn hooks_check(hooks_dir: &Path) -> Check {
+    let installed: Vec<&str> = installed_hooks(hooks_dir)
+        .iter()
+        .map(|kind| kind.file_name())
+        .collect();
+
+    if installed.is_empty() {
+        Check::warn("Git hooks", format!("No sage hooks installed in {}", hooks_dir.display()))
+    } else {
+        Check::pass("Git hooks", format!("Installed: {}", installed.join(", ")))
+    }
+}
+
+fn check_editor(checks: &mut Vec<Check>) {
+    // Check the program exactly as the edit prompts will start it
+    let command = editor_command();
+    let program = command.get_program().to_string_lossy();
+
+    if is_executable_on_path(&program) {
+        record(checks, Check::pass("Editor", resolve_editor()));
+    } else {
+        record(checks, Check::fail("Editor",
+            format!("'{}' not found\n\n{}", program, SageError::EditorFailed)));
+    }
+}
+
+fn is_executable_on_path(program: &str) -> bool {
+    if program.is_empty() {
+        return false;
+    }
+
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_classification() {
+        assert_eq!(identity_check("user.name", Some("Jane Doe".to_string())).status, Status::Pass);
+        let check = identity_check("user.email", None);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("git config --global user.email"));
+
+        assert_eq!(upstream_check(Some("origin/main".to_string())).status, Status::Pass);
+        assert_eq!(upstream_check(None).status, Status::Warn);
+
+        let models = vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()];
+        assert_eq!(model_check("openai", "gpt-4o", &models).status, Status::Pass);
+        assert_eq!(model_check("openai", "gpt-9", &models).status, Status::Warn);
+    }
+
+    #[test]
+    fn test_hook_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(hooks_check(dir.path()).status, Status::Warn);
+
+        // A hook that merely mentions sage was not installed by it
+        std::fs::write(dir.path().join("commit-msg"), "#!/bin/sh\nsage lint --file \"$1\"\n").unwrap();
+        assert_eq!(hooks_check(dir.path()).status, Status::Warn);
+    }
+}
@@ -11,6 +11,9 @@ pub enum SageError {
     GitCommitFailed(String),
     GitPushFailed(String),
     GitDiffFailed(String),
+    GitCommandFailed(String),
+    GitIdentityNotSet { key: String },
+    GitNoUpstream,
 
     // Configuration errors
     ConfigInvalidJson(String),
@@ -22,9 +25,16 @@ pub enum SageError {
     // API errors
     ApiNetworkError { provider: String, details: String },
     ApiAuthError { provider: String },
+    ApiTimeout { provider: String, details: String },
     ApiResponseError { provider: String, details: String },
     ApiNoResponse { provider: String },
     ApiUnsupportedProvider { provider: String },
+    ApiReplayMiss { key: String, dir: String },
+    ApiContextLengthExceeded { provider: String, details: String },
+    ApiModelNotFound { provider: String, model: String },
+    ApiQuotaExceeded { provider: String, details: String },
+    ApiOverloaded { provider: String },
+    ApiContentFiltered { provider: String, details: String },
 
     // I/O errors
     IoError(String),
@@ -34,6 +44,31 @@ pub enum SageError {
 
     // User input errors
     InvalidInput(String),
+
+    // Diagnostics errors
+    DoctorChecksFailed { failures: usize },
+
+    // Hook errors
+    HookNotInstalled { hook: String },
+
+    // Lint errors
+    LintFailed { errors: usize },
+
+    // Split errors
+    SplitPlanInvalid(String),
+    SplitFailed { details: String },
+    SplitRollbackFailed { details: String, head: String, backup: String },
+
+    // History rewriting errors
+    RewritePublished { upstream: String },
+
+    // Forge errors
+    ForgeUnknown { url: String },
+    ForgeTokenNotSet { forge: String, env: String },
+    ForgeAuthFailed { forge: String },
+
+    // Review errors
+    ReviewInvalid(String),
 }
 
 impl fmt::Display for SageError {
@@ -58,6 +93,15 @@ impl fmt::Display for SageError {
             SageError::GitDiffFailed(details) => {
                 write!(f, "Failed to get git diff: {}", details)
             },
+            SageError::GitCommandFailed(details) => {
+                write!(f, "Git command failed: {}\n\nTip: Check that git is installed and on your PATH", details)
+            },
+            SageError::GitIdentityNotSet { key } => {
+                write!(f, "{} is not set, so git cannot record the commit author\n\nTip: Run 'git config --global {} <value>'", key, key)
+            },
+            SageError::GitNoUpstream => {
+                write!(f, "The current branch has no upstream, so 'sage -p' cannot push it\n\nTip: Run 'git push -u origin <branch>' once to set it")
+            },
 
             // Config errors
             SageError::ConfigInvalidJson(details) => {
@@ -80,6 +124,9 @@ impl fmt::Display for SageError {
             SageError::ApiNetworkError { provider, details } => {
                 write!(f, "Network error connecting to {}: {}\n\nTip: Check your internet connection and API endpoint availability", provider, details)
             },
+            SageError::ApiTimeout { provider, details } => {
+                write!(f, "Request to {} timed out: {}\n\nTip: Raise network.read_timeout_secs with 'sage config --read-timeout <secs>' or check your proxy settings", provider, details)
+            },
             SageError::ApiAuthError { provider } => {
                 write!(f, "Authentication failed for {}\n\nTip: Verify your API key with 'sage config -s' and update if needed", provider)
             },
@@ -90,7 +137,25 @@ impl fmt::Display for SageError {
                 write!(f, "No response from {} API\n\nTip: The API may be experiencing issues. Try again later", provider)
             },
             SageError::ApiUnsupportedProvider { provider } => {
-                write!(f, "Unsupported provider: {}\n\nTip: Supported providers are: openai, claude", provider)
+                write!(f, "Unsupported provider: {}\n\nTip: Supported providers are: openai, claude, ollama, replay", provider)
+            },
+            SageError::ApiContextLengthExceeded { provider, details } => {
+                write!(f, "Request too large for the {} model's context window: {}\n\nTip: Stage fewer files or split the change into smaller commits", provider, details)
+            },
+            SageError::ApiModelNotFound { provider, model } => {
+                write!(f, "Model '{}' is not available on {}\n\nTip: Run 'sage models -p {} --select' to choose an available model", model, provider, provider.to_lowercase())
+            },
+            SageError::ApiQuotaExceeded { provider, details } => {
+                write!(f, "{} quota exhausted: {}\n\nTip: Check your plan and billing details, or switch providers with 'sage use <provider>'", provider, details)
+            },
+            SageError::ApiOverloaded { provider } => {
+                write!(f, "{} is temporarily overloaded\n\nTip: Wait a moment and try again", provider)
+            },
+            SageError::ApiContentFiltered { provider, details } => {
+                write!(f, "{} content filter blocked the request: {}\n\nTip: Check the diff for secrets or sensitive content and unstage those files", provider, details)
+            },
+            SageError::ApiReplayMiss { key, dir } => {
+                write!(f, "No recorded response for this prompt (fixture {}.json not found in {})\n\nTip: Record it first with 'sage --record {}' using a live provider", key, dir, dir)
             },
 
             // I/O errors
@@ -107,6 +172,53 @@ impl fmt::Display for SageError {
             SageError::InvalidInput(details) => {
                 write!(f, "Invalid input: {}", details)
             },
+
+            // Diagnostics errors
+            SageError::DoctorChecksFailed { failures } => {
+                write!(f, "{} diagnostic check(s) failed\n\nTip: Fix the issues marked above and run 'sage doctor' again", failures)
+            },
+
+            // Hook errors
+            SageError::HookNotInstalled { hook } => {
+                write!(f, "No sage {} hook is installed\n\nTip: Install it with 'sage hook install {}'", hook, hook)
+            },
+
+            // Lint errors
+            SageError::LintFailed { errors } => {
+                write!(f, "{} commit message lint error(s)\n\nTip: Fix the messages above or run 'sage lint --fix' for a suggested rewrite", errors)
+            },
+
+            // Split errors
+            SageError::SplitPlanInvalid(details) => {
+                write!(f, "Invalid split plan: {}\n\nTip: Every change number must belong to exactly one commit; run 'sage split' again for a new plan", details)
+            },
+            SageError::SplitFailed { details } => {
+                write!(f, "Split failed: {}\nThe branch and staged changes were restored.\n\nTip: Commit the changes with 'sage', or stage them in parts with 'git add -p'", details)
+            },
+            SageError::SplitRollbackFailed { details, head, backup } => {
+                write!(f, "Split failed and could not be rolled back: {}\n\nTip: Restore the original state with 'git reset {}' and 'git apply --cached {}'", details, head, backup)
+            },
+
+            // History rewriting errors
+            SageError::RewritePublished { upstream } => {
+                write!(f, "Some of these commits are already on {}\n\nTip: Rewriting published history breaks other clones; pass --force to do it anyway", upstream)
+            },
+
+            // Forge errors
+            SageError::ForgeUnknown { url } => {
+                write!(f, "Could not tell which forge hosts {}\n\nTip: Pass --forge github or --forge gitlab", url)
+            },
+            SageError::ForgeTokenNotSet { forge, env } => {
+                write!(f, "No API token set for {}\n\nTip: Run 'sage config --forge {} --forge-token <token>' or set {}", forge, forge.to_lowercase(), env)
+            },
+            SageError::ForgeAuthFailed { forge } => {
+                write!(f, "{} rejected the API token\n\nTip: Check that the token is valid and can create pull requests, then update it with 'sage config --forge {} --forge-token <token>'", forge, forge.to_lowercase())
+            },
+
+            // Review errors
+            SageError::ReviewInvalid(details) => {
+                write!(f, "Invalid review: {}\n\nTip: Run the review again, or route the review task to a stronger model with 'sage config --task review --model <MODEL>'", details)
+            },
         }
     }
 }
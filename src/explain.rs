@@ -0,0 +1,148 @@
+//! Plain-language explanations of existing history (`sage explain`)
+//!
+//! A single commit is explained from its own diff against its first parent; a
+//! range from the combined diff of its commits. The commit messages, and
+//! optionally the changed files' contents, give the model the "why".
+
+use colored::Colorize;
+
+use crate::ai::call_ai_with_diff;
+use crate::ai::tools::truncate;
+use crate::cli::{ExplainArgs, Task};
+use crate::config::Config;
+use crate::error::{Result, SageError};
+use crate::git::{
+    get_commit_diff, get_commits, get_merge_base, get_range_diff, read_commit, show_file_at,
+    ChangeKind, CommitInfo, Diff,
+};
+use crate::progress::spinner;
+use crate::prompts;
+
+/// Changed files whose contents are sent with `--files`
+const MAX_CONTEXT_FILES: usize = 5;
+
+/// Bytes of each file sent with `--files`
+const MAX_FILE_BYTES: usize = 6_000;
+
+/// Smallest token limit for explanations
+const MIN_EXPLAIN_TOKENS: usize = 1024;
+
+/// What is being explained
+struct Subject {
+    commits: Vec<CommitInfo>,
+    diff: Diff,
+    /// Revision the changed files are read at
+    to: String,
+}
+
+/// Split "a..b" or "a...b" into its ends (empty ends mean HEAD) and whether
+/// it is a symmetric range
+fn split_range(rev: &str) -> Option<(&str, &str, bool)> {
+    let (from, to, symmetric) = match rev.split_once("...") {
+        Some((from, to)) => (from, to, true),
+        None => {
+            let (from, to) = rev.split_once("..")?;
+            (from, to, false)
+        },
+    };
+    Some((or_head(from), or_head(to), symmetric))
+}
+
+fn or_head(rev: &str) -> &str {
+    if rev.is_empty() { "HEAD" } else { rev }
+}
+
+fn resolve(rev: &str) -> Result<Subject> {
+    let Some((from, to, symmetric)) = split_range(rev) else {
+        let commit = read_commit(rev)?;
+        return Ok(Subject {
+            diff: get_commit_diff(&commit.sha)?,
+            to: commit.sha.clone(),
+            commits: vec![CommitInfo { sha: commit.sha, message: commit.message.trim_end().to_string() }],
+        });
+    };
+
+    // "a...b" explains what b adds since it forked from a
+    let from = if symmetric { get_merge_base(from, to)? } else { from.to_string() };
+    let commits = get_commits(&format!("{}..{}", from, to))?;
+    if commits.is_empty() {
+        return Err(SageError::InvalidInput(format!("No commits in {}", rev)));
+    }
+
+    Ok(Subject {
+        diff: get_range_diff(&from, to)?,
+        to: to.to_string(),
+        commits,
+    })
+}
+
+/// Commit list for the prompt, oldest first
+fn format_commits(commits: &[CommitInfo]) -> String {
+    commits.iter()
+        .rev()
+        .map(|commit| {
+            let short = commit.sha.get(..7).unwrap_or(&commit.sha);
+            format!("{} {}", short, commit.message.trim().replace('\n', "\n    "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Contents of the most-changed files at `rev`, each cut to a budget
+fn file_context(diff: &Diff, rev: &str) -> String {
+    let mut files: Vec<_> = diff.files.iter()
+        .filter(|file| file.kind != ChangeKind::Deleted && !file.binary)
+        .collect();
+    files.sort_by_key(|file| std::cmp::Reverse(file.added + file.removed));
+
+    files.iter()
+        .take(MAX_CONTEXT_FILES)
+        .filter_map(|file| {
+            let contents = show_file_at(rev, file.path()).ok()?;
+            Some(format!("=== {} ===\n{}", file.path(), truncate(&contents, MAX_FILE_BYTES)))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// `sage explain`: print an explanation of a commit or range
+pub async fn run_explain(config: &Config, args: &ExplainArgs) -> Result<()> {
+    let subject = resolve(&args.rev)?;
+    let commits = format_commits(&subject.commits);
+    let files_changed = subject.diff.summary();
+    let files = args.files.then(|| file_context(&subject.diff, &subject.to));
+    let context = args.context.as_deref().unwrap_or("");
+
+    let config = config.with_min_tokens(MIN_EXPLAIN_TOKENS);
+
+    let count = subject.commits.len();
+    let spinner = spinner(format!("Explaining {} commit{} using AI...", count, if count == 1 { "" } else { "s" }));
+
+    let result = call_ai_with_diff(&config, Task::Summarize, &subject.diff, |truncated_diff| {
+        prompts::build_explain_prompt(context, &commits, &files_changed, files.as_deref(), truncated_diff)
+    }).await;
+
+    spinner.finish_and_clear();
+
+    let response = result?;
+    for commit in subject.commits.iter().rev() {
+        let short = commit.sha.get(..7).unwrap_or(&commit.sha);
+        println!("{} {}", short.yellow(), commit.message.lines().next().unwrap_or(""));
+    }
+    println!("\n{}", response.message.trim());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(split_range("HEAD~2"), None);
+        assert_eq!(split_range("v1.2.0..v1.3.0"), Some(("v1.2.0", "v1.3.0", false)));
+        assert_eq!(split_range("main..."), Some(("main", "HEAD", true)));
+        assert_eq!(split_range("..feature"), Some(("HEAD", "feature", false)));
+    }
+}
@@ -0,0 +1,297 @@
+//! Opening pull requests on GitHub and merge requests on GitLab (`sage pr --create`)
+//!
+//! The forge, host and repository come from the remote URL. An open pull
+//! request for the branch is updated in place; otherwise a new one is opened.
+
+use reqwest::{Client, RequestBuilder, Response};
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+
+use crate::cli::Forge;
+use crate::config::Config;
+use crate::error::{Result, SageError};
+use crate::http::{self, request_error};
+use crate::pr::PrText;
+
+const GITHUB_HOST: &str = "github.com";
+const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_API_VERSION: &str = "2022-11-28";
+
+/// A repository on a forge, parsed from a remote URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRepo {
+    pub host: String,
+    /// "owner/repo" on GitHub, "group/subgroup/project" on GitLab
+    pub path: String,
+}
+
+/// Parse `git@host:owner/repo.git`, `ssh://git@host:22/owner/repo.git` or
+/// `https://host/owner/repo.git`
+pub fn parse_remote_url(url: &str) -> Option<RemoteRepo> {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            (host.split(':').next()?, path)
+        },
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            (authority.rsplit('@').next()?, path)
+        },
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+
+    Some(RemoteRepo {
+        host: host.to_lowercase(),
+        path: path.to_string(),
+    })
+}
+
+/// Forge hosting `host`: an explicit choice, then the host name, then the
+/// only forge with settings
+fn detect_forge(host: &str, forge: Option<Forge>, config: &Config) -> Option<Forge> {
+    if forge.is_some() {
+        return forge;
+    }
+    if host.contains("github") {
+        return Some(Forge::GitHub);
+    }
+    if host.contains("gitlab") {
+        return Some(Forge::GitLab);
+    }
+
+    let mut configured = [Forge::GitHub, Forge::GitLab].into_iter()
+        .filter(|forge| config.forges.contains_key(forge.as_str()));
+    match (configured.next(), configured.next()) {
+        (Some(forge), None) => Some(forge),
+        _ => None,
+    }
+}
+
+/// REST API root: the configured URL, else the default for the host
+fn api_url(forge: Forge, host: &str, config: &Config) -> String {
+    if let Some(url) = config.forges.get(forge.as_str()).and_then(|forge| forge.api_url.as_deref()) {
+        return url.trim_end_matches('/').to_string();
+    }
+
+    match forge {
+        Forge::GitHub if host == GITHUB_HOST => GITHUB_API_URL.to_string(),
+        Forge::GitHub => format!("https://{}/api/v3", host),
+        Forge::GitLab => format!("https://{}/api/v4", host),
+    }
+}
+
+/// Environment variables checked for a token when none is configured
+fn token_env_vars(forge: Forge) -> &'static [&'static str] {
+    match forge {
+        Forge::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+        Forge::GitLab => &["GITLAB_TOKEN"],
+    }
+}
+
+fn token(forge: Forge, config: &Config) -> Result<String> {
+    config.forges.get(forge.as_str())
+        .and_then(|forge| forge.token.clone())
+        .or_else(|| token_env_vars(forge).iter().find_map(|var| env::var(var).ok()))
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| SageError::ForgeTokenNotSet {
+            forge: forge.name().to_string(),
+            env: token_env_vars(forge)[0].to_string(),
+        })
+}
+
+/// Where and how to open the pull request, resolved before any AI call so
+/// configuration problems show up early
+pub struct Target {
+    forge: Forge,
+    repo: RemoteRepo,
+    api_url: String,
+    token: String,
+}
+
+impl Target {
+    pub fn resolve(remote_url: &str, forge: Option<Forge>, config: &Config) -> Result<Target> {
+        let unknown = || SageError::ForgeUnknown { url: remote_url.to_string() };
+        let repo = parse_remote_url(remote_url).ok_or_else(unknown)?;
+        let forge = detect_forge(&repo.host, forge, config).ok_or_else(unknown)?;
+
+        Ok(Target {
+            forge,
+            api_url: api_url(forge, &repo.host, config),
+            token: token(forge, config)?,
+            repo,
+        })
+    }
+
+    pub fn forge(&self) -> Forge {
+        self.forge
+    }
+}
+
+/// What the forge calls a pull request
+pub fn request_name(forge: Forge) -> &'static str {
+    match forge {
+        Forge::GitHub => "pull request",
+        Forge::GitLab => "merge request",
+    }
+}
+
+/// The pull request that was opened or updated
+pub struct PullRequest {
+    pub url: String,
+    /// Whether an already open pull request was updated
+    pub updated: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubPull {
+    number: u64,
+    html_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitLabMergeRequest {
+    iid: u64,
+    web_url: String,
+}
+
+/// Send a request, turning auth failures and error statuses into errors
+async fn send(forge: Forge, request: RequestBuilder) -> Result<Response> {
+    let response = request.send()
+        .await
+        .map_err(|e| request_error(forge.name(), e))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    if status.as_u16() == 401 {
+        return Err(SageError::ForgeAuthFailed { forge: forge.name().to_string() });
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let details = serde_json::from_str::<serde_json::Value>(&body).ok()
+        .and_then(|value| value.get("message").map(|message| match message {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }))
+        .unwrap_or(body);
+
+    Err(SageError::ApiResponseError {
+        provider: forge.name().to_string(),
+        details: format!("{} {}", status.as_u16(), details.trim()),
+    })
+}
+
+async fn parse<T: for<'de> Deserialize<'de>>(forge: Forge, response: Response) -> Result<T> {
+    response.json().await.map_err(|e| SageError::ApiResponseError {
+        provider: forge.name().to_string(),
+        details: format!("Failed to parse response: {}", e),
+    })
+}
+
+async fn submit_github(client: &Client, target: &Target, head: &str, base: &str, pr: &PrText) -> Result<PullRequest> {
+    let repo_url = format!("{}/repos/{}", target.api_url, target.repo.path);
+    let owner = target.repo.path.split('/').next().unwrap_or_default();
+    let with_headers = |request: RequestBuilder| {
+        request
+            .bearer_auth(&target.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", GITHUB_API_VERSION)
+            .header("User-Agent", "sage")
+    };
+
+    let open = client.get(format!("{}/pulls", repo_url))
+        .query(&[("head", format!("{}:{}", owner, head)), ("state", "open".to_string())]);
+    let open: Vec<GitHubPull> = parse(Forge::GitHub, send(Forge::GitHub, with_headers(open)).await?).await?;
+
+    if let Some(existing) = open.first() {
+        let update = client.patch(format!("{}/pulls/{}", repo_url, existing.number))
+            .json(&json!({ "title": pr.title, "body": pr.body }));
+        let pull: GitHubPull = parse(Forge::GitHub, send(Forge::GitHub, with_headers(update)).await?).await?;
+        return Ok(PullRequest { url: pull.html_url, updated: true });
+    }
+
+    let create = client.post(format!("{}/pulls", repo_url))
+        .json(&json!({ "title": pr.title, "body": pr.body, "head": head, "base": base }));
+    let pull: GitHubPull = parse(Forge::GitHub, send(Forge::GitHub, with_headers(create)).await?).await?;
+    Ok(PullRequest { url: pull.html_url, updated: false })
+}
+
+async fn submit_gitlab(client: &Client, target: &Target, head: &str, base: &str, pr: &PrText) -> Result<PullRequest> {
+    // GitLab accepts the URL-encoded project path in place of its numeric ID
+    let project_url = format!("{}/projects/{}", target.api_url, target.repo.path.replace('/', "%2F"));
+    let with_headers = |request: RequestBuilder| request.header("PRIVATE-TOKEN", &target.token);
+
+    let open = client.get(format!("{}/merge_requests", project_url))
+        .query(&[("source_branch", head), ("state", "opened")]);
+    let open: Vec<GitLabMergeRequest> = parse(Forge::GitLab, send(Forge::GitLab, with_headers(open)).await?).await?;
+
+    if let Some(existing) = open.first() {
+        let update = client.put(format!("{}/merge_requests/{}", project_url, existing.iid))
+            .json(&json!({ "title": pr.title, "description": pr.body }));
+        let merge_request: GitLabMergeRequest = parse(Forge::GitLab, send(Forge::GitLab, with_headers(update)).await?).await?;
+        return Ok(PullRequest { url: merge_request.web_url, updated: true });
+    }
+
+    let create = client.post(format!("{}/merge_requests", project_url))
+        .json(&json!({
+            "title": pr.title,
+            "description": pr.body,
+            "source_branch": head,
+            "target_branch": base,
+        }));
+    let merge_request: GitLabMergeRequest = parse(Forge::GitLab, send(Forge::GitLab, with_headers(create)).await?).await?;
+    Ok(PullRequest { url: merge_request.web_url, updated: false })
+}
+
+/// Open a pull request from `head` into `base`, or update the open one for `head`
+pub async fn submit(config: &Config, target: &Target, head: &str, base: &str, pr: &PrText) -> Result<PullRequest> {
+    let client = http::client(&config.network)?;
+    match target.forge {
+        Forge::GitHub => submit_github(client, target, head, base, pr).await,
+        Forge::GitLab => submit_gitlab(client, target, head, base, pr).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ForgeConfig;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let expected = Some(RemoteRepo { host: "github.com".to_string(), path: "thanipro/sage".to_string() });
+        assert_eq!(parse_remote_url("git@github.com:thanipro/sage.git"), expected);
+        assert_eq!(parse_remote_url("https://github.com/thanipro/sage"), expected);
+        assert_eq!(parse_remote_url("ssh://git@GitHub.com:22/thanipro/sage.git/"), expected);
+        assert_eq!(parse_remote_url("https://token@gitlab.example.com/group/sub/project.git").unwrap().path, "group/sub/project");
+        assert_eq!(parse_remote_url("/srv/git/sage.git"), None);
+    }
+
+    #[test]
+    fn test_detect_forge_and_api_url() {
+        let mut config = Config::default();
+        assert_eq!(detect_forge("gitlab.com", None, &config), Some(Forge::GitLab));
+        assert_eq!(detect_forge("git.example.com", None, &config), None);
+        assert_eq!(detect_forge("git.example.com", Some(Forge::GitHub), &config), Some(Forge::GitHub));
+
+        assert_eq!(api_url(Forge::GitHub, "github.com", &config), GITHUB_API_URL);
+        assert_eq!(api_url(Forge::GitHub, "git.example.com", &config), "https://git.example.com/api/v3");
+        assert_eq!(api_url(Forge::GitLab, "gitlab.com", &config), "https://gitlab.com/api/v4");
+
+        config.forges.insert("gitlab".to_string(), ForgeConfig {
+            token: None,
+            api_url: Some("http://127.0.0.1:8080/api/v4/".to_string()),
+        });
+        assert_eq!(detect_forge("git.example.com", None, &config), Some(Forge::GitLab));
+        assert_eq!(api_url(Forge::GitLab, "git.example.com", &config), "http://127.0.0.1:8080/api/v4");
+    }
+}
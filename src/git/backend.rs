@@ -0,0 +1,150 @@
+//! Pluggable implementation of the git operations sage performs
+//!
+//! The libgit2 backend works in-process and is used whenever it is compiled in
+//! and the repository opens; the CLI backend shells out to `git` and is the
+//! fallback (or can be forced with `sage config --git-backend cli`).
+//!
+//! Every read or write of the index, history, refs and tags goes through the
+//! backend. Only questions about the setup (the git version, config values,
+//! upstream and default branch names, the repository root and paths inside
+//! `.git`) ask `git` directly.
+
+use std::sync::OnceLock;
+
+use crate::error::Result;
+use super::rewrite::{Ident, RawCommit};
+use super::{CommitInfo, CommitOptions, PushOptions, ResetMode};
+use super::cli::CliBackend;
+#[cfg(feature = "libgit2")]
+use super::libgit2::Git2Backend;
+
+/// Backends selectable through `sage config --git-backend`
+pub const BACKEND_NAMES: &[&str] = &["libgit2", "cli"];
+
+pub trait GitBackend: Send + Sync {
+    /// Short name shown by `sage doctor`
+    fn name(&self) -> &'static str;
+
+    /// Unified diff of unstaged changes (`all`) or of the index against HEAD
+    fn diff(&self, all: bool) -> Result<String>;
+
+    /// `git status --porcelain` listing (`all`) or `git diff --cached --name-status`
+    fn files_changed(&self, all: bool) -> Result<String>;
+
+    /// Stage paths (or pathspecs), including deletions
+    fn stage(&self, paths: &[String]) -> Result<()>;
+
+    fn stage_all(&self) -> Result<()>;
+
+    fn has_staged_changes(&self) -> Result<bool>;
+
+    /// Commit the index, returning the new commit's SHA
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<String>;
+
+    /// Short name of the checked out branch, or "HEAD" when detached
+    fn current_branch(&self) -> Result<String>;
+
+    fn branch_exists(&self, name: &str) -> Result<bool>;
+
+    /// Create a branch at HEAD and switch to it, keeping local changes
+    fn create_branch(&self, name: &str) -> Result<()>;
+
+    fn push(&self, options: &PushOptions) -> Result<()>;
+
+    // History, index and ref operations for split, squash, reword, explain,
+    // changelog, release and the model's tools. Revisions and paths are
+    // validated by the callers in `git`.
+
+    /// SHA of HEAD, or None before the first commit
+    fn head_sha(&self) -> Result<Option<String>>;
+
+    /// Patch of the index against HEAD (`cached`) or of tracked files against
+    /// the index, binary files included, limited to `paths` unless empty
+    fn patch(&self, cached: bool, paths: &[String]) -> Result<String>;
+
+    /// Staged changes without context lines, for their hunk headers
+    fn staged_hunks(&self) -> Result<String>;
+
+    /// Apply a patch to the index only, leaving the working tree untouched
+    fn apply_to_index(&self, patch: &str) -> Result<()>;
+
+    /// Point the current branch at `sha`
+    fn reset(&self, sha: &str, mode: ResetMode) -> Result<()>;
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<String>;
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool>;
+
+    /// Unified diff between two revisions
+    fn range_diff(&self, from: &str, to: &str) -> Result<String>;
+
+    /// Unified diff of a commit against its first parent
+    fn commit_diff(&self, sha: &str) -> Result<String>;
+
+    /// Commits in `range`, newest first; `single` stops after the first
+    fn log(&self, range: &str, single: bool) -> Result<Vec<CommitInfo>>;
+
+    /// SHAs along the first-parent chain from `rev`, newest first
+    fn first_parents(&self, rev: &str) -> Result<Vec<String>>;
+
+    /// Recent commits touching `path`, one line each
+    fn path_log(&self, path: &str, limit: usize) -> Result<String>;
+
+    /// Committer date of `rev` as YYYY-MM-DD
+    fn commit_date(&self, rev: &str) -> Result<String>;
+
+    /// Contents of an object such as "HEAD:src/main.rs"
+    fn show(&self, spec: &str) -> Result<String>;
+
+    fn read_commit(&self, rev: &str) -> Result<RawCommit>;
+
+    /// Create a commit object without moving any ref, signing it when `sign`
+    /// is set and a key is available; returns the SHA and whether it was signed
+    fn write_commit(
+        &self,
+        tree: &str,
+        parents: &[String],
+        author: &Ident,
+        committer: &Ident,
+        message: &str,
+        sign: bool,
+    ) -> Result<(String, bool)>;
+
+    /// Move HEAD (and the branch it points to) from `old` to `new`
+    fn update_head(&self, new: &str, old: &str, reason: &str) -> Result<()>;
+
+    /// Most recent tag reachable from `rev`, or with `exact` the tag on it
+    fn nearest_tag(&self, rev: &str, exact: bool) -> Result<Option<String>>;
+
+    /// Tags reachable from `rev`
+    fn tags_merged(&self, rev: &str) -> Result<Vec<String>>;
+
+    fn tag_exists(&self, name: &str) -> Result<bool>;
+
+    /// Create an annotated tag on HEAD; git signs it when tag.gpgSign is set
+    fn create_tag(&self, name: &str, message: &str) -> Result<()>;
+}
+
+static BACKEND: OnceLock<Box<dyn GitBackend>> = OnceLock::new();
+
+/// Choose the backend for this process; has no effect once a backend is in use
+pub fn select_backend(name: Option<&str>) {
+    let _ = BACKEND.set(open_backend(name));
+}
+
+/// The process-wide backend, opening the default one on first use
+pub fn backend() -> &'static dyn GitBackend {
+    BACKEND.get_or_init(|| open_backend(None)).as_ref()
+}
+
+#[cfg_attr(not(feature = "libgit2"), allow(unused_variables))]
+fn open_backend(name: Option<&str>) -> Box<dyn GitBackend> {
+    #[cfg(feature = "libgit2")]
+    if name != Some("cli") {
+        if let Some(backend) = Git2Backend::open() {
+            return Box::new(backend);
+        }
+    }
+
+    Box::new(CliBackend)
+}
@@ -0,0 +1,556 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::error::{Result, SageError};
+use super::backend::GitBackend;
+use super::rewrite::{parse_raw_commit, Ident, RawCommit};
+use super::{CommitInfo, CommitOptions, PushOptions, ResetMode};
+
+/// Backend that runs the `git` executable for every operation
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn name(&self) -> &'static str {
+        "cli"
+    }
+
+    fn diff(&self, all: bool) -> Result<String> {
+        let args = if all {
+            vec!["diff"]
+        } else {
+            vec!["diff", "--cached"]
+        };
+
+        let output = Command::new("git")
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn files_changed(&self, all: bool) -> Result<String> {
+        let args = if all {
+            vec!["status", "--porcelain"]
+        } else {
+            vec!["diff", "--cached", "--name-status"]
+        };
+
+        let output = Command::new("git")
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        let output = Command::new("git")
+            .arg("add")
+            .args(paths)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitStagingFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        let output = Command::new("git")
+            .args(["add", "--all"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitStagingFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn has_staged_changes(&self) -> Result<bool> {
+        let output = Command::new("git")
+            .args(["diff", "--cached", "--quiet"])
+            .status()?;
+
+        Ok(!output.success())
+    }
+
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<String> {
+        // -F keeps multi-line messages intact; the file is private and removed on drop
+        let mut message_file = tempfile::Builder::new()
+            .prefix("sage-commit-")
+            .tempfile()?;
+        message_file.write_all(message.as_bytes())?;
+        message_file.flush()?;
+
+        let mut cmd = Command::new("git");
+        cmd.arg("commit").arg("-F").arg(message_file.path());
+
+        if options.amend {
+            cmd.arg("--amend");
+        }
+        if options.signoff {
+            cmd.arg("--signoff");
+        }
+        match options.sign_key.as_deref() {
+            Some("") => { cmd.arg("--gpg-sign"); },
+            Some(key) => { cmd.arg(format!("--gpg-sign={}", key)); },
+            None => {},
+        }
+        if let Some(author) = &options.author {
+            cmd.arg(format!("--author={}", author));
+        }
+        if let Some(date) = &options.date {
+            cmd.arg(format!("--date={}", date));
+        }
+        if options.no_verify {
+            cmd.arg("--no-verify");
+        }
+
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommitFailed(error));
+        }
+
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommitFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn branch_exists(&self, name: &str) -> Result<bool> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", name])
+            .output()?;
+
+        Ok(output.status.success())
+    }
+
+    fn create_branch(&self, name: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args(["checkout", "-b", name])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::InvalidInput(
+                format!("Failed to create branch '{}': {}", name, error)
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn push(&self, options: &PushOptions) -> Result<()> {
+        let mut cmd = Command::new("git");
+        cmd.arg("push");
+        if options.force {
+            cmd.arg("--force");
+        }
+        if let Some((remote, branch)) = &options.upstream {
+            cmd.args(["--set-upstream", remote, branch]);
+        }
+
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitPushFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn head_sha(&self) -> Result<Option<String>> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+            .output()?;
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn patch(&self, cached: bool, paths: &[String]) -> Result<String> {
+        let mut cmd = Command::new("git");
+        cmd.args(["diff", "--binary", "--no-color", "--no-ext-diff", "--src-prefix=a/", "--dst-prefix=b/"]);
+        if cached {
+            cmd.arg("--cached");
+        }
+        let output = cmd.arg("--").args(paths).output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        // A lossy conversion would corrupt the patch when it is applied again
+        String::from_utf8(output.stdout).map_err(|_| SageError::GitDiffFailed(
+            "changes contain text that is not valid UTF-8".to_string()
+        ))
+    }
+
+    fn staged_hunks(&self) -> Result<String> {
+        let output = Command::new("git")
+            .args(["diff", "--cached", "-U0", "--no-color"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn apply_to_index(&self, patch: &str) -> Result<()> {
+        let mut child = Command::new("git")
+            .args(["apply", "--cached", "--whitespace=nowarn", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(patch.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitStagingFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn reset(&self, sha: &str, mode: ResetMode) -> Result<()> {
+        let mode = match mode {
+            ResetMode::Soft => "--soft",
+            ResetMode::Mixed => "--mixed",
+        };
+        let output = Command::new("git")
+            .args(["reset", "--quiet", mode, sha])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<String> {
+        let output = Command::new("git")
+            .args(["merge-base", a, b])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(
+                if error.trim().is_empty() { format!("{} and {} have no common ancestor", a, b) } else { error }
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        let output = Command::new("git")
+            .args(["merge-base", "--is-ancestor", ancestor, descendant])
+            .output()?;
+
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => Err(SageError::GitCommandFailed(String::from_utf8_lossy(&output.stderr).to_string())),
+        }
+    }
+
+    fn range_diff(&self, from: &str, to: &str) -> Result<String> {
+        let output = Command::new("git")
+            .args(["diff", "--no-color", "--no-ext-diff", from, to, "--"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn commit_diff(&self, sha: &str) -> Result<String> {
+        let output = Command::new("git")
+            .args(["show", "--format=", "--first-parent", "--no-color", "--no-ext-diff", sha])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitDiffFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn log(&self, range: &str, single: bool) -> Result<Vec<CommitInfo>> {
+        let mut cmd = Command::new("git");
+        cmd.arg("log").arg("--format=%H%x00%B%x1e");
+        if single {
+            cmd.arg("-1");
+        }
+        let output = cmd.arg(range).arg("--").output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\x1e')
+            .filter_map(|record| {
+                let (sha, message) = record.trim_start_matches('\n').split_once('\0')?;
+                Some(CommitInfo {
+                    sha: sha.to_string(),
+                    message: message.trim_end().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn first_parents(&self, rev: &str) -> Result<Vec<String>> {
+        let output = Command::new("git")
+            .args(["rev-list", "--first-parent", rev, "--"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn path_log(&self, path: &str, limit: usize) -> Result<String> {
+        let output = Command::new("git")
+            .args(["log", &format!("-n{}", limit), "--format=%h %ad %an: %s", "--date=short", "--", path])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn commit_date(&self, rev: &str) -> Result<String> {
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%cs", rev, "--"])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn show(&self, spec: &str) -> Result<String> {
+        let output = Command::new("git")
+            .args(["show", spec])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn read_commit(&self, rev: &str) -> Result<RawCommit> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(SageError::InvalidInput(format!("Not a commit: {}", rev)));
+        }
+        let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        let output = Command::new("git")
+            .args(["cat-file", "commit", &sha])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        parse_raw_commit(&sha, &String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| SageError::GitCommandFailed(format!("Could not parse commit {}", sha)))
+    }
+
+    fn write_commit(
+        &self,
+        tree: &str,
+        parents: &[String],
+        author: &Ident,
+        committer: &Ident,
+        message: &str,
+        sign: bool,
+    ) -> Result<(String, bool)> {
+        let mut message_file = tempfile::Builder::new()
+            .prefix("sage-rewrite-")
+            .tempfile()?;
+        message_file.write_all(message.as_bytes())?;
+        message_file.flush()?;
+
+        let run = |sign: bool| -> Result<std::process::Output> {
+            let mut cmd = Command::new("git");
+            cmd.arg("commit-tree").arg(tree);
+            for parent in parents {
+                cmd.arg("-p").arg(parent);
+            }
+            if sign {
+                cmd.arg("-S");
+            }
+            cmd.arg("-F").arg(message_file.path())
+                .env("GIT_AUTHOR_NAME", &author.name)
+                .env("GIT_AUTHOR_EMAIL", &author.email)
+                .env("GIT_AUTHOR_DATE", &author.date)
+                .env("GIT_COMMITTER_NAME", &committer.name)
+                .env("GIT_COMMITTER_EMAIL", &committer.email)
+                .env("GIT_COMMITTER_DATE", &committer.date);
+            Ok(cmd.output()?)
+        };
+
+        let mut signed = sign;
+        let mut output = run(sign)?;
+        if sign && !output.status.success() {
+            signed = false;
+            output = run(false)?;
+        }
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommitFailed(error));
+        }
+
+        Ok((String::from_utf8_lossy(&output.stdout).trim().to_string(), signed))
+    }
+
+    fn update_head(&self, new: &str, old: &str, reason: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args(["update-ref", "-m", reason, "HEAD", new, old])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(())
+    }
+
+    fn nearest_tag(&self, rev: &str, exact: bool) -> Result<Option<String>> {
+        let mode = if exact { "--exact-match" } else { "--abbrev=0" };
+        let output = Command::new("git")
+            .args(["describe", "--tags", mode, rev])
+            .output()?;
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn tags_merged(&self, rev: &str) -> Result<Vec<String>> {
+        let output = Command::new("git")
+            .args(["tag", "--list", "--merged", rev])
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
+    fn tag_exists(&self, name: &str) -> Result<bool> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet"])
+            .arg(format!("refs/tags/{}", name))
+            .output()?;
+
+        Ok(output.status.success())
+    }
+
+    fn create_tag(&self, name: &str, message: &str) -> Result<()> {
+        let mut child = Command::new("git")
+            .args(["tag", "--annotate", name, "--file=-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(message.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(SageError::GitCommandFailed(error));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,615 @@
+//! Structured view of `git diff` output
+//!
+//! The unified diff is parsed once into files and hunks; truncation, change
+//! summaries and prompts all work from this model instead of re-scanning text.
+
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+}
+
+impl ChangeKind {
+    /// Single-letter code as used by `git diff --name-status`
+    pub fn code(&self) -> char {
+        match self {
+            ChangeKind::Added => 'A',
+            ChangeKind::Deleted => 'D',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Renamed => 'R',
+            ChangeKind::Copied => 'C',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// The raw `@@ -a,b +c,d @@ section` line
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// Text after the closing `@@`, usually the enclosing function
+    pub section: String,
+    /// Body lines including their ' ', '+', '-' or '\' prefix, and the '\r'
+    /// of CRLF lines
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    fn parse_header(header: &str) -> Hunk {
+        let mut parts = header.splitn(3, "@@");
+        parts.next();
+        let ranges = parts.next().unwrap_or("").trim();
+        let section = parts.next().unwrap_or("").trim().to_string();
+
+        let mut old = (0, 0);
+        let mut new = (0, 0);
+        for range in ranges.split_whitespace() {
+            if let Some(r) = range.strip_prefix('-') {
+                old = parse_range(r);
+            } else if let Some(r) = range.strip_prefix('+') {
+                new = parse_range(r);
+            }
+        }
+
+        Hunk {
+            header: header.to_string(),
+            old_start: old.0,
+            old_lines: old.1,
+            new_start: new.0,
+            new_lines: new.1,
+            section,
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl Hunk {
+    /// Line ranges of each run of consecutive added/removed lines
+    pub fn change_groups(&self) -> Vec<Range<usize>> {
+        let mut groups: Vec<Range<usize>> = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let is_change = line.starts_with('+') || line.starts_with('-');
+            // "\ No newline at end of file" belongs to the line before it
+            let continues = line.starts_with('\\') && groups.last().is_some_and(|group| group.end == i);
+            if !is_change && !continues {
+                continue;
+            }
+
+            match groups.last_mut() {
+                Some(group) if group.end == i => group.end = i + 1,
+                _ => groups.push(i..i + 1),
+            }
+        }
+
+        groups
+    }
+
+    /// This hunk with only the change groups flagged in `keep`: removals that
+    /// are not kept become context and additions are dropped. The new start
+    /// line is shifted by `offset`. None when nothing is kept.
+    pub fn select(&self, keep: &[bool], offset: i64) -> Option<Hunk> {
+        let groups = self.change_groups();
+        if !groups.iter().enumerate().any(|(i, _)| keep.get(i).copied().unwrap_or(false)) {
+            return None;
+        }
+
+        let mut lines = Vec::new();
+        let mut dropped_previous = false;
+        for (i, line) in self.lines.iter().enumerate() {
+            let kept = groups.iter()
+                .position(|group| group.contains(&i))
+                .is_none_or(|group| keep.get(group).copied().unwrap_or(false));
+
+            if kept {
+                lines.push(line.clone());
+                dropped_previous = false;
+            } else if let Some(text) = line.strip_prefix('-') {
+                lines.push(format!(" {}", text));
+                dropped_previous = false;
+            } else if line.starts_with('+') {
+                dropped_previous = true;
+            } else if !dropped_previous {
+                lines.push(line.clone());
+            }
+        }
+
+        let count = |prefix: char| lines.iter().filter(|line| line.starts_with(' ') || line.starts_with(prefix)).count() as u32;
+        let old_lines = count('-');
+        let new_lines = count('+');
+
+        // Empty sides point at the line before the change, as git writes them
+        let mut new_start = self.old_start as i64 + offset;
+        if old_lines == 0 && new_lines > 0 {
+            new_start += 1;
+        } else if new_lines == 0 && old_lines > 0 {
+            new_start -= 1;
+        }
+        let new_start = new_start.max(0) as u32;
+
+        let section = if self.section.is_empty() { String::new() } else { format!(" {}", self.section) };
+        Some(Hunk {
+            header: format!(
+                "@@ -{} +{} @@{}",
+                format_range(self.old_start, old_lines),
+                format_range(new_start, new_lines),
+                section
+            ),
+            old_start: self.old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            section: self.section.clone(),
+            lines,
+        })
+    }
+}
+
+fn format_range(start: u32, count: u32) -> String {
+    if count == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, count)
+    }
+}
+
+/// Parse "start,count" (count defaults to 1)
+fn parse_range(range: &str) -> (u32, u32) {
+    let mut parts = range.splitn(2, ',');
+    let start = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let count = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    (start, count)
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.header)?;
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffFile {
+    pub kind: ChangeKind,
+    /// Path before the change; None for added files
+    pub old_path: Option<String>,
+    /// Path after the change; None for deleted files
+    pub new_path: Option<String>,
+    /// Similarity percentage for renames and copies
+    pub similarity: Option<u8>,
+    pub binary: bool,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+    /// Raw header lines, from `diff --git` up to the first hunk
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl DiffFile {
+    fn new(header_line: &str) -> DiffFile {
+        let (old_path, new_path) = parse_git_header_paths(header_line);
+
+        DiffFile {
+            kind: ChangeKind::Modified,
+            old_path,
+            new_path,
+            similarity: None,
+            binary: false,
+            old_mode: None,
+            new_mode: None,
+            added: 0,
+            removed: 0,
+            header: vec![header_line.to_string()],
+            hunks: Vec::new(),
+        }
+    }
+
+    /// Current path of the file (the old path for deletions)
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or("")
+    }
+
+    /// Whether the file mode changed (e.g. it became executable)
+    pub fn mode_changed(&self) -> bool {
+        matches!((&self.old_mode, &self.new_mode), (Some(old), Some(new)) if old != new)
+            && !matches!(self.kind, ChangeKind::Added | ChangeKind::Deleted)
+    }
+
+    /// One-line summary, e.g. `R  old.rs -> new.rs (92% similar, +3 -1)`
+    pub fn summary(&self) -> String {
+        let paths = match self.kind {
+            ChangeKind::Renamed | ChangeKind::Copied => format!(
+                "{} -> {}",
+                self.old_path.as_deref().unwrap_or(""),
+                self.new_path.as_deref().unwrap_or("")
+            ),
+            _ => self.path().to_string(),
+        };
+
+        let mut details = Vec::new();
+        if let Some(similarity) = self.similarity {
+            details.push(format!("{}% similar", similarity));
+        }
+        if self.mode_changed() {
+            details.push(format!(
+                "mode {} -> {}",
+                self.old_mode.as_deref().unwrap_or(""),
+                self.new_mode.as_deref().unwrap_or("")
+            ));
+        }
+        if self.binary {
+            details.push("binary".to_string());
+        } else {
+            details.push(format!("+{} -{}", self.added, self.removed));
+        }
+
+        format!("{}  {} ({})", self.kind.code(), paths, details.join(", "))
+    }
+
+    fn parse_header_line(&mut self, line: &str) {
+        if let Some(mode) = line.strip_prefix("new file mode ") {
+            self.kind = ChangeKind::Added;
+            self.old_path = None;
+            self.new_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            self.kind = ChangeKind::Deleted;
+            self.new_path = None;
+            self.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            self.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            self.new_mode = Some(mode.to_string());
+        } else if let Some(score) = line.strip_prefix("similarity index ") {
+            self.similarity = score.trim_end_matches('%').parse().ok();
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            self.kind = ChangeKind::Renamed;
+            self.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            self.kind = ChangeKind::Renamed;
+            self.new_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            self.kind = ChangeKind::Copied;
+            self.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            self.kind = ChangeKind::Copied;
+            self.new_path = Some(path.to_string());
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            self.binary = true;
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            self.old_path = strip_diff_prefix(path, "a/");
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            self.new_path = strip_diff_prefix(path, "b/");
+        }
+    }
+
+    /// Header lines plus a few lines of each hunk, for when the full file is too large
+    pub fn sample(&self, lines_per_hunk: usize) -> String {
+        let mut out = String::new();
+        for line in &self.header {
+            out.push_str(line);
+            out.push('\n');
+        }
+
+        for hunk in &self.hunks {
+            out.push_str(&hunk.header);
+            out.push('\n');
+            for line in hunk.lines.iter().take(lines_per_hunk) {
+                out.push_str(line);
+                out.push('\n');
+            }
+            if hunk.lines.len() > lines_per_hunk {
+                out.push_str("...[truncated]...\n");
+            }
+        }
+
+        out
+    }
+
+    /// Copy of this file's patch keeping only the hunks at `indices`
+    pub fn with_hunks(&self, indices: &[usize]) -> DiffFile {
+        let hunks: Vec<Hunk> = indices.iter().filter_map(|&i| self.hunks.get(i).cloned()).collect();
+        let count = |prefix: char| hunks.iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.starts_with(prefix))
+            .count();
+
+        DiffFile {
+            added: count('+'),
+            removed: count('-'),
+            hunks,
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this file's patch keeping the change groups flagged in `keep`,
+    /// one list per hunk (see `Hunk::select`). None when nothing is kept.
+    pub fn select(&self, keep: &[Vec<bool>]) -> Option<DiffFile> {
+        let mut offset = 0i64;
+        let mut hunks = Vec::new();
+        for (hunk, keep) in self.hunks.iter().zip(keep) {
+            if let Some(selected) = hunk.select(keep, offset) {
+                offset += selected.new_lines as i64 - selected.old_lines as i64;
+                hunks.push(selected);
+            }
+        }
+
+        if hunks.is_empty() {
+            return None;
+        }
+
+        let count = |prefix: char| hunks.iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.starts_with(prefix))
+            .count();
+
+        Some(DiffFile {
+            added: count('+'),
+            removed: count('-'),
+            hunks,
+            ..self.clone()
+        })
+    }
+
+    /// Number of hunk header and body lines
+    pub fn body_len(&self) -> usize {
+        self.hunks.iter().map(|hunk| hunk.lines.len() + 1).sum()
+    }
+}
+
+impl fmt::Display for DiffFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.header {
+            writeln!(f, "{}", line)?;
+        }
+        for hunk in &self.hunks {
+            write!(f, "{}", hunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// Paths from `diff --git a/<old> b/<new>`; refined later by the ---/+++ and rename lines
+fn parse_git_header_paths(line: &str) -> (Option<String>, Option<String>) {
+    let rest = line.trim_start_matches("diff --git ");
+    match rest.strip_prefix("a/").and_then(|r| r.split_once(" b/")) {
+        Some((old, new)) => (Some(old.to_string()), Some(new.to_string())),
+        None => (None, None),
+    }
+}
+
+fn strip_diff_prefix(path: &str, prefix: &str) -> Option<String> {
+    let path = path.trim_end_matches('\t');
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// A parsed unified diff
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diff {
+    pub files: Vec<DiffFile>,
+}
+
+impl Diff {
+    pub fn parse(text: &str) -> Diff {
+        let mut files: Vec<DiffFile> = Vec::new();
+
+        // Unlike lines(), this keeps the '\r' of CRLF lines, so patches rebuilt
+        // with Display still apply to files with Windows line endings
+        for line in text.split_inclusive('\n') {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            if line.starts_with("diff --git ") {
+                files.push(DiffFile::new(line));
+                continue;
+            }
+
+            // Anything before the first file header is not part of the diff
+            let Some(file) = files.last_mut() else { continue };
+
+            if line.starts_with("@@") {
+                file.hunks.push(Hunk::parse_header(line));
+            } else if let Some(hunk) = file.hunks.last_mut() {
+                if line.starts_with('+') {
+                    file.added += 1;
+                } else if line.starts_with('-') {
+                    file.removed += 1;
+                }
+                hunk.lines.push(line.to_string());
+            } else {
+                file.parse_header_line(line);
+                file.header.push(line.to_string());
+            }
+        }
+
+        Diff { files }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn added(&self) -> usize {
+        self.files.iter().map(|file| file.added).sum()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.files.iter().map(|file| file.removed).sum()
+    }
+
+    /// One summary line per file
+    pub fn summary(&self) -> String {
+        self.files.iter()
+            .map(DiffFile::summary)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for file in &self.files {
+            write!(f, "{}", file)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@ mod a;
+ use std::fmt;
+-use std::io;
++use std::io::{self, Write};
++use std::fs;
+
+diff --git a/old name.txt b/new name.txt
+similarity index 92%
+rename from old name.txt
+rename to new name.txt
+index 3333333..4444444 100644
+--- a/old name.txt
++++ b/new name.txt
+@@ -1 +1 @@
+-hello
++hello world
+\\ No newline at end of file
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..5555555
+Binary files /dev/null and b/logo.png differ
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/gone.rs b/gone.rs
+deleted file mode 100644
+index 6666666..0000000
+--- a/gone.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn main() {}
+-
+";
+
+    #[test]
+    fn test_parse_files() {
+        let diff = Diff::parse(DIFF);
+        assert_eq!(diff.files.len(), 5);
+
+        let modified = &diff.files[0];
+        assert_eq!(modified.kind, ChangeKind::Modified);
+        assert_eq!(modified.path(), "src/lib.rs");
+        assert_eq!((modified.added, modified.removed), (2, 1));
+        assert_eq!(modified.hunks[0].section, "mod a;");
+        assert_eq!((modified.hunks[0].old_start, modified.hunks[0].old_lines), (1, 3));
+        assert_eq!((modified.hunks[0].new_start, modified.hunks[0].new_lines), (1, 4));
+
+        let renamed = &diff.files[1];
+        assert_eq!(renamed.kind, ChangeKind::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("old name.txt"));
+        assert_eq!(renamed.new_path.as_deref(), Some("new name.txt"));
+        assert_eq!(renamed.similarity, Some(92));
+
+        let binary = &diff.files[2];
+        assert_eq!(binary.kind, ChangeKind::Added);
+        assert!(binary.binary);
+        assert_eq!(binary.old_path, None);
+
+        assert!(diff.files[3].mode_changed());
+
+        let deleted = &diff.files[4];
+        assert_eq!(deleted.kind, ChangeKind::Deleted);
+        assert_eq!(deleted.path(), "gone.rs");
+        assert_eq!(deleted.removed, 2);
+
+        assert_eq!((diff.added(), diff.removed()), (3, 4));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        assert_eq!(Diff::parse(DIFF).to_string(), DIFF);
+        assert!(Diff::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_crlf_round_trips() {
+        let text = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,3 @@
+ one\r
+-two\r
++TWO\r
+ three\r
+";
+        let diff = Diff::parse(text);
+        assert_eq!(diff.files[0].hunks[0].lines[1], "-two\r");
+        assert_eq!(diff.to_string(), text);
+
+        let kept = diff.files[0].select(&[vec![true]]).unwrap();
+        assert_eq!(kept.to_string(), text);
+    }
+
+    #[test]
+    fn test_select_change_groups() {
+        let hunk = &Diff::parse(DIFF).files[0].hunks[0];
+        assert_eq!(hunk.change_groups(), vec![1..4]);
+
+        let diff = Diff::parse("\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,5 +1,5 @@ top
+ one
+-two
++TWO
+ three
+-four
++FOUR
+ five
+");
+        let file = &diff.files[0];
+        assert_eq!(file.hunks[0].change_groups(), vec![1..3, 4..6]);
+        assert!(file.select(&[vec![false, false]]).is_none());
+
+        let second = file.select(&[vec![false, true]]).unwrap();
+        assert_eq!(second.hunks[0].header, "@@ -1,5 +1,5 @@ top");
+        assert_eq!(second.hunks[0].lines, vec![" one", " two", " three", "-four", "+FOUR", " five"]);
+        assert_eq!((second.added, second.removed), (1, 1));
+        assert_eq!(file.select(&[vec![true, true]]).unwrap(), *file);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(Diff::parse(DIFF).summary(), "\
+M  src/lib.rs (+2 -1)
+R  old name.txt -> new name.txt (92% similar, +1 -1)
+A  logo.png (binary)
+M  run.sh (mode 100644 -> 100755, +0 -0)
+D  gone.rs (+0 -2)");
+    }
+}
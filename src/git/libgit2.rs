@@ -0,0 +1,639 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use git2::{
+    Branch, Diff, DiffFindOptions, DiffFormat, IndexAddOption, Pathspec, PathspecFlags, Repository, ResetType, Status,
+    StatusOptions,
+};
+
+use crate::error::{Result, SageError};
+use super::backend::GitBackend;
+use super::cli::CliBackend;
+use super::rewrite::{Ident, RawCommit};
+use super::{CommitInfo, CommitOptions, PushOptions, ResetMode};
+
+/// Hooks that `git commit` runs and libgit2 does not
+const COMMIT_HOOKS: &[&str] = &["pre-commit", "prepare-commit-msg", "commit-msg", "post-commit"];
+
+/// In-process backend built on libgit2
+///
+/// Pushing always goes through the CLI so credential helpers and SSH config
+/// keep working, as do commits that need hooks, signing, a sign-off or an
+/// author/date override. Resets, ancestry checks and simple lookups run
+/// in-process; patches, logs, tags and history rewriting still use the CLI,
+/// whose output formats the callers parse.
+pub struct Git2Backend {
+    repo: Mutex<Repository>,
+}
+
+impl Git2Backend {
+    /// Open the repository containing the working directory (honoring GIT_DIR)
+    pub fn open() -> Option<Self> {
+        Repository::open_from_env().ok().map(Self::from_repository)
+    }
+
+    pub fn from_repository(repo: Repository) -> Self {
+        Git2Backend { repo: Mutex::new(repo) }
+    }
+
+    fn repo(&self) -> MutexGuard<'_, Repository> {
+        self.repo.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Stage `paths`, given relative to `cwd` as on the command line
+    fn stage_from(&self, cwd: &Path, paths: &[String]) -> Result<()> {
+        let repo = self.repo();
+        let workdir = repo.workdir()
+            .ok_or_else(|| SageError::GitStagingFailed("cannot stage files in a bare repository".to_string()))?
+            .to_path_buf();
+        let mut index = repo.index().map_err(staging_error)?;
+
+        for path in paths {
+            let spec = repo_pathspec(&workdir, cwd, path)?;
+
+            // Like `git add`, a pathspec must name a tracked or existing file
+            let pathspec = Pathspec::new([&spec]).map_err(staging_error)?;
+            let known = pathspec.match_index(&index, PathspecFlags::NO_MATCH_ERROR).is_ok()
+                || pathspec.match_workdir(&repo, PathspecFlags::NO_MATCH_ERROR).is_ok();
+            if !known {
+                return Err(SageError::GitStagingFailed(format!("pathspec '{}' did not match any files", path)));
+            }
+
+            // add_all picks up new and modified files, update_all removes deleted ones
+            index.add_all([&spec], IndexAddOption::DEFAULT, None).map_err(staging_error)?;
+            index.update_all([&spec], None).map_err(staging_error)?;
+        }
+
+        index.write().map_err(staging_error)
+    }
+
+    /// Whether `git commit` would do something libgit2 cannot (hooks, signing,
+    /// trailers, author or date overrides)
+    fn needs_cli_commit(repo: &Repository, options: &CommitOptions) -> bool {
+        if options.signoff || options.sign_key.is_some() || options.author.is_some() || options.date.is_some() {
+            return true;
+        }
+
+        let config = match repo.config() {
+            Ok(config) => config,
+            Err(_) => return true,
+        };
+
+        if config.get_bool("commit.gpgsign").unwrap_or(false) {
+            return true;
+        }
+
+        let hooks_dir = match config.get_path("core.hooksPath") {
+            Ok(path) if path.is_absolute() => path,
+            Ok(path) => repo.workdir().map(|dir| dir.join(&path)).unwrap_or(path),
+            Err(_) => repo.path().join("hooks"),
+        };
+
+        COMMIT_HOOKS.iter().any(|hook| is_executable(&hooks_dir.join(hook)))
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn diff_error(e: git2::Error) -> SageError {
+    SageError::GitDiffFailed(e.message().to_string())
+}
+
+/// Make `path` (relative to `cwd`, possibly a glob) relative to the
+/// repository root, as libgit2 pathspecs are
+fn repo_pathspec(workdir: &Path, cwd: &Path, path: &str) -> Result<String> {
+    // Compare canonical forms so symlinked temp or home directories still match
+    let workdir = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+
+    let mut resolved = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                resolved.pop();
+            },
+            other => resolved.push(other),
+        }
+    }
+
+    let relative = resolved.strip_prefix(&workdir).map_err(|_| {
+        SageError::GitStagingFailed(format!("'{}' is outside the repository at {}", path, workdir.display()))
+    })?;
+
+    let spec = relative.to_string_lossy().replace('\\', "/");
+    Ok(if spec.is_empty() { "*".to_string() } else { spec })
+}
+
+fn staging_error(e: git2::Error) -> SageError {
+    SageError::GitStagingFailed(e.message().to_string())
+}
+
+fn command_error(e: git2::Error) -> SageError {
+    SageError::GitCommandFailed(e.message().to_string())
+}
+
+fn commit_error(e: git2::Error) -> SageError {
+    SageError::GitCommitFailed(e.message().to_string())
+}
+
+/// Diff of the index against HEAD (or an empty tree before the first commit)
+fn staged_diff(repo: &Repository) -> std::result::Result<Diff<'_>, git2::Error> {
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e),
+    };
+
+    repo.diff_tree_to_index(head_tree.as_ref(), None, None)
+}
+
+fn changes_diff(repo: &Repository, all: bool) -> std::result::Result<Diff<'_>, git2::Error> {
+    let mut diff = if all {
+        repo.diff_index_to_workdir(None, None)?
+    } else {
+        staged_diff(repo)?
+    };
+
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    Ok(diff)
+}
+
+/// Render a diff the way `git diff` prints it
+fn patch_text(diff: &Diff) -> std::result::Result<String, git2::Error> {
+    let mut out = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            out.push(line.origin() as u8);
+        }
+        out.extend_from_slice(line.content());
+        true
+    })?;
+
+    Ok(String::from_utf8_lossy(&out).to_string())
+}
+
+fn delta_status_char(status: git2::Delta) -> char {
+    match status {
+        git2::Delta::Added => 'A',
+        git2::Delta::Deleted => 'D',
+        git2::Delta::Renamed => 'R',
+        git2::Delta::Copied => 'C',
+        git2::Delta::Typechange => 'T',
+        git2::Delta::Conflicted => 'U',
+        _ => 'M',
+    }
+}
+
+/// Rename/copy similarity of delta `index`; git2 only exposes it in the patch header
+fn similarity(diff: &Diff, index: usize) -> Option<u32> {
+    let mut patch = git2::Patch::from_diff(diff, index).ok()??;
+    let text = patch.to_buf().ok()?;
+
+    String::from_utf8_lossy(&text).lines()
+        .find_map(|line| line.strip_prefix("similarity index "))
+        .and_then(|score| score.trim_end_matches('%').parse().ok())
+}
+
+/// `git diff --cached --name-status` lines
+fn name_status(diff: &Diff) -> String {
+    let mut out = String::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let old_path = delta.old_file().path().map(|p| p.display().to_string()).unwrap_or_default();
+        let new_path = delta.new_file().path().map(|p| p.display().to_string()).unwrap_or_default();
+        let status = delta_status_char(delta.status());
+
+        if matches!(status, 'R' | 'C') {
+            let score = similarity(diff, i).map(|s| s.to_string()).unwrap_or_default();
+            out.push_str(&format!("{}{}\t{}\t{}\n", status, score, old_path, new_path));
+        } else {
+            out.push_str(&format!("{}\t{}\n", status, new_path));
+        }
+    }
+    out
+}
+
+/// Two-letter `git status --porcelain` code
+fn porcelain_code(status: Status) -> Option<String> {
+    if status.contains(Status::IGNORED) {
+        return None;
+    }
+    if status.contains(Status::CONFLICTED) {
+        return Some("UU".to_string());
+    }
+    if status.contains(Status::WT_NEW) {
+        return Some("??".to_string());
+    }
+
+    let index = if status.contains(Status::INDEX_NEW) {
+        'A'
+    } else if status.contains(Status::INDEX_RENAMED) {
+        'R'
+    } else if status.contains(Status::INDEX_DELETED) {
+        'D'
+    } else if status.contains(Status::INDEX_TYPECHANGE) {
+        'T'
+    } else if status.contains(Status::INDEX_MODIFIED) {
+        'M'
+    } else {
+        ' '
+    };
+
+    let worktree = if status.contains(Status::WT_DELETED) {
+        'D'
+    } else if status.contains(Status::WT_TYPECHANGE) {
+        'T'
+    } else if status.contains(Status::WT_RENAMED) {
+        'R'
+    } else if status.contains(Status::WT_MODIFIED) {
+        'M'
+    } else {
+        ' '
+    };
+
+    if index == ' ' && worktree == ' ' {
+        return None;
+    }
+    Some(format!("{}{}", index, worktree))
+}
+
+impl GitBackend for Git2Backend {
+    fn name(&self) -> &'static str {
+        "libgit2"
+    }
+
+    fn diff(&self, all: bool) -> Result<String> {
+        let repo = self.repo();
+        let diff = changes_diff(&repo, all).map_err(diff_error)?;
+        patch_text(&diff).map_err(diff_error)
+    }
+
+    fn files_changed(&self, all: bool) -> Result<String> {
+        let repo = self.repo();
+
+        if !all {
+            let diff = changes_diff(&repo, false).map_err(diff_error)?;
+            return Ok(name_status(&diff));
+        }
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true)
+            .recurse_untracked_dirs(false)
+            .renames_head_to_index(true);
+        let statuses = repo.statuses(Some(&mut options)).map_err(diff_error)?;
+
+        let mut out = String::new();
+        for entry in statuses.iter() {
+            let Some(code) = porcelain_code(entry.status()) else { continue };
+            let path = entry.path().unwrap_or_default();
+
+            let renamed_from = entry.head_to_index()
+                .filter(|delta| delta.status() == git2::Delta::Renamed)
+                .and_then(|delta| delta.old_file().path().map(|p| p.display().to_string()));
+
+            match renamed_from {
+                Some(old_path) => out.push_str(&format!("{} {} -> {}\n", code, old_path, path)),
+                None => out.push_str(&format!("{} {}\n", code, path)),
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        self.stage_from(&std::env::current_dir()?, paths)
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        let repo = self.repo();
+        let mut index = repo.index().map_err(staging_error)?;
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).map_err(staging_error)?;
+        index.update_all(["*"], None).map_err(staging_error)?;
+        index.write().map_err(staging_error)
+    }
+
+    fn has_staged_changes(&self) -> Result<bool> {
+        let repo = self.repo();
+        let diff = staged_diff(&repo).map_err(diff_error)?;
+        Ok(diff.deltas().len() > 0)
+    }
+
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<String> {
+        let repo = self.repo();
+        if Self::needs_cli_commit(&repo, options) {
+            return CliBackend.commit(message, options);
+        }
+
+        let signature = repo.signature().map_err(commit_error)?;
+        let message = git2::message_prettify(message, None).map_err(commit_error)?;
+        // Reload first: git itself may have changed the index (e.g. `git apply --cached`)
+        let tree_id = repo.index()
+            .and_then(|mut index| index.read(false).and_then(|_| index.write_tree()))
+            .map_err(commit_error)?;
+        let tree = repo.find_tree(tree_id).map_err(commit_error)?;
+
+        let head = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit().map_err(commit_error)?),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(commit_error(e)),
+        };
+
+        if options.amend {
+            let head = head.ok_or_else(|| SageError::GitCommitFailed(
+                "You have nothing to amend.".to_string()
+            ))?;
+            let oid = head.amend(Some("HEAD"), None, Some(&signature), None, Some(&message), Some(&tree))
+                .map_err(commit_error)?;
+            return Ok(oid.to_string());
+        }
+
+        if head.as_ref().is_some_and(|commit| commit.tree_id() == tree_id) {
+            return Err(SageError::GitCommitFailed("nothing to commit".to_string()));
+        }
+
+        let parents: Vec<&git2::Commit> = head.iter().collect();
+        let oid = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)
+            .map_err(commit_error)?;
+
+        Ok(oid.to_string())
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        let repo = self.repo();
+        let head = repo.head().map_err(diff_error)?;
+        Ok(head.shorthand().unwrap_or("HEAD").to_string())
+    }
+
+    fn branch_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.repo().revparse_single(name).is_ok())
+    }
+
+    fn create_branch(&self, name: &str) -> Result<()> {
+        let repo = self.repo();
+        let failed = |details: String| SageError::InvalidInput(
+            format!("Failed to create branch '{}': {}", name, details)
+        );
+
+        if !Branch::name_is_valid(name).unwrap_or(false) {
+            return Err(failed("not a valid branch name".to_string()));
+        }
+
+        // The new branch points at HEAD, so the index and working tree stay as they are
+        match repo.head() {
+            Ok(head) => {
+                let commit = head.peel_to_commit().map_err(|e| failed(e.message().to_string()))?;
+                repo.branch(name, &commit, false).map_err(|e| failed(e.message().to_string()))?;
+            },
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {},
+            Err(e) => return Err(failed(e.message().to_string())),
+        }
+
+        repo.set_head(&format!("refs/heads/{}", name))
+            .map_err(|e| failed(e.message().to_string()))
+    }
+
+    fn push(&self, options: &PushOptions) -> Result<()> {
+        CliBackend.push(options)
+    }
+
+    fn head_sha(&self) -> Result<Option<String>> {
+        let repo = self.repo();
+        let sha = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit().map_err(command_error)?.id().to_string()),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(command_error(e)),
+        };
+        Ok(sha)
+    }
+
+    fn patch(&self, cached: bool, paths: &[String]) -> Result<String> {
+        CliBackend.patch(cached, paths)
+    }
+
+    fn staged_hunks(&self) -> Result<String> {
+        CliBackend.staged_hunks()
+    }
+
+    fn apply_to_index(&self, patch: &str) -> Result<()> {
+        CliBackend.apply_to_index(patch)
+    }
+
+    fn reset(&self, sha: &str, mode: ResetMode) -> Result<()> {
+        let repo = self.repo();
+        let target = repo.revparse_single(sha).map_err(command_error)?;
+        let kind = match mode {
+            ResetMode::Soft => ResetType::Soft,
+            ResetMode::Mixed => ResetType::Mixed,
+        };
+        repo.reset(&target, kind, None).map_err(command_error)
+    }
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<String> {
+        let repo = self.repo();
+        let a_id = repo.revparse_single(a).and_then(|o| o.peel_to_commit()).map_err(command_error)?.id();
+        let b_id = repo.revparse_single(b).and_then(|o| o.peel_to_commit()).map_err(command_error)?.id();
+        match repo.merge_base(a_id, b_id) {
+            Ok(oid) => Ok(oid.to_string()),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Err(SageError::GitCommandFailed(
+                format!("{} and {} have no common ancestor", a, b)
+            )),
+            Err(e) => Err(command_error(e)),
+        }
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        let repo = self.repo();
+        let ancestor = repo.revparse_single(ancestor).and_then(|o| o.peel_to_commit()).map_err(command_error)?.id();
+        let descendant = repo.revparse_single(descendant).and_then(|o| o.peel_to_commit()).map_err(command_error)?.id();
+        // git treats a commit as its own ancestor; libgit2 does not
+        Ok(ancestor == descendant || repo.graph_descendant_of(descendant, ancestor).map_err(command_error)?)
+    }
+
+    fn range_diff(&self, from: &str, to: &str) -> Result<String> {
+        CliBackend.range_diff(from, to)
+    }
+
+    fn commit_diff(&self, sha: &str) -> Result<String> {
+        CliBackend.commit_diff(sha)
+    }
+
+    fn log(&self, range: &str, single: bool) -> Result<Vec<CommitInfo>> {
+        CliBackend.log(range, single)
+    }
+
+    fn first_parents(&self, rev: &str) -> Result<Vec<String>> {
+        CliBackend.first_parents(rev)
+    }
+
+    fn path_log(&self, path: &str, limit: usize) -> Result<String> {
+        CliBackend.path_log(path, limit)
+    }
+
+    fn commit_date(&self, rev: &str) -> Result<String> {
+        CliBackend.commit_date(rev)
+    }
+
+    fn show(&self, spec: &str) -> Result<String> {
+        CliBackend.show(spec)
+    }
+
+    fn read_commit(&self, rev: &str) -> Result<RawCommit> {
+        CliBackend.read_commit(rev)
+    }
+
+    fn write_commit(
+        &self,
+        tree: &str,
+        parents: &[String],
+        author: &Ident,
+        committer: &Ident,
+        message: &str,
+        sign: bool,
+    ) -> Result<(String, bool)> {
+        CliBackend.write_commit(tree, parents, author, committer, message, sign)
+    }
+
+    fn update_head(&self, new: &str, old: &str, reason: &str) -> Result<()> {
+        CliBackend.update_head(new, old, reason)
+    }
+
+    fn nearest_tag(&self, rev: &str, exact: bool) -> Result<Option<String>> {
+        CliBackend.nearest_tag(rev, exact)
+    }
+
+    fn tags_merged(&self, rev: &str) -> Result<Vec<String>> {
+        CliBackend.tags_merged(rev)
+    }
+
+    fn tag_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.repo().find_reference(&format!("refs/tags/{}", name)).is_ok())
+    }
+
+    fn create_tag(&self, name: &str, message: &str) -> Result<()> {
+        CliBackend.create_tag(name, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_repo() -> (tempfile::TempDir, Git2Backend) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let mut config = repo.config().unwrap();
+            config.set_str("user.name", "Sage Test").unwrap();
+            config.set_str("user.email", "sage@example.com").unwrap();
+        }
+        (dir, Git2Backend::from_repository(repo))
+    }
+
+    #[test]
+    fn test_stage_and_commit() {
+        let (dir, backend) = test_repo();
+        fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+
+        assert!(!backend.has_staged_changes().unwrap());
+        backend.stage_all().unwrap();
+        assert!(backend.has_staged_changes().unwrap());
+        assert_eq!(backend.files_changed(false).unwrap(), "A\ta.txt\n");
+        assert!(backend.diff(false).unwrap().contains("+hello"));
+
+        let sha = backend.commit("feat: add a\n\n", &CommitOptions::default()).unwrap();
+        assert!(!backend.has_staged_changes().unwrap());
+
+        // Paths given from a subdirectory are relative to it, not the root
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}\n").unwrap();
+        backend.stage_from(&src, &["main.rs".to_string()]).unwrap();
+        assert_eq!(backend.files_changed(false).unwrap(), "A\tsrc/main.rs\n");
+        assert!(backend.stage_from(&src, &["missing.rs".to_string()]).is_err());
+        // Unchanged tracked files still match, as with `git add`
+        backend.stage_from(dir.path(), &["a.txt".to_string()]).unwrap();
+        assert!(backend.stage_from(&src, &["../../outside.txt".to_string()]).is_err());
+
+        let repo = backend.repo();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), sha);
+        assert_eq!(head.message(), Some("feat: add a\n"));
+        assert_eq!(head.author().name(), Some("Sage Test"));
+    }
+
+    #[test]
+    fn test_worktree_diff_and_status() {
+        let (dir, backend) = test_repo();
+        fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+        fs::write(dir.path().join("gone.txt"), "bye\n").unwrap();
+        backend.stage_all().unwrap();
+        backend.commit("initial", &CommitOptions::default()).unwrap();
+
+        fs::write(dir.path().join("a.txt"), "world\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        fs::remove_file(dir.path().join("gone.txt")).unwrap();
+
+        assert!(backend.diff(true).unwrap().contains("-hello\n+world\n"));
+        assert_eq!(backend.files_changed(true).unwrap(), " M a.txt\n D gone.txt\n?? new.txt\n");
+
+        backend.stage_from(dir.path(), &["gone.txt".to_string()]).unwrap();
+        assert_eq!(backend.files_changed(false).unwrap(), "D\tgone.txt\n");
+        backend.commit("remove gone.txt", &CommitOptions::default()).unwrap();
+        assert!(backend.commit("nothing staged", &CommitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_create_branch_keeps_changes() {
+        let (dir, backend) = test_repo();
+        fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+        backend.stage_all().unwrap();
+        backend.commit("initial", &CommitOptions::default()).unwrap();
+        fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+
+        assert!(!backend.branch_exists("feature/x").unwrap());
+        backend.create_branch("feature/x").unwrap();
+        assert!(backend.branch_exists("feature/x").unwrap());
+        assert_eq!(backend.current_branch().unwrap(), "feature/x");
+        assert!(backend.diff(true).unwrap().contains("+changed"));
+
+        assert!(backend.create_branch("bad..name").is_err());
+    }
+
+    #[test]
+    fn test_history_operations() {
+        let (dir, backend) = test_repo();
+        assert_eq!(backend.head_sha().unwrap(), None);
+
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        backend.stage_all().unwrap();
+        let first = backend.commit("first", &CommitOptions::default()).unwrap();
+        fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+        backend.stage_all().unwrap();
+        let second = backend.commit("second", &CommitOptions::default()).unwrap();
+
+        assert_eq!(backend.head_sha().unwrap(), Some(second.clone()));
+        assert_eq!(backend.merge_base(&first, &second).unwrap(), first);
+        assert!(backend.is_ancestor(&first, &second).unwrap());
+        assert!(backend.is_ancestor(&second, &second).unwrap());
+        assert!(!backend.is_ancestor(&second, &first).unwrap());
+        assert!(!backend.tag_exists("v1.0.0").unwrap());
+
+        // A soft reset keeps the second commit's change staged
+        backend.reset(&first, ResetMode::Soft).unwrap();
+        assert_eq!(backend.head_sha().unwrap(), Some(first.clone()));
+        assert!(backend.has_staged_changes().unwrap());
+
+        backend.reset(&first, ResetMode::Mixed).unwrap();
+        assert!(!backend.has_staged_changes().unwrap());
+        assert!(backend.diff(true).unwrap().contains("+two"));
+    }
+}
@@ -0,0 +1,531 @@
+use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+use crate::error::{Result, SageError};
+
+mod backend;
+mod cli;
+mod diff;
+#[cfg(feature = "libgit2")]
+mod libgit2;
+mod rewrite;
+
+pub use backend::{backend, select_backend, BACKEND_NAMES};
+pub use diff::{ChangeKind, Diff, DiffFile, Hunk};
+pub use rewrite::{get_commit_diff, get_first_parents, is_ancestor, read_commit, update_head, write_commit, RawCommit};
+
+const MAX_DIFF_SIZE: usize = 15000;
+/// Diff budget used when a provider rejects the first attempt for context length
+pub const RETRY_DIFF_SIZE: usize = MAX_DIFF_SIZE / 4;
+
+/// Validate file paths to prevent command injection and ensure they're safe
+fn validate_file_path(path: &str) -> Result<()> {
+    // Check for null bytes (can cause issues with C APIs)
+    if path.contains('\0') {
+        return Err(SageError::InvalidInput(
+            "File path contains null bytes".to_string()
+        ));
+    }
+
+    // Check for suspicious patterns that might indicate command injection attempts
+    let suspicious_patterns = [
+        "|", "&", ";", "`", "$", "(", ")", "<", ">", "\n", "\r"
+    ];
+
+    for pattern in &suspicious_patterns {
+        if path.contains(pattern) {
+            return Err(SageError::InvalidInput(
+                format!("File path contains suspicious character: {}", pattern)
+            ));
+        }
+    }
+
+    // Ensure path doesn't try to escape repository using absolute paths or ..
+    let path_obj = Path::new(path);
+
+    if path_obj.is_absolute() {
+        return Err(SageError::InvalidInput(
+            "Absolute paths are not allowed. Use relative paths within the repository".to_string()
+        ));
+    }
+
+    for component in path_obj.components() {
+        if let std::path::Component::ParentDir = component {
+            return Err(SageError::InvalidInput(
+                "Parent directory (..) traversal is not allowed".to_string()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_git_repo() -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+pub fn get_diff(all: bool) -> Result<Diff> {
+    Ok(Diff::parse(&backend().diff(all)?))
+}
+
+pub fn get_files_changed(all: bool) -> Result<String> {
+    backend().files_changed(all)
+}
+
+pub fn stage_files(files: &[String]) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    // Validate all file paths before handing them to the backend
+    for file in files {
+        validate_file_path(file)?;
+    }
+
+    backend().stage(files)
+}
+
+pub fn stage_all_files() -> Result<()> {
+    backend().stage_all()
+}
+
+pub fn has_staged_changes() -> Result<bool> {
+    backend().has_staged_changes()
+}
+
+/// Options passed through to `git commit`
+///
+/// There is no template option: sage always supplies the message, and git only
+/// reads `commit.template` when it asks for one in the editor.
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptions {
+    pub amend: bool,
+    pub signoff: bool,
+    /// Sign the commit (GPG or SSH, per gpg.format); an empty key uses the default
+    pub sign_key: Option<String>,
+    /// Author override, "Name <email>"
+    pub author: Option<String>,
+    pub date: Option<String>,
+    /// Skip the pre-commit and commit-msg hooks
+    pub no_verify: bool,
+}
+
+/// Commit the index, returning the new commit's SHA
+pub fn commit_changes(message: &str, options: &CommitOptions) -> Result<String> {
+    backend().commit(message, options)
+}
+
+/// Options passed through to `git push`
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    pub force: bool,
+    /// Remote and branch to push to and record as the branch's upstream
+    pub upstream: Option<(String, String)>,
+}
+
+pub fn push_changes(force: bool) -> Result<()> {
+    println!("{}", "Pushing changes...".blue());
+    backend().push(&PushOptions { force, ..Default::default() })?;
+    println!("{}", "Changes pushed successfully!".green());
+    Ok(())
+}
+
+/// Push `branch` to `remote` and make it the branch's upstream
+pub fn push_branch(remote: &str, branch: &str) -> Result<()> {
+    backend().push(&PushOptions {
+        upstream: Some((remote.to_string(), branch.to_string())),
+        ..Default::default()
+    })
+}
+
+pub fn get_current_branch() -> Result<String> {
+    backend().current_branch()
+}
+
+pub fn create_and_checkout_branch(branch_name: &str) -> Result<()> {
+    backend().create_branch(branch_name)?;
+    println!("{}", format!("Switched to new branch '{}'", branch_name).green());
+    Ok(())
+}
+
+pub fn branch_exists(branch_name: &str) -> Result<bool> {
+    backend().branch_exists(branch_name)
+}
+
+pub fn get_git_version() -> Result<String> {
+    let output = Command::new("git")
+        .arg("--version")
+        .output()?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(SageError::GitCommandFailed(error));
+    }
+
+    let version = String::from_utf8_lossy(&output.stdout);
+    Ok(version.trim().trim_start_matches("git version ").to_string())
+}
+
+/// Read a git config value, returning None when it is not set
+pub fn get_config_value(key: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .output()?;
+
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || value.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(value))
+}
+
+/// Upstream tracking branch of HEAD (e.g. "origin/main"), if any
+pub fn get_upstream_branch() -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
+        .output()?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+/// Default branch of the origin remote (e.g. "origin/main"), if known
+pub fn get_remote_default_branch() -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["symbolic-ref", "--quiet", "--short", "refs/remotes/origin/HEAD"])
+        .output()?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+/// Hooks directory for the repository, honoring core.hooksPath
+pub fn get_hooks_dir() -> Result<PathBuf> {
+    get_git_path("hooks")
+}
+
+/// Path of `name` inside the repository's git directory
+pub fn get_git_path(name: &str) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", name])
+        .output()?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(SageError::GitCommandFailed(error));
+    }
+
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Top-level directory of the working tree
+pub fn get_repo_root() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(SageError::GitCommandFailed(error));
+    }
+
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// SHA of HEAD, or None before the first commit
+pub fn get_head_sha() -> Result<Option<String>> {
+    backend().head_sha()
+}
+
+/// Staged changes as a patch that `git apply` can replay, binary files included
+pub fn get_staged_patch() -> Result<Diff> {
+    Ok(Diff::parse(&backend().patch(true, &[])?))
+}
+
+/// Unstaged changes to tracked files under `paths` (all files when empty),
+/// as a patch that `git apply --cached` can replay
+pub fn get_unstaged_patch(paths: &[String]) -> Result<Diff> {
+    for path in paths {
+        validate_file_path(path)?;
+    }
+
+    Ok(Diff::parse(&backend().patch(false, paths)?))
+}
+
+/// Apply a patch to the index only, leaving the working tree untouched
+pub fn apply_to_index(patch: &str) -> Result<()> {
+    backend().apply_to_index(patch)
+}
+
+/// How far `reset_to` and `reset_soft` go beyond moving the branch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetMode {
+    /// Keep the index and working tree
+    Soft,
+    /// Reset the index, keeping the working tree
+    Mixed,
+}
+
+/// Point the current branch at `sha` and reset the index to it, keeping the working tree
+pub fn reset_to(sha: &str) -> Result<()> {
+    validate_rev(sha)?;
+    backend().reset(sha, ResetMode::Mixed)
+}
+
+/// Point the current branch at `sha`, keeping the index and working tree
+pub fn reset_soft(sha: &str) -> Result<()> {
+    validate_rev(sha)?;
+    backend().reset(sha, ResetMode::Soft)
+}
+
+fn validate_rev(rev: &str) -> Result<()> {
+    if rev.is_empty() || rev.starts_with('-') || rev.contains(char::is_whitespace) {
+        return Err(SageError::InvalidInput(format!("Invalid revision: {}", rev)));
+    }
+    Ok(())
+}
+
+/// Best common ancestor of two revisions
+pub fn get_merge_base(a: &str, b: &str) -> Result<String> {
+    validate_rev(a)?;
+    validate_rev(b)?;
+    backend().merge_base(a, b)
+}
+
+/// Diff between two revisions
+pub fn get_range_diff(from: &str, to: &str) -> Result<Diff> {
+    validate_rev(from)?;
+    validate_rev(to)?;
+    Ok(Diff::parse(&backend().range_diff(from, to)?))
+}
+
+/// A commit's SHA and full message
+pub struct CommitInfo {
+    pub sha: String,
+    pub message: String,
+}
+
+/// Commits in `range` (e.g. "origin/main..HEAD"), newest first; a single
+/// revision yields just that commit
+pub fn get_commits(range: &str) -> Result<Vec<CommitInfo>> {
+    log_commits(range, !range.contains(".."))
+}
+
+/// Every commit reachable from `rev`, newest first
+pub fn get_history(rev: &str) -> Result<Vec<CommitInfo>> {
+    log_commits(rev, false)
+}
+
+fn log_commits(range: &str, single: bool) -> Result<Vec<CommitInfo>> {
+    if range.starts_with('-') || range.contains(char::is_whitespace) {
+        return Err(SageError::InvalidInput(format!("Invalid revision range: {}", range)));
+    }
+
+    backend().log(range, single)
+}
+
+/// Most recent tag reachable from `rev`, if any
+pub fn get_last_tag(rev: &str) -> Result<Option<String>> {
+    validate_rev(rev)?;
+    backend().nearest_tag(rev, false)
+}
+
+/// Tag pointing exactly at `rev`, if any
+pub fn get_tag_at(rev: &str) -> Result<Option<String>> {
+    validate_rev(rev)?;
+    backend().nearest_tag(rev, true)
+}
+
+/// Tags reachable from `rev`
+pub fn get_tags_merged(rev: &str) -> Result<Vec<String>> {
+    validate_rev(rev)?;
+    backend().tags_merged(rev)
+}
+
+/// Whether the tag `name` exists
+pub fn tag_exists(name: &str) -> Result<bool> {
+    validate_rev(name)?;
+    backend().tag_exists(name)
+}
+
+/// Create an annotated tag on HEAD; git signs it when tag.gpgSign is set
+pub fn create_annotated_tag(name: &str, message: &str) -> Result<()> {
+    validate_rev(name)?;
+    backend().create_tag(name, message)
+}
+
+/// Committer date of `rev` as YYYY-MM-DD
+pub fn get_commit_date(rev: &str) -> Result<String> {
+    validate_rev(rev)?;
+    backend().commit_date(rev)
+}
+
+/// Where to read a file's contents from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileSource {
+    Head,
+    Index,
+}
+
+/// Read a file as it exists at HEAD or in the index
+pub fn show_file(source: FileSource, path: &str) -> Result<String> {
+    validate_file_path(path)?;
+
+    let spec = match source {
+        FileSource::Head => format!("HEAD:{}", path),
+        FileSource::Index => format!(":{}", path),
+    };
+    backend().show(&spec)
+}
+
+/// Read a file as it exists at `rev`
+pub fn show_file_at(rev: &str, path: &str) -> Result<String> {
+    validate_rev(rev)?;
+    validate_file_path(path)?;
+    backend().show(&format!("{}:{}", rev, path))
+}
+
+/// Recent commits touching `path`, one line each
+pub fn get_path_log(path: &str, limit: usize) -> Result<String> {
+    validate_file_path(path)?;
+    backend().path_log(path, limit)
+}
+
+/// Enclosing functions/sections of each staged hunk, taken from the hunk headers
+pub fn get_changed_symbols() -> Result<String> {
+    Ok(parse_changed_symbols(&backend().staged_hunks()?))
+}
+
+fn parse_changed_symbols(diff: &str) -> String {
+    let mut symbols = Vec::new();
+
+    for file in &Diff::parse(diff).files {
+        for hunk in &file.hunks {
+            let entry = if hunk.section.is_empty() {
+                format!("{}: (top level)", file.path())
+            } else {
+                format!("{}: {}", file.path(), hunk.section)
+            };
+            if !symbols.contains(&entry) {
+                symbols.push(entry);
+            }
+        }
+    }
+
+    symbols.join("\n")
+}
+
+/// Maximum bytes of diff text printed by `show_changes`
+const DISPLAY_DIFF_SIZE: usize = 2000;
+
+pub fn show_changes(diff: &Diff) -> Result<()> {
+    println!("{}", "Changes to be committed:".blue().bold());
+    for file in &diff.files {
+        println!("{}", file.summary());
+    }
+    println!("{}", format!("{} files changed, +{} -{}", diff.files.len(), diff.added(), diff.removed()).dimmed());
+    println!("\n{}", "Diff:".blue().bold());
+
+    let text = diff.to_string();
+    if text.is_empty() {
+        println!("(empty diff)");
+    } else if text.len() > DISPLAY_DIFF_SIZE {
+        println!("{}", &text[..floor_char_boundary(&text, DISPLAY_DIFF_SIZE)]);
+        println!("... [truncated for display]");
+    } else {
+        println!("{}", text);
+    }
+
+    Ok(())
+}
+
+pub fn smart_truncate_diff(diff: &Diff) -> String {
+    truncate_diff(diff, MAX_DIFF_SIZE)
+}
+
+/// Largest char boundary in `s` that is not past `index`
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Truncate a diff to roughly `max_size` bytes, keeping every file header and
+/// hunk header of large files plus a sample of each hunk
+pub fn truncate_diff(diff: &Diff, max_size: usize) -> String {
+    let text = diff.to_string();
+    if text.len() <= max_size {
+        return text;
+    }
+
+    let mut important_parts = String::new();
+
+    for (i, file) in diff.files.iter().enumerate() {
+        if important_parts.len() > max_size / 2 {
+            important_parts.push_str(&format!("... [{} more files not shown]\n", diff.files.len() - i));
+            break;
+        }
+
+        if file.body_len() > 20 {
+            important_parts.push_str(&file.sample(3));
+        } else {
+            important_parts.push_str(&file.to_string());
+        }
+    }
+
+    if important_parts.len() < max_size {
+        important_parts
+    } else {
+        format!("{}... [truncated - diff too large]", &text[..floor_char_boundary(&text, max_size / 2)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smart_truncate_diff() {
+        let small_diff = "diff --git a/file.txt b/file.txt\nindex 123..456 789\n--- a/file.txt\n+++ b/file.txt\n@@ -1,3 +1,3 @@\n-old line\n+new line\n context\n";
+        assert_eq!(smart_truncate_diff(&Diff::parse(small_diff)), small_diff);
+
+        let large_diff = format!("diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1 +1 @@\n{}", "+a\n".repeat(MAX_DIFF_SIZE));
+        assert!(smart_truncate_diff(&Diff::parse(&large_diff)).len() <= MAX_DIFF_SIZE);
+    }
+
+    #[test]
+    fn test_parse_changed_symbols() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n\
+                    @@ -10,0 +11 @@ fn parse(input: &str) {\n+    let x = 1;\n\
+                    @@ -20 +21 @@ fn parse(input: &str) {\n-a\n+b\n\
+                    @@ -1 +1 @@\n-use a;\n+use b;\n";
+
+        assert_eq!(parse_changed_symbols(diff), "src/lib.rs: fn parse(input: &str) {\nsrc/lib.rs: (top level)");
+    }
+
+    #[test]
+    fn test_truncate_diff_to_retry_size() {
+        let file = format!("diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1 +1 @@\n{}", "+line\n".repeat(200));
+        let large_diff = Diff::parse(&file.repeat(200));
+        assert!(truncate_diff(&large_diff, RETRY_DIFF_SIZE).len() < smart_truncate_diff(&large_diff).len());
+
+        let multibyte = Diff::parse(&format!("diff --git a/f b/f\n@@ -1 +1 @@\n+{}\n", "é".repeat(RETRY_DIFF_SIZE)));
+        assert!(truncate_diff(&multibyte, RETRY_DIFF_SIZE).len() <= RETRY_DIFF_SIZE);
+    }
+}
@@ -0,0 +1,151 @@
+//! History rewriting plumbing
+//!
+//! Commits are recreated from their trees, keeping author and committer, and
+//! HEAD is moved once at the end, so a failure part way leaves the branch
+//! untouched. The object and ref work itself is done by the git backend.
+
+use crate::error::Result;
+use super::{backend, Diff};
+
+/// Name, email and raw date (`<seconds> <tz>`) of an author or committer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub email: String,
+    pub date: String,
+}
+
+/// A commit object as stored by git
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawCommit {
+    pub sha: String,
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: Ident,
+    pub committer: Ident,
+    /// Whether the commit carries a GPG or SSH signature
+    pub signed: bool,
+    pub message: String,
+}
+
+/// Parse `Name <email> 1700000000 +0100`
+fn parse_ident(value: &str) -> Option<Ident> {
+    let (name, rest) = value.split_once(" <")?;
+    let (email, date) = rest.split_once("> ")?;
+    Some(Ident {
+        name: name.to_string(),
+        email: email.to_string(),
+        date: date.to_string(),
+    })
+}
+
+pub(super) fn parse_raw_commit(sha: &str, text: &str) -> Option<RawCommit> {
+    let (headers, message) = text.split_once("\n\n").unwrap_or((text, ""));
+
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+    let mut signed = false;
+
+    for line in headers.lines() {
+        // Continuation lines of multi-line headers (signatures, mergetags)
+        if line.starts_with(' ') {
+            continue;
+        }
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "tree" => tree = Some(value.to_string()),
+            "parent" => parents.push(value.to_string()),
+            "author" => author = parse_ident(value),
+            "committer" => committer = parse_ident(value),
+            "gpgsig" | "gpgsig-sha256" => signed = true,
+            _ => {},
+        }
+    }
+
+    Some(RawCommit {
+        sha: sha.to_string(),
+        tree: tree?,
+        parents,
+        author: author?,
+        committer: committer?,
+        signed,
+        message: message.to_string(),
+    })
+}
+
+/// Read a commit object
+pub fn read_commit(rev: &str) -> Result<RawCommit> {
+    backend().read_commit(rev)
+}
+
+/// Changes introduced by a commit, against its first parent
+pub fn get_commit_diff(sha: &str) -> Result<Diff> {
+    Ok(Diff::parse(&backend().commit_diff(sha)?))
+}
+
+/// SHAs along the first-parent chain from `rev`, newest first
+pub fn get_first_parents(rev: &str) -> Result<Vec<String>> {
+    backend().first_parents(rev)
+}
+
+/// Whether `ancestor` is reachable from `descendant`
+pub fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    backend().is_ancestor(ancestor, descendant)
+}
+
+/// Create a commit object without moving any ref. When `sign` is set the
+/// commit is signed with the user's key if possible; returns the new SHA and
+/// whether it was signed.
+pub fn write_commit(
+    tree: &str,
+    parents: &[String],
+    author: &Ident,
+    committer: &Ident,
+    message: &str,
+    sign: bool,
+) -> Result<(String, bool)> {
+    backend().write_commit(tree, parents, author, committer, message, sign)
+}
+
+/// Move HEAD (and the branch it points to) from `old` to `new`
+pub fn update_head(new: &str, old: &str, reason: &str) -> Result<()> {
+    backend().update_head(new, old, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_raw_commit() {
+        let text = "\
+tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904
+parent 1111111111111111111111111111111111111111
+author Jane Doe <jane@example.com> 1700000000 +0100
+committer John Roe <john@example.com> 1700000100 -0500
+gpgsig -----BEGIN PGP SIGNATURE-----
+ \n iQEzBAABCAAdFiEE
+ -----END PGP SIGNATURE-----
+
+wip
+
+More text.
+";
+        let commit = parse_raw_commit("abc", text).unwrap();
+        assert_eq!(commit.tree, "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        assert_eq!(commit.parents, vec!["1111111111111111111111111111111111111111"]);
+        assert_eq!(commit.author, Ident {
+            name: "Jane Doe".to_string(),
+            email: "jane@example.com".to_string(),
+            date: "1700000000 +0100".to_string(),
+        });
+        assert_eq!(commit.committer.date, "1700000100 -0500");
+        assert!(commit.signed);
+        assert_eq!(commit.message, "wip\n\nMore text.\n");
+
+        assert!(parse_raw_commit("abc", "tree x\n\nno author\n").is_none());
+    }
+}
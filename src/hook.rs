@@ -0,0 +1,264 @@
+//! Git hook integration
+//!
+//! `sage hook install` writes a small shell script into the repository's hooks
+//! directory that calls back into sage. A hook that already exists is moved
+//! aside and run first, so installing sage never disables an existing hook.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+use crate::ai::call_ai_validated;
+use crate::cli::{HookKind, Task};
+use crate::config::{get_config_path, load_config};
+use crate::error::{Result, SageError};
+use crate::git::{get_config_value, get_diff, get_hooks_dir};
+use crate::lint::{clean_message, load_lint_config, validate_style};
+use crate::prompts;
+
+/// First comment line of every hook script written by sage
+const HOOK_MARKER: &str = "# Installed by sage";
+/// Suffix of a pre-existing hook that the sage hook chains to
+const CHAINED_SUFFIX: &str = ".pre-sage";
+
+impl HookKind {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => "prepare-commit-msg",
+            HookKind::CommitMsg => "commit-msg",
+        }
+    }
+
+    /// Shell command the hook runs after any chained hook
+    fn command(&self) -> &'static str {
+        match self {
+            // Never block a commit because message generation failed
+            HookKind::PrepareCommitMsg => "\"$SAGE\" hook run \"$@\" || true",
+            HookKind::CommitMsg => "exec \"$SAGE\" lint --file \"$1\"",
+        }
+    }
+}
+
+pub fn install_hook(kind: HookKind) -> Result<()> {
+    let hooks_dir = get_hooks_dir()?;
+    let sage_path = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "sage".to_string());
+
+    let chained = install_into(&hooks_dir, kind, &sage_path)?;
+
+    println!("{}", format!("Installed {} hook in {}", kind.file_name(), hooks_dir.display()).green());
+    if chained {
+        println!("{}", format!("Existing hook kept as {}{} and run first", kind.file_name(), CHAINED_SUFFIX).blue());
+    }
+    Ok(())
+}
+
+pub fn uninstall_hook(kind: HookKind) -> Result<()> {
+    let hooks_dir = get_hooks_dir()?;
+    let restored = uninstall_from(&hooks_dir, kind)?;
+
+    println!("{}", format!("Removed {} hook", kind.file_name()).green());
+    if restored {
+        println!("{}", "Restored the previously installed hook".blue());
+    }
+    Ok(())
+}
+
+/// Hooks in `hooks_dir` that sage installed, recognised by their marker line
+pub fn installed_hooks(hooks_dir: &Path) -> Vec<HookKind> {
+    [HookKind::PrepareCommitMsg, HookKind::CommitMsg]
+        .into_iter()
+        .filter(|kind| is_sage_hook(&hooks_dir.join(kind.file_name())))
+        .collect()
+}
+
+fn is_sage_hook(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .map(|script| script.contains(HOOK_MARKER))
+        .unwrap_or(false)
+}
+
+fn chained_path(hook_path: &Path) -> PathBuf {
+    let mut name = hook_path.as_os_str().to_owned();
+    name.push(CHAINED_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Write the hook script, returning whether an existing hook was chained
+fn install_into(hooks_dir: &Path, kind: HookKind, sage_path: &str) -> Result<bool> {
+    fs::create_dir_all(hooks_dir)?;
+
+    let hook_path = hooks_dir.join(kind.file_name());
+    let chained = chained_path(&hook_path);
+
+    if hook_path.exists() && !is_sage_hook(&hook_path) {
+        if chained.exists() {
+            return Err(SageError::InvalidInput(format!(
+                "Both {} and {} exist; remove one before installing",
+                hook_path.display(), chained.display()
+            )));
+        }
+        fs::rename(&hook_path, &chained)?;
+    }
+
+    fs::write(&hook_path, hook_script(kind, sage_path))?;
+    make_executable(&hook_path)?;
+
+    Ok(chained.exists())
+}
+
+/// Remove the hook script, returning whether a chained hook was restored
+fn uninstall_from(hooks_dir: &Path, kind: HookKind) -> Result<bool> {
+    let hook_path = hooks_dir.join(kind.file_name());
+    if !is_sage_hook(&hook_path) {
+        return Err(SageError::HookNotInstalled { hook: kind.file_name().to_string() });
+    }
+
+    fs::remove_file(&hook_path)?;
+
+    let chained = chained_path(&hook_path);
+    if chained.exists() {
+        fs::rename(&chained, &hook_path)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn hook_script(kind: HookKind, sage_path: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {marker}; remove with 'sage hook uninstall {name}'\n\
+         chained=\"$0{suffix}\"\n\
+         if [ -x \"$chained\" ]; then\n    \"$chained\" \"$@\" || exit $?\nfi\n\
+         \n\
+         SAGE={sage}\n\
+         [ -x \"$SAGE\" ] || SAGE=sage\n\
+         command -v \"$SAGE\" >/dev/null 2>&1 || exit 0\n\
+         {command}\n",
+        marker = HOOK_MARKER,
+        name = kind.file_name(),
+        suffix = CHAINED_SUFFIX,
+        sage = shell_quote(sage_path),
+        command = kind.command(),
+    )
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Only plain `git commit` gets a message; -m/-F, templates, merges, squashes and amends keep theirs
+fn should_generate(source: Option<&str>) -> bool {
+    source.is_none_or(str::is_empty)
+}
+
+/// Whether the message file already has content besides comments and, with
+/// `git commit -v`, the diff below the scissors line
+fn has_message(content: &str, comment_char: &str) -> bool {
+    !clean_message(content, comment_char).is_empty()
+}
+
+/// `prepare-commit-msg` entrypoint: fill `msg_file` with a generated message
+pub async fn run_prepare_commit_msg(msg_file: &str, source: Option<&str>) -> Result<()> {
+    if !should_generate(source) {
+        return Ok(());
+    }
+
+    let current = fs::read_to_string(msg_file)?;
+    let comment_char = get_config_value("core.commentChar")?.unwrap_or_else(|| "#".to_string());
+    if has_message(&current, &comment_char) {
+        return Ok(());
+    }
+
+    let diff = get_diff(false)?;
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    let config = load_config(&get_config_path()?)?;
+    let files_changed = diff.summary();
+    let style = config.default_commit_style();
+    let lint_config = load_lint_config()?;
+
+    eprintln!("{}", "sage: generating commit message...".blue());
+    let (response, problems) = call_ai_validated(&config, Task::Commit, &diff, |truncated_diff| {
+        prompts::build_commit_prompt(style, "", &files_changed, truncated_diff)
+    }, |message| {
+        validate_style(message, style, &lint_config)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }).await?;
+
+    for problem in &problems {
+        eprintln!("{}", format!("sage: warning: {}", problem).yellow());
+    }
+
+    fs::write(msg_file, format!("{}\n{}", response.message, current))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_generate_only_for_plain_commits() {
+        assert!(should_generate(None));
+        assert!(should_generate(Some("")));
+        for source in ["message", "template", "merge", "squash", "commit"] {
+            assert!(!should_generate(Some(source)));
+        }
+    }
+
+    #[test]
+    fn test_has_message() {
+        assert!(!has_message("\n# Please enter the commit message\n#\n", "#"));
+        assert!(has_message("fix: typo\n\n# Please enter the commit message\n", "#"));
+        assert!(!has_message("; comment\n", ";"));
+
+        let verbose = "\n# Please enter the commit message\n\
+            # ------------------------ >8 ------------------------\n\
+            # Do not modify or remove the line above.\n\
+            diff --git a/a.txt b/a.txt\n\
+            +hello\n";
+        assert!(!has_message(verbose, "#"));
+        assert!(has_message(&format!("fix: typo\n{}", verbose), "#"));
+    }
+
+    #[test]
+    fn test_install_chains_and_uninstall_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = "#!/bin/sh\necho existing\n";
+        fs::write(dir.path().join("prepare-commit-msg"), existing).unwrap();
+
+        assert!(install_into(dir.path(), HookKind::PrepareCommitMsg, "/opt/it's/sage").unwrap());
+        let script = fs::read_to_string(dir.path().join("prepare-commit-msg")).unwrap();
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains("SAGE='/opt/it'\\''s/sage'"));
+        assert_eq!(fs::read_to_string(dir.path().join("prepare-commit-msg.pre-sage")).unwrap(), existing);
+        assert_eq!(installed_hooks(dir.path()), vec![HookKind::PrepareCommitMsg]);
+
+        // Reinstalling updates the script without chaining it to itself
+        assert!(install_into(dir.path(), HookKind::PrepareCommitMsg, "sage").unwrap());
+        assert_eq!(fs::read_to_string(dir.path().join("prepare-commit-msg.pre-sage")).unwrap(), existing);
+
+        assert!(uninstall_from(dir.path(), HookKind::PrepareCommitMsg).unwrap());
+        assert_eq!(fs::read_to_string(dir.path().join("prepare-commit-msg")).unwrap(), existing);
+        assert!(installed_hooks(dir.path()).is_empty());
+        assert!(uninstall_from(dir.path(), HookKind::PrepareCommitMsg).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Shared HTTP client for provider (and other API) calls
+//!
+//! The client is built once per process from `NetworkConfig` so connection
+//! pools, proxy settings and extra root certificates are reused across calls.
+
+use std::fs;
+use std::sync::OnceLock;
+use std::time::Duration;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+
+use crate::config::NetworkConfig;
+use crate::error::{Result, SageError};
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Get the process-wide client, building it from `network` on first use
+pub fn client(network: &NetworkConfig) -> Result<&'static Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let client = build_client(network)?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+fn build_client(network: &NetworkConfig) -> Result<Client> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(
+            network.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS)
+        ))
+        .read_timeout(Duration::from_secs(
+            network.read_timeout_secs.unwrap_or(DEFAULT_READ_TIMEOUT_SECS)
+        ));
+
+    // Without an explicit proxy reqwest already honors HTTPS_PROXY/HTTP_PROXY/NO_PROXY
+    if let Some(proxy_url) = &network.proxy {
+        let no_proxy = match &network.no_proxy {
+            Some(list) => NoProxy::from_string(list),
+            None => NoProxy::from_env(),
+        };
+
+        let proxy = Proxy::all(proxy_url)
+            .map_err(|e| SageError::InvalidInput(format!("Invalid proxy URL '{}': {}", proxy_url, e)))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &network.ca_certs {
+        let pem = fs::read(path)
+            .map_err(|e| SageError::IoError(format!("Failed to read CA certificate {}: {}", path, e)))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| SageError::InvalidInput(format!("Invalid CA certificate {}: {}", path, e)))?;
+
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder.build().map_err(|e| SageError::ApiNetworkError {
+        provider: "HTTP client".to_string(),
+        details: e.to_string(),
+    })
+}
+
+/// Convert a request failure into a timeout or network error for `provider`
+pub fn request_error(provider: &str, error: reqwest::Error) -> SageError {
+    if error.is_timeout() {
+        SageError::ApiTimeout {
+            provider: provider.to_string(),
+            details: error.to_string(),
+        }
+    } else {
+        SageError::ApiNetworkError {
+            provider: provider.to_string(),
+            details: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Interactive hunk selection (`sage -i`)
+//!
+//! Walks the unstaged hunks like `git add -p` and stages the chosen ones with
+//! `git apply --cached`, so generation then sees exactly that selection.
+
+use std::io::{self, Write};
+use colored::Colorize;
+
+use crate::error::Result;
+use crate::git::{apply_to_index, get_unstaged_patch, ChangeKind, Diff, DiffFile, Hunk};
+
+const HELP: &str = "\
+y - stage this hunk
+n - do not stage this hunk
+s - split this hunk into its separate changes
+a - stage this and all remaining hunks in the file
+d - skip this and all remaining hunks in the file
+q - quit; stage what was chosen so far
+? - print help";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Answer {
+    Yes,
+    No,
+    Split,
+    All,
+    Done,
+    Quit,
+    Help,
+}
+
+fn parse_answer(input: &str) -> Option<Answer> {
+    match input.trim().to_lowercase().as_str() {
+        "y" => Some(Answer::Yes),
+        "n" => Some(Answer::No),
+        "s" => Some(Answer::Split),
+        "a" => Some(Answer::All),
+        "d" => Some(Answer::Done),
+        "q" => Some(Answer::Quit),
+        "?" => Some(Answer::Help),
+        _ => None,
+    }
+}
+
+/// Ask until one of `allowed` is given; end of input counts as quit
+fn ask(question: &str, allowed: &[Answer]) -> Result<Answer> {
+    let keys: String = allowed.iter()
+        .map(|answer| match answer {
+            Answer::Yes => "y",
+            Answer::No => "n",
+            Answer::Split => "s",
+            Answer::All => "a",
+            Answer::Done => "d",
+            Answer::Quit => "q",
+            Answer::Help => "?",
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    loop {
+        print!("{} [{}]? ", question.blue().bold(), keys);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            return Ok(Answer::Quit);
+        }
+
+        match parse_answer(&input) {
+            Some(Answer::Help) => println!("{}", HELP),
+            Some(answer) if allowed.contains(&answer) => return Ok(answer),
+            _ => println!("{}", HELP),
+        }
+    }
+}
+
+fn print_hunk(hunk: &Hunk) {
+    println!("{}", hunk.header.cyan());
+    for line in &hunk.lines {
+        if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Files whose hunks can be staged separately; the rest are staged whole
+fn selectable_by_hunk(file: &DiffFile) -> bool {
+    file.kind == ChangeKind::Modified && !file.binary && !file.mode_changed() && !file.hunks.is_empty()
+}
+
+/// What the user chose for one file
+enum Choice {
+    Whole,
+    Groups(Vec<Vec<bool>>),
+}
+
+/// Ask about each change group of a split hunk; returns false when the user quits
+fn choose_groups(hunk: &Hunk, keep: &mut [bool]) -> Result<bool> {
+    let count = keep.len();
+    for index in 0..count {
+        let mut only = vec![false; count];
+        only[index] = true;
+        if let Some(part) = hunk.select(&only, 0) {
+            println!();
+            print_hunk(&part);
+        }
+
+        let question = format!("({}/{}) Stage this part", index + 1, count);
+        match ask(&question, &[Answer::Yes, Answer::No, Answer::Quit, Answer::Help])? {
+            Answer::Yes => keep[index] = true,
+            Answer::Quit => return Ok(false),
+            _ => {},
+        }
+    }
+
+    Ok(true)
+}
+
+/// Walk the hunks of `diff` and record what to stage for each file
+fn choose(diff: &Diff) -> Result<Vec<Option<Choice>>> {
+    let mut choices: Vec<Option<Choice>> = diff.files.iter().map(|_| None).collect();
+
+    'files: for (file_index, file) in diff.files.iter().enumerate() {
+        println!("\n{}", file.summary().bold());
+
+        if !selectable_by_hunk(file) {
+            for hunk in &file.hunks {
+                print_hunk(hunk);
+            }
+            match ask("Stage this file", &[Answer::Yes, Answer::No, Answer::Quit, Answer::Help])? {
+                Answer::Yes => choices[file_index] = Some(Choice::Whole),
+                Answer::Quit => break 'files,
+                _ => {},
+            }
+            continue;
+        }
+
+        let mut keep: Vec<Vec<bool>> = file.hunks.iter()
+            .map(|hunk| vec![false; hunk.change_groups().len()])
+            .collect();
+
+        let total = file.hunks.len();
+        let mut hunk_index = 0;
+        while hunk_index < total {
+            let hunk = &file.hunks[hunk_index];
+            println!();
+            print_hunk(hunk);
+
+            let mut allowed = vec![Answer::Yes, Answer::No];
+            if keep[hunk_index].len() > 1 {
+                allowed.push(Answer::Split);
+            }
+            allowed.extend([Answer::All, Answer::Done, Answer::Quit, Answer::Help]);
+
+            let question = format!("({}/{}) Stage this hunk", hunk_index + 1, total);
+            match ask(&question, &allowed)? {
+                Answer::Yes => keep[hunk_index].fill(true),
+                Answer::Split if !choose_groups(hunk, &mut keep[hunk_index])? => {
+                    choices[file_index] = Some(Choice::Groups(keep));
+                    break 'files;
+                },
+                Answer::All => {
+                    keep[hunk_index..].iter_mut().for_each(|groups| groups.fill(true));
+                    break;
+                },
+                Answer::Done => break,
+                Answer::Quit => {
+                    choices[file_index] = Some(Choice::Groups(keep));
+                    break 'files;
+                },
+                _ => {},
+            }
+            hunk_index += 1;
+        }
+
+        choices[file_index] = Some(Choice::Groups(keep));
+    }
+
+    Ok(choices)
+}
+
+/// Patch staging the chosen changes
+fn build_patch(diff: &Diff, choices: &[Option<Choice>]) -> String {
+    diff.files.iter()
+        .zip(choices)
+        .filter_map(|(file, choice)| match choice {
+            Some(Choice::Whole) => Some(file.to_string()),
+            Some(Choice::Groups(keep)) => file.select(keep).map(|selected| selected.to_string()),
+            None => None,
+        })
+        .collect()
+}
+
+/// `sage -i`: pick unstaged hunks under `paths` (all tracked files when empty)
+/// and stage them
+pub fn select_hunks(paths: &[String]) -> Result<()> {
+    let diff = get_unstaged_patch(paths)?;
+    if diff.is_empty() {
+        println!("{}", "No unstaged changes to choose from.".yellow());
+        return Ok(());
+    }
+
+    let choices = choose(&diff)?;
+    let patch = build_patch(&diff, &choices);
+    if patch.is_empty() {
+        println!("\n{}", "No changes selected.".yellow());
+        return Ok(());
+    }
+
+    apply_to_index(&patch)?;
+
+    let staged = Diff::parse(&patch).files.len();
+    println!("\n{}", format!("Staged the selected changes in {} file(s).", staged).green());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,5 +1,5 @@
+ one
+-two
++TWO
+ three
+-four
++FOUR
+ five
+diff --git a/logo.png b/logo.png
+index 1111111..2222222 100644
+Binary files a/logo.png and b/logo.png differ
+";
+
+    #[test]
+    fn test_parse_answer() {
+        assert_eq!(parse_answer("y\n"), Some(Answer::Yes));
+        assert_eq!(parse_answer(" S "), Some(Answer::Split));
+        assert_eq!(parse_answer("yes"), None);
+    }
+
+    #[test]
+    fn test_build_patch_from_choices() {
+        let diff = Diff::parse(DIFF);
+        assert!(selectable_by_hunk(&diff.files[0]));
+        assert!(!selectable_by_hunk(&diff.files[1]));
+
+        let choices = vec![Some(Choice::Groups(vec![vec![true, false]])), None];
+        let patch = build_patch(&diff, &choices);
+        assert!(patch.contains("-two\n+TWO\n three\n four\n five\n"));
+        assert!(!patch.contains("logo.png"));
+
+        let choices = vec![Some(Choice::Groups(vec![vec![false, false]])), Some(Choice::Whole)];
+        assert!(build_patch(&diff, &choices).starts_with("diff --git a/logo.png"));
+    }
+}
@@ -0,0 +1,501 @@
+//! Conventional Commits linting
+//!
+//! Messages are checked against the header grammar `type(scope)!: subject`
+//! plus body and footer rules. Rules are configured per repository in
+//! `.sage-lint.json` at the top of the working tree.
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use colored::Colorize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::call_ai;
+use crate::cli::{CommitStyle, Task};
+use crate::config::{get_config_path, load_config};
+use crate::error::{Result, SageError};
+use crate::git::{get_commits, get_config_value, get_repo_root};
+use crate::prompts::{self, STYLE_BODY_WRAP, STYLE_HEADER_LENGTH};
+
+/// Per-repository lint configuration file, relative to the repository root
+pub const LINT_CONFIG_FILE: &str = ".sage-lint.json";
+
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// Conventional Commits header: `type(scope)!: subject`
+const HEADER_PATTERN: &str = r"^(?P<type>\w+)(?:\((?P<scope>[^()]*)\))?(?P<bang>!)?: (?P<subject>.*)$";
+
+/// Footer line: `Token: value` or `Token #value`
+const FOOTER_PATTERN: &str = r"^(?P<token>(?i:BREAKING[ -]CHANGE)|[A-Za-z][\w-]*)(?:: | #)(?P<value>.*)$";
+
+/// Any line that reads like it starts a footer, including tokens the footer
+/// grammar rejects; other lines in the footer continue the previous value
+const TOKEN_START_PATTERN: &str = r"^[^\s:#][^:#]*(?:: | #)";
+
+/// Headers git and sage generate themselves, which are not linted
+const AUTOGENERATED_PREFIXES: &[&str] = &["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Allowed commit types
+    pub types: Vec<String>,
+    /// Allowed scopes; empty allows any scope
+    pub scopes: Vec<String>,
+    pub require_scope: bool,
+    pub max_header_length: usize,
+    /// Maximum body line length; 0 disables the check
+    pub body_wrap: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            types: ["build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            scopes: Vec::new(),
+            require_scope: false,
+            max_header_length: 72,
+            body_wrap: 72,
+        }
+    }
+}
+
+/// Load `.sage-lint.json` from the repository root, or the defaults
+pub fn load_lint_config() -> Result<LintConfig> {
+    let path = get_repo_root()?.join(LINT_CONFIG_FILE);
+    if !path.exists() {
+        return Ok(LintConfig::default());
+    }
+
+    serde_json::from_str(&fs::read_to_string(&path)?)
+        .map_err(|e| SageError::ConfigInvalidJson(format!("{}: {}", path.display(), e)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub severity: Severity,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    fn error(rule: &'static str, message: impl Into<String>) -> Self {
+        Violation { severity: Severity::Error, rule, message: message.into() }
+    }
+
+    fn warning(rule: &'static str, message: impl Into<String>) -> Self {
+        Violation { severity: Severity::Warning, rule, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+/// Drop comment lines and anything below the scissors line, as git does
+pub fn clean_message(message: &str, comment_char: &str) -> String {
+    let mut lines = Vec::new();
+    for line in message.lines() {
+        if line.starts_with(comment_char) {
+            if line.contains(SCISSORS) {
+                break;
+            }
+            continue;
+        }
+        lines.push(line.trim_end());
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+fn is_breaking_token(token: &str) -> bool {
+    token == "BREAKING CHANGE" || token == "BREAKING-CHANGE"
+}
+
+/// The parts of a Conventional Commits message that release tooling needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub subject: String,
+    /// Marked with `!` or a BREAKING CHANGE footer
+    pub breaking: bool,
+    /// Description from the BREAKING CHANGE footer, if any
+    pub breaking_note: Option<String>,
+}
+
+/// Parse a commit message's header and breaking-change footer; None when the
+/// header is not in Conventional Commits form
+pub fn parse_conventional(message: &str) -> Option<ConventionalCommit> {
+    let header_re = Regex::new(HEADER_PATTERN).unwrap();
+    let message = message.trim();
+    let captures = header_re.captures(message.lines().next()?)?;
+
+    let breaking_note = message.lines()
+        .skip(1)
+        .find_map(|line| {
+            let (token, value) = line.split_once(": ")?;
+            is_breaking_token(token).then(|| value.trim().to_string())
+        })
+        .filter(|note| !note.is_empty());
+
+    Some(ConventionalCommit {
+        commit_type: captures["type"].to_lowercase(),
+        scope: captures.name("scope")
+            .map(|scope| scope.as_str().trim().to_string())
+            .filter(|scope| !scope.is_empty()),
+        subject: captures["subject"].trim().to_string(),
+        breaking: captures.name("bang").is_some() || breaking_note.is_some(),
+        breaking_note,
+    })
+}
+
+/// Check a commit message; comment lines (starting with `comment_char`) are ignored
+pub fn lint_message(message: &str, config: &LintConfig, comment_char: &str) -> Vec<Violation> {
+    let header_re = Regex::new(HEADER_PATTERN).unwrap();
+    let footer_re = Regex::new(FOOTER_PATTERN).unwrap();
+    let token_start_re = Regex::new(TOKEN_START_PATTERN).unwrap();
+
+    let message = clean_message(message, comment_char);
+    let lines: Vec<&str> = message.lines().collect();
+    let Some(header) = lines.first() else {
+        return vec![Violation::error("header-empty", "Message is empty")];
+    };
+
+    if AUTOGENERATED_PREFIXES.iter().any(|prefix| header.starts_with(prefix)) {
+        return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+
+    if header.chars().count() > config.max_header_length {
+        violations.push(Violation::error("header-max-length", format!(
+            "Header is {} characters, the limit is {}", header.chars().count(), config.max_header_length
+        )));
+    }
+
+    match header_re.captures(header) {
+        None => violations.push(Violation::error("header-format",
+            "Header must look like 'type(scope): subject' or 'type: subject'")),
+        Some(captures) => {
+            let commit_type = &captures["type"];
+            if !config.types.iter().any(|t| t == commit_type) {
+                violations.push(Violation::error("type-enum", format!(
+                    "Type '{}' is not allowed; use one of: {}", commit_type, config.types.join(", ")
+                )));
+            }
+
+            match captures.name("scope").map(|s| s.as_str().trim()) {
+                Some("") => violations.push(Violation::error("scope-empty", "Scope parentheses are empty")),
+                None if config.require_scope => violations.push(Violation::error("scope-empty", "A scope is required")),
+                Some(scope) if !config.scopes.is_empty() => {
+                    for part in scope.split(',').map(str::trim) {
+                        if !config.scopes.iter().any(|s| s == part) {
+                            violations.push(Violation::error("scope-enum", format!(
+                                "Scope '{}' is not allowed; use one of: {}", part, config.scopes.join(", ")
+                            )));
+                        }
+                    }
+                },
+                _ => {},
+            }
+
+            let subject = captures["subject"].trim();
+            if subject.is_empty() {
+                violations.push(Violation::error("subject-empty", "Subject is empty"));
+            } else if subject.ends_with('.') {
+                violations.push(Violation::warning("subject-full-stop", "Subject should not end with a period"));
+            }
+        },
+    }
+
+    if lines.len() < 2 {
+        return violations;
+    }
+
+    if !lines[1].is_empty() {
+        violations.push(Violation::error("body-leading-blank", "Leave a blank line between the header and the body"));
+    }
+
+    // The footer is the last paragraph, when it starts with a token line and
+    // every token-like line in it is a valid footer; prose such as
+    // "Note that: ..." keeps the paragraph in the body
+    let body: Vec<&str> = lines[1..].to_vec();
+    let footer_start = body.iter()
+        .rposition(|line| line.is_empty())
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let paragraph = &body[footer_start..];
+    let has_footer = paragraph.first().is_some_and(|line| footer_re.is_match(line))
+        && paragraph.iter()
+            .filter(|line| token_start_re.is_match(line))
+            .all(|line| footer_re.is_match(line));
+    let (body_lines, footer_lines) = if has_footer {
+        body.split_at(footer_start)
+    } else {
+        (&body[..], &[][..])
+    };
+
+    for line in body_lines {
+        let upper = line.to_uppercase();
+        if upper.starts_with("BREAKING CHANGE") || upper.starts_with("BREAKING-CHANGE") {
+            violations.push(Violation::error("footer-format", "BREAKING CHANGE belongs in the footer, the last paragraph"));
+        } else if config.body_wrap > 0 && line.chars().count() > config.body_wrap && !line.contains("://") {
+            violations.push(Violation::warning("body-max-line-length", format!(
+                "Body line is longer than {} characters: '{}'", config.body_wrap, line
+            )));
+        }
+    }
+
+    // Lines that don't start a token continue the previous footer's value
+    for captures in footer_lines.iter().filter_map(|line| footer_re.captures(line)) {
+        let token = &captures["token"];
+        if token.eq_ignore_ascii_case("breaking change") || token.eq_ignore_ascii_case("breaking-change") {
+            if !is_breaking_token(token) {
+                violations.push(Violation::error("footer-format", "BREAKING CHANGE must be uppercase"));
+            } else if captures["value"].trim().is_empty() {
+                violations.push(Violation::error("footer-format", "BREAKING CHANGE needs a description"));
+            }
+        }
+    }
+
+    violations
+}
+
+/// Check a generated message against what `style` asked the model for, on
+/// top of the repository's lint rules
+pub fn validate_style(message: &str, style: Option<CommitStyle>, lint_config: &LintConfig) -> Vec<Violation> {
+    let (max_header_length, needs_body) = match style {
+        Some(CommitStyle::Standard) | None => (lint_config.max_header_length, false),
+        Some(CommitStyle::Detailed) => (STYLE_HEADER_LENGTH, true),
+        Some(CommitStyle::Short) => (STYLE_HEADER_LENGTH, false),
+    };
+    // A stricter or disabled (0) repository wrap wins over the style's
+    let body_wrap = match lint_config.body_wrap {
+        0 => 0,
+        wrap => wrap.min(STYLE_BODY_WRAP),
+    };
+    let config = LintConfig {
+        max_header_length: max_header_length.min(lint_config.max_header_length),
+        body_wrap,
+        ..lint_config.clone()
+    };
+
+    let mut violations = lint_message(message, &config, "#");
+    let line_count = message.trim().lines().count();
+    if needs_body && line_count < 3 {
+        violations.push(Violation::error("body-empty", "Add a blank line and a body explaining the change"));
+    } else if !needs_body && line_count > 1 {
+        violations.push(Violation::error("single-line", "Message must be a single line"));
+    }
+
+    violations
+}
+
+fn print_violations(violations: &[Violation]) {
+    for violation in violations {
+        match violation.severity {
+            Severity::Error => println!("  {} {}", "✖".red(), violation),
+            Severity::Warning => println!("  {} {}", "⚠".yellow(), violation),
+        }
+    }
+}
+
+fn error_count(violations: &[Violation]) -> usize {
+    violations.iter().filter(|v| v.severity == Severity::Error).count()
+}
+
+/// Ask the AI for a version of `message` that fixes `violations`
+async fn suggest_fix(message: &str, violations: &[Violation], lint_config: &LintConfig) -> Result<String> {
+    let config = load_config(&get_config_path()?)?;
+    let violations = violations.iter()
+        .map(|v| format!("- {}", v))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = prompts::build_lint_fix_prompt(message, &violations, &lint_config.types);
+    Ok(call_ai(&config, Task::Commit, &prompt).await?.message)
+}
+
+/// `sage lint`: lint a message file, a single commit or a range of commits
+pub async fn run_lint(range: Option<&str>, file: Option<&str>, fix: bool) -> Result<()> {
+    let lint_config = load_lint_config()?;
+
+    match file {
+        Some(path) => lint_file(path, &lint_config, fix).await,
+        None => lint_range(range.unwrap_or("HEAD"), &lint_config, fix).await,
+    }
+}
+
+async fn lint_file(path: &str, lint_config: &LintConfig, fix: bool) -> Result<()> {
+    let comment_char = get_config_value("core.commentChar")?.unwrap_or_else(|| "#".to_string());
+    let message = fs::read_to_string(path)?;
+    let violations = lint_message(&message, lint_config, &comment_char);
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}", "Commit message does not follow Conventional Commits:".yellow().bold());
+    print_violations(&violations);
+
+    let errors = error_count(&violations);
+    if errors == 0 {
+        return Ok(());
+    }
+
+    if fix {
+        let suggestion = suggest_fix(&clean_message(&message, &comment_char), &violations, lint_config).await?;
+        println!("\n{}", "Suggested message:".green().bold());
+        println!("{}", suggestion);
+
+        if io::stdin().is_terminal() && confirm("Use the suggested message?")? {
+            fs::write(path, format!("{}\n", suggestion))?;
+            if error_count(&lint_message(&suggestion, lint_config, &comment_char)) == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(SageError::LintFailed { errors })
+}
+
+async fn lint_range(range: &str, lint_config: &LintConfig, fix: bool) -> Result<()> {
+    let commits = get_commits(range)?;
+    let mut errors = 0;
+
+    for commit in &commits {
+        let violations = lint_message(&commit.message, lint_config, "#");
+        if violations.is_empty() {
+            continue;
+        }
+
+        let subject = commit.message.lines().next().unwrap_or_default();
+        println!("{} {}", commit.sha.get(..7).unwrap_or(&commit.sha).yellow(), subject);
+        print_violations(&violations);
+
+        if fix && error_count(&violations) > 0 {
+            let suggestion = suggest_fix(&commit.message, &violations, lint_config).await?;
+            println!("  {} {}", "Suggested:".green(), suggestion);
+        }
+
+        errors += error_count(&violations);
+    }
+
+    if errors > 0 {
+        return Err(SageError::LintFailed { errors });
+    }
+
+    println!("{}", format!("{} commit(s) checked, no errors", commits.len()).green());
+    Ok(())
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(message: &str) -> Vec<&'static str> {
+        lint_message(message, &LintConfig::default(), "#")
+            .into_iter()
+            .map(|v| v.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_messages() {
+        assert!(rules("feat(api): add pagination").is_empty());
+        assert!(rules("fix!: drop legacy flag\n\nThe flag was unused.\n\nBREAKING CHANGE: --legacy is gone\nRefs #42").is_empty());
+        assert!(rules("Merge branch 'main' into feature").is_empty());
+        assert!(rules("docs: fix typo\n# Please enter the commit message\n").is_empty());
+    }
+
+    #[test]
+    fn test_header_rules() {
+        assert_eq!(rules("added stuff"), vec!["header-format"]);
+        assert_eq!(rules("feature: add stuff"), vec!["type-enum"]);
+        assert_eq!(rules("feat(): add stuff"), vec!["scope-empty"]);
+        assert_eq!(rules("feat: add stuff."), vec!["subject-full-stop"]);
+        assert_eq!(rules(&format!("feat: {}", "a".repeat(80))), vec!["header-max-length"]);
+    }
+
+    #[test]
+    fn test_scope_config() {
+        let config = LintConfig {
+            scopes: vec!["api".to_string(), "cli".to_string()],
+            require_scope: true,
+            ..LintConfig::default()
+        };
+        assert!(lint_message("feat(api,cli): add flag", &config, "#").is_empty());
+        assert_eq!(lint_message("feat(db): add index", &config, "#")[0].rule, "scope-enum");
+        assert_eq!(lint_message("feat: add index", &config, "#")[0].rule, "scope-empty");
+    }
+
+    #[test]
+    fn test_body_and_footer_rules() {
+        assert_eq!(rules("feat: add x\nbody without blank line"), vec!["body-leading-blank"]);
+        assert_eq!(rules(&format!("feat: add x\n\n{}", "word ".repeat(20))), vec!["body-max-line-length"]);
+        assert_eq!(rules("feat: add x\n\nbreaking change: removed y"), vec!["footer-format"]);
+        assert_eq!(rules("feat: add x\n\nReviewed-by: Jane\nand the rest of her review"), Vec::<&str>::new());
+        assert_eq!(rules("feat: add x\n\nNote: the cache moved\nSide effect: old entries are dropped"), Vec::<&str>::new());
+        assert_eq!(rules("feat: add x\n\nBREAKING CHANGE: removed y\n  and z too"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_parse_conventional() {
+        let commit = parse_conventional("feat(api)!: drop v1 routes\n\nBody.\n\nBREAKING CHANGE: v1 clients must upgrade").unwrap();
+        assert_eq!(commit.commit_type, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+        assert_eq!(commit.subject, "drop v1 routes");
+        assert!(commit.breaking);
+        assert_eq!(commit.breaking_note.as_deref(), Some("v1 clients must upgrade"));
+
+        let commit = parse_conventional("fix: handle empty input").unwrap();
+        assert_eq!(commit.scope, None);
+        assert!(!commit.breaking);
+
+        assert_eq!(parse_conventional("Update README"), None);
+    }
+
+    #[test]
+    fn test_validate_style() {
+        let config = LintConfig::default();
+        let style_rules = |message: &str, style| -> Vec<&'static str> {
+            validate_style(message, style, &config).into_iter().map(|v| v.rule).collect()
+        };
+        let long_header = format!("feat: {}", "a".repeat(50));
+
+        assert!(style_rules(&long_header, None).is_empty());
+        assert_eq!(style_rules(&long_header, Some(CommitStyle::Short)), vec!["header-max-length"]);
+        assert_eq!(style_rules("feat: add x\n\nWhy.", Some(CommitStyle::Standard)), vec!["single-line"]);
+        assert_eq!(style_rules("feat: add x", Some(CommitStyle::Detailed)), vec!["body-empty"]);
+        assert!(style_rules("feat: add x\n\n- Explain why", Some(CommitStyle::Detailed)).is_empty());
+        assert_eq!(style_rules("Added x", Some(CommitStyle::Short)), vec!["header-format"]);
+
+        let long_body = format!("feat: add x\n\n- {}", "word ".repeat(16).trim_end());
+        assert_eq!(style_rules(&long_body, Some(CommitStyle::Detailed)), vec!["body-max-line-length"]);
+        let unwrapped = LintConfig { body_wrap: 0, ..LintConfig::default() };
+        assert!(validate_style(&long_body, Some(CommitStyle::Detailed), &unwrapped).is_empty());
+        let loose = LintConfig { body_wrap: 100, ..LintConfig::default() };
+        assert_eq!(validate_style(&long_body, Some(CommitStyle::Detailed), &loose)[0].rule, "body-max-line-length");
+    }
+}
@@ -4,26 +4,40 @@ mod config;
 mod git;
 mod ai;
 mod prompts;
+mod http;
+mod doctor;
+mod hook;
+mod interactive;
+mod lint;
+mod split;
+mod reword;
+mod pr;
+mod forge;
+mod changelog;
+mod release;
+mod explain;
+mod review;
+mod progress;
 
 use std::process::exit;
-use std::env;
-use std::fs;
 use std::io::{self, Write};
 use std::time::Instant;
 use colored::Colorize;
 use clap::{Parser, CommandFactory};
 use clap_complete::{generate, Shell};
-use indicatif::{ProgressBar, ProgressStyle};
 
 use error::{Result, SageError};
-use cli::{Cli, Commands, ConfigArgs};
-use config::{get_config_path, load_config, save_config};
+use cli::{ChangelogArgs, Cli, Commands, CommitStyle, ConfigArgs, ExplainArgs, HookAction, PrArgs, ReleaseArgs, ReviewArgs, SamplingArgs, SquashArgs, Task};
+use config::{edit_text, get_config_path, load_config, save_config};
 use git::{
     is_git_repo, get_diff, get_files_changed, stage_files, stage_all_files,
-    has_staged_changes, commit_changes, push_changes, show_changes, smart_truncate_diff,
-    get_current_branch, create_and_checkout_branch, branch_exists
+    has_staged_changes, commit_changes, push_changes, show_changes,
+    get_current_branch, create_and_checkout_branch, branch_exists, select_backend,
+    get_commits, get_head_sha, get_merge_base, get_range_diff, get_upstream_branch, is_ancestor,
+    reset_soft, CommitOptions
 };
-use ai::call_ai;
+use ai::{call_ai_validated, call_ai_with_diff, default_model, list_models, DETERMINISTIC_SEED};
+use progress::spinner;
 
 #[tokio::main]
 async fn main() {
@@ -34,6 +48,13 @@ async fn main() {
         exit(1);
     }
 
+    // Config errors are reported by the command itself; here they just mean the default backend
+    let git_backend = get_config_path()
+        .and_then(|path| load_config(&path))
+        .ok()
+        .and_then(|config| config.git_backend);
+    select_backend(git_backend.as_deref());
+
     if let Err(e) = run_app(cli).await {
         eprintln!("{} {}", "Error:".red().bold(), e);
         exit(1);
@@ -51,8 +72,48 @@ async fn run_app(cli: Cli) -> Result<()> {
         Some(Commands::Diff { files, all }) => {
             show_diff_command(files, *all)?;
         },
-        Some(Commands::Branch { files, all, context, yes, verbose }) => {
-            run_branch_flow(files, *all, context.as_deref(), *yes, *verbose).await?;
+        Some(Commands::Branch { files, all, context, yes, verbose, sampling }) => {
+            run_branch_flow(files, *all, context.as_deref(), *yes, *verbose, sampling, cli.record.as_deref()).await?;
+        },
+        Some(Commands::Models { provider, select }) => {
+            run_models_command(provider.as_deref(), *select).await?;
+        },
+        Some(Commands::Doctor) => {
+            doctor::run_doctor().await?;
+        },
+        Some(Commands::Lint { range, file, fix }) => {
+            lint::run_lint(range.as_deref(), file.as_deref(), *fix).await?;
+        },
+        Some(Commands::Split { context, style, dry_run, yes, sampling }) => {
+            run_split_flow(&cli, context.as_deref(), *style, *dry_run, *yes, sampling).await?;
+        },
+        Some(Commands::Reword { range, context, style, force, dry_run, yes, sampling }) => {
+            run_reword_flow(&cli, range, context.as_deref(), *style, *force, *dry_run, *yes, sampling).await?;
+        },
+        Some(Commands::Squash(args)) => {
+            run_squash_flow(&cli, args).await?;
+        },
+        Some(Commands::Pr(args)) => {
+            run_pr_flow(&cli, args).await?;
+        },
+        Some(Commands::Changelog(args)) => {
+            run_changelog_flow(&cli, args).await?;
+        },
+        Some(Commands::Release(args)) => {
+            run_release_flow(&cli, args).await?;
+        },
+        Some(Commands::Review(args)) => {
+            run_review_flow(&cli, args).await?;
+        },
+        Some(Commands::Explain(args)) => {
+            run_explain_flow(&cli, args).await?;
+        },
+        Some(Commands::Hook { action }) => match action {
+            HookAction::Install { hook } => hook::install_hook(*hook)?,
+            HookAction::Uninstall { hook } => hook::uninstall_hook(*hook)?,
+            HookAction::Run { msg_file, source, .. } => {
+                hook::run_prepare_commit_msg(msg_file, source.as_deref()).await?;
+            },
         },
         Some(Commands::Completion { shell }) => {
             generate_completions(*shell);
@@ -88,7 +149,18 @@ fn handle_config_command(args: &ConfigArgs) -> Result<()> {
 
     let mut updated = false;
 
-    if let Some(provider) = &args.provider {
+    if let Some(task) = args.clear_task {
+        config.clear_task_route(task)?;
+        println!("{}", format!("Route cleared for task: {}", task.as_str()).green());
+        updated = true;
+    } else if let Some(task) = args.task {
+        if args.provider.is_none() && args.model.is_none() {
+            return Err(SageError::InvalidInput("--provider or --model required with --task".to_string()));
+        }
+        config.set_task_route(task, args.provider.clone(), args.model.clone())?;
+        println!("{}", format!("Route updated for task: {}", task.as_str()).green());
+        updated = true;
+    } else if let Some(provider) = &args.provider {
         if let Some(key) = &args.key {
             config.set_provider(provider, Some(key.clone()), args.model.clone())?;
             println!("{}", format!("Provider set to: {} with new API key", provider).green());
@@ -98,6 +170,21 @@ fn handle_config_command(args: &ConfigArgs) -> Result<()> {
             println!("{}", format!("Provider set to: {}", provider).green());
             updated = true;
         }
+
+        if has_sampling_settings(args) {
+            config.set_sampling(provider, args.temperature, args.top_p, args.seed, &args.stop)?;
+            println!("{}", format!("Sampling settings updated for provider: {}", provider).green());
+        }
+
+        if let Some(url) = &args.base_url {
+            config.set_base_url(provider, url)?;
+            println!("{}", format!("Base URL set to: {}", url).green());
+        }
+
+        if let Some(dir) = &args.fixtures_dir {
+            config.set_fixtures_dir(dir)?;
+            println!("{}", format!("Replay fixtures directory set to: {}", dir).green());
+        }
     } else if let Some(provider) = &args.update_key {
         if let Some(key) = &args.key {
             config.update_key(provider, key)?;
@@ -116,6 +203,50 @@ fn handle_config_command(args: &ConfigArgs) -> Result<()> {
         config.set_provider(&provider_name, None, Some(model.clone()))?;
         println!("{}", format!("Model updated for provider: {}", provider_name).green());
         updated = true;
+    } else if has_sampling_settings(args) {
+        let provider_name = config.active_provider.clone();
+        config.set_sampling(&provider_name, args.temperature, args.top_p, args.seed, &args.stop)?;
+        println!("{}", format!("Sampling settings updated for provider: {}", provider_name).green());
+        updated = true;
+    } else if let Some(url) = &args.base_url {
+        let provider_name = config.active_provider.clone();
+        config.set_base_url(&provider_name, url)?;
+        println!("{}", format!("Base URL updated for provider: {}", provider_name).green());
+        updated = true;
+    } else if let Some(dir) = &args.fixtures_dir {
+        config.set_fixtures_dir(dir)?;
+        println!("{}", format!("Replay fixtures directory set to: {}", dir).green());
+        updated = true;
+    } else if args.proxy.is_some() || args.no_proxy.is_some() || !args.ca_cert.is_empty()
+        || args.connect_timeout.is_some() || args.read_timeout.is_some() {
+        config.set_network(
+            args.proxy.clone(),
+            args.no_proxy.clone(),
+            &args.ca_cert,
+            args.connect_timeout,
+            args.read_timeout,
+        )?;
+        println!("{}", "Network settings updated".green());
+        updated = true;
+    } else if let Some(author) = &args.author {
+        config.set_commit_author(author)?;
+        println!("{}", format!("Commit author set to: {}", author).green());
+        updated = true;
+    } else if let Some(backend) = &args.git_backend {
+        config.set_git_backend(backend)?;
+        println!("{}", format!("Git backend set to: {}", backend).green());
+        updated = true;
+    } else if let Some(forge) = args.forge {
+        if args.forge_token.is_none() && args.forge_api_url.is_none() {
+            return Err(SageError::InvalidInput("--forge-token or --forge-api-url required with --forge".to_string()));
+        }
+        config.set_forge(forge, args.forge_token.clone(), args.forge_api_url.clone())?;
+        println!("{}", format!("Forge settings updated for: {}", forge.as_str()).green());
+        updated = true;
+    } else if let Some(base) = &args.pr_base {
+        config.set_pr_base(base)?;
+        println!("{}", format!("PR base branch set to: {}", base).green());
+        updated = true;
     } else if let Some(tokens) = args.max_tokens {
         config.set_max_tokens(tokens)?;
         println!("{}", format!("Max tokens set to: {}", tokens).green());
@@ -141,6 +272,26 @@ fn handle_config_command(args: &ConfigArgs) -> Result<()> {
     Ok(())
 }
 
+fn has_sampling_settings(args: &ConfigArgs) -> bool {
+    args.temperature.is_some() || args.top_p.is_some() || args.seed.is_some() || !args.stop.is_empty()
+}
+
+/// Apply per-run sampling flags to every provider for this invocation only
+fn apply_sampling_args(config: &mut config::Config, args: &SamplingArgs) -> Result<()> {
+    let (temperature, seed) = if args.deterministic {
+        (Some(0.0), args.seed.or(Some(DETERMINISTIC_SEED)))
+    } else {
+        (args.temperature, args.seed)
+    };
+
+    let providers: Vec<String> = config.providers.keys().cloned().collect();
+    for provider in providers {
+        config.set_sampling(&provider, temperature, args.top_p, seed, &args.stop)?;
+    }
+
+    Ok(())
+}
+
 fn use_provider(provider: &str) -> Result<()> {
     let config_path = get_config_path()?;
     let mut config = load_config(&config_path)?;
@@ -158,25 +309,129 @@ fn use_provider(provider: &str) -> Result<()> {
     Ok(())
 }
 
+async fn run_models_command(provider: Option<&str>, select: bool) -> Result<()> {
+    let config_path = get_config_path()?;
+    let mut config = load_config(&config_path)?;
+
+    let mut provider_names: Vec<String> = match provider {
+        Some(name) => {
+            if !config.providers.contains_key(name) {
+                return Err(SageError::ConfigProviderNotConfigured {
+                    provider: name.to_string()
+                });
+            }
+            vec![name.to_string()]
+        },
+        None => config.providers.keys()
+            .filter(|name| default_model(name).is_some())
+            .cloned()
+            .collect(),
+    };
+    provider_names.sort();
+
+    let spinner = spinner("Fetching available models...");
+
+    let mut listings = Vec::new();
+    for name in &provider_names {
+        let result = list_models(&config, name).await;
+        listings.push((name.clone(), result));
+    }
+
+    spinner.finish_and_clear();
+
+    for (name, result) in &listings {
+        let active_model = config.providers[name].model.clone()
+            .or_else(|| default_model(name).map(|m| m.to_string()))
+            .unwrap_or_default();
+
+        println!("{}{}",
+            name.blue().bold(),
+            if name == &config.active_provider { " (active provider)" } else { "" }
+        );
+
+        match result {
+            Ok(models) if models.is_empty() => println!("  {}", "No models returned".yellow()),
+            Ok(models) => {
+                for model in models {
+                    if model == &active_model {
+                        println!("  {} {}", model.green().bold(), "(active)".green());
+                    } else {
+                        println!("  {}", model);
+                    }
+                }
+                if !models.contains(&active_model) {
+                    println!("  {}", format!("Warning: configured model '{}' is not in this list", active_model).yellow());
+                }
+            },
+            Err(e) => println!("  {}", format!("Could not list models: {}", e.to_string().lines().next().unwrap_or_default()).red()),
+        }
+        println!();
+    }
+
+    if select {
+        let name = provider.unwrap_or(&config.active_provider).to_string();
+        let models = match listings.iter().find(|(n, _)| n == &name) {
+            Some((_, Ok(models))) if !models.is_empty() => models.clone(),
+            _ => return Err(SageError::InvalidInput(
+                format!("No models available to select for provider '{}'", name)
+            )),
+        };
+
+        if let Some(model) = select_model(&name, &models)? {
+            config.set_model(&name, &model)?;
+            save_config(&config, &config_path)?;
+            println!("{}", format!("Model for {} set to: {}", name, model).green());
+        } else {
+            println!("{}", "Model unchanged.".yellow());
+        }
+    }
+
+    Ok(())
+}
+
+fn select_model(provider: &str, models: &[String]) -> Result<Option<String>> {
+    println!("{}", format!("Select a model for {}:", provider).blue());
+    for (i, model) in models.iter().enumerate() {
+        println!("  {}) {}", i + 1, model);
+    }
+    print!("Choice [1-{}, empty to keep current]: ", models.len());
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    match input.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= models.len() => Ok(Some(models[n - 1].clone())),
+        _ => Err(SageError::InvalidInput(format!("Invalid model choice: {}", input))),
+    }
+}
+
 fn show_diff_command(files: &[String], all: bool) -> Result<()> {
     if !files.is_empty() {
         stage_files(files)?;
     }
 
     let diff = get_diff(all)?;
-    let files_changed = get_files_changed(all)?;
 
-    if diff.trim().is_empty() {
+    if diff.is_empty() {
         println!("{}", "No changes to display.".yellow());
         return Ok(());
     }
 
-    show_changes(&diff, &files_changed)
+    show_changes(&diff)
 }
 
 async fn run_commit_flow(cli: &Cli) -> Result<()> {
     let config_path = get_config_path()?;
-    let config = load_config(&config_path)?;
+    let mut config = load_config(&config_path)?;
+    config.record_dir = cli.record.clone();
+    config.use_tools = cli.tools;
+    apply_sampling_args(&mut config, &cli.sampling)?;
 
     let should_stage_all = cli.all || config.preferences.auto_stage_all.unwrap_or(false);
     let should_show_diff = cli.show_diff || config.preferences.show_diff.unwrap_or(false);
@@ -184,7 +439,9 @@ async fn run_commit_flow(cli: &Cli) -> Result<()> {
     let is_verbose = cli.verbose || config.preferences.verbose.unwrap_or(false);
     let should_push = cli.push || config.preferences.auto_push.unwrap_or(false);
 
-    if should_stage_all || !cli.files.is_empty() {
+    if cli.interactive {
+        interactive::select_hunks(&cli.files)?;
+    } else if should_stage_all || !cli.files.is_empty() {
         if should_stage_all {
             if is_verbose {
                 println!("{}", "Staging all changes...".blue());
@@ -202,13 +459,21 @@ async fn run_commit_flow(cli: &Cli) -> Result<()> {
         return Err(SageError::GitNoStagedChanges);
     }
 
+    if cli.review {
+        let context = cli.context.as_deref().unwrap_or("");
+        if !review::review_before_commit(&config, context, should_skip_confirm).await? {
+            println!("{}", "Commit aborted.".yellow());
+            return Ok(());
+        }
+    }
+
     if let Some(message) = &cli.message {
         if cli.dry_run {
             println!("{}", "Would commit with message:".blue());
             println!("{}", message);
         } else {
-            commit_changes(message, cli.amend)?;
-            println!("{}", "Changes committed successfully!".green());
+            let sha = commit_changes(message, &commit_options(cli, &config))?;
+            print_committed(&sha);
 
             if should_push {
                 push_changes(cli.force_push)?;
@@ -223,45 +488,36 @@ async fn run_commit_flow(cli: &Cli) -> Result<()> {
 
     let start = Instant::now();
     let diff = get_diff(false)?;
-    let files_changed = get_files_changed(false)?;
 
-    if diff.trim().is_empty() {
+    if diff.is_empty() {
         return Err(SageError::GitNoChanges);
     }
 
     if should_show_diff {
-        show_changes(&diff, &files_changed)?;
+        show_changes(&diff)?;
     }
 
-    let truncated_diff = smart_truncate_diff(&diff);
+    let files_changed = diff.summary();
 
     let context_str = cli.context.as_deref().unwrap_or("");
-    let commit_style = cli.style.or_else(|| {
-        config.default_style.as_ref().and_then(|s| match s.as_str() {
-            "standard" => Some(cli::CommitStyle::Standard),
-            "detailed" => Some(cli::CommitStyle::Detailed),
-            "short" => Some(cli::CommitStyle::Short),
-            _ => None,
-        })
-    });
-
-    let prompt = prompts::build_commit_prompt(
-        commit_style,
-        context_str,
-        &files_changed,
-        &truncated_diff,
-    );
-
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.blue} {msg}")
-            .unwrap()
-    );
-    spinner.set_message("Generating commit message using AI...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-
-    let response = call_ai(&config, &prompt).await?;
+    let commit_style = cli.style.or_else(|| config.default_commit_style());
+
+    let spinner = spinner("Generating commit message using AI...");
+
+    let lint_config = lint::load_lint_config()?;
+    let (response, problems) = call_ai_validated(&config, Task::Commit, &diff, |truncated_diff| {
+        prompts::build_commit_prompt(
+            commit_style,
+            context_str,
+            &files_changed,
+            truncated_diff,
+        )
+    }, |message| {
+        lint::validate_style(message, commit_style, &lint_config)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }).await?;
 
     spinner.finish_and_clear();
 
@@ -274,26 +530,24 @@ async fn run_commit_flow(cli: &Cli) -> Result<()> {
     println!("{}", response.message);
 
     if is_verbose {
-        println!("\n{}", format!("Tokens: {} in / {} out / {} total",
-            response.usage.input_tokens,
-            response.usage.output_tokens,
-            response.usage.total_tokens
-        ).cyan());
+        println!("\n{}", format!("Tokens: {}", response.usage).cyan());
     }
 
 
     if cli.dry_run {
+        print_style_warnings(&problems);
         println!("\n{}", "Dry run - changes were not committed.".yellow());
     } else {
         let (should_commit, final_message) = if should_skip_confirm {
+            print_style_warnings(&problems);
             (true, response.message.clone())
         } else {
-            confirm_commit(&response.message)?
+            confirm_commit(&response.message, &problems)?
         };
 
         if should_commit {
-            commit_changes(&final_message, cli.amend)?;
-            println!("{}", "Changes committed successfully!".green());
+            let sha = commit_changes(&final_message, &commit_options(cli, &config))?;
+            print_committed(&sha);
 
             if should_push {
                 push_changes(cli.force_push)?;
@@ -306,12 +560,222 @@ async fn run_commit_flow(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+/// Commit options from the command line, falling back to saved preferences
+fn commit_options(cli: &Cli, config: &config::Config) -> CommitOptions {
+    let sign_by_default = config.preferences.sign_commits.unwrap_or(false);
+
+    CommitOptions {
+        amend: cli.amend,
+        signoff: cli.signoff || config.preferences.signoff.unwrap_or(false),
+        sign_key: cli.gpg_sign.clone().or_else(|| sign_by_default.then(String::new)),
+        author: cli.author.clone().or_else(|| config.commit_author.clone()),
+        date: cli.date.clone(),
+        no_verify: cli.no_verify || config.preferences.no_verify.unwrap_or(false),
+    }
+}
+
+fn print_committed(sha: &str) {
+    let short_sha = sha.get(..7).unwrap_or(sha);
+    println!("{}", format!("Changes committed successfully! ({})", short_sha).green());
+}
+
+async fn run_squash_flow(cli: &Cli, args: &SquashArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    let merge_base = get_merge_base(&args.base, "HEAD")?;
+    let commits = get_commits(&format!("{}..HEAD", merge_base))?;
+    let Some(oldest) = commits.last() else {
+        return Err(SageError::InvalidInput(format!("No commits between {} and HEAD to squash", args.base)));
+    };
+
+    if args.commit {
+        if has_staged_changes()? {
+            return Err(SageError::InvalidInput(
+                "Commit or unstage your staged changes before squashing".to_string()
+            ));
+        }
+        if !args.force {
+            if let Some(upstream) = get_upstream_branch()? {
+                if is_ancestor(&oldest.sha, &upstream)? {
+                    return Err(SageError::RewritePublished { upstream });
+                }
+            }
+        }
+    }
+
+    let diff = get_range_diff(&merge_base, "HEAD")?;
+    let files_changed = diff.summary();
+    let commit_list = commits.iter()
+        .rev()
+        .map(|commit| format!("- {}", commit.message.trim().replace('\n', "\n  ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let context_str = args.context.as_deref().unwrap_or("");
+    let commit_style = args.style.or_else(|| config.default_commit_style());
+
+    let spinner = spinner(format!("Generating a message for {} commits using AI...", commits.len()));
+
+    let lint_config = lint::load_lint_config()?;
+    let (response, problems) = call_ai_validated(&config, Task::Commit, &diff, |truncated_diff| {
+        prompts::build_squash_prompt(
+            commit_style,
+            context_str,
+            &commit_list,
+            &files_changed,
+            truncated_diff,
+        )
+    }, |message| {
+        lint::validate_style(message, commit_style, &lint_config)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }).await?;
+
+    spinner.finish_and_clear();
+
+    println!("\n{}", format!("Squash message for {} commits:", commits.len()).green().bold());
+    println!("{}", response.message);
+
+    if !args.commit {
+        print_style_warnings(&problems);
+        return Ok(());
+    }
+
+    let skip_confirm = args.yes || config.preferences.skip_confirmation.unwrap_or(false);
+    let (should_commit, final_message) = if skip_confirm {
+        print_style_warnings(&problems);
+        (true, response.message.clone())
+    } else {
+        confirm_commit(&response.message, &problems)?
+    };
+
+    if !should_commit {
+        println!("{}", "Squash aborted.".yellow());
+        return Ok(());
+    }
+
+    let old_head = get_head_sha()?.unwrap_or_default();
+    reset_soft(&merge_base)?;
+
+    let options = CommitOptions {
+        amend: false,
+        ..commit_options(cli, &config)
+    };
+    match commit_changes(&final_message, &options) {
+        Ok(sha) => {
+            print_committed(&sha);
+            let short_head = old_head.get(..7).unwrap_or(&old_head);
+            println!("{}", format!("Squashed {} commits; undo with: git reset --soft {}", commits.len(), short_head).dimmed());
+            Ok(())
+        },
+        Err(e) => {
+            // Put the branch back where it was; the index already matches it
+            reset_soft(&old_head)?;
+            Err(e)
+        },
+    }
+}
+
+async fn run_split_flow(
+    cli: &Cli,
+    context: Option<&str>,
+    style: Option<CommitStyle>,
+    dry_run: bool,
+    yes: bool,
+    sampling: &SamplingArgs,
+) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, sampling)?;
+
+    let style = style.or_else(|| config.default_commit_style());
+    let skip_confirm = yes || config.preferences.skip_confirmation.unwrap_or(false);
+    let options = CommitOptions {
+        amend: false,
+        ..commit_options(cli, &config)
+    };
+
+    split::run_split(&config, style, context.unwrap_or(""), &options, dry_run, skip_confirm).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_reword_flow(
+    cli: &Cli,
+    range: &str,
+    context: Option<&str>,
+    style: Option<CommitStyle>,
+    force: bool,
+    dry_run: bool,
+    yes: bool,
+    sampling: &SamplingArgs,
+) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, sampling)?;
+
+    let style = style.or_else(|| config.default_commit_style());
+    let skip_confirm = yes || config.preferences.skip_confirmation.unwrap_or(false);
+
+    reword::run_reword(&config, range, style, context.unwrap_or(""), force, dry_run, skip_confirm).await
+}
+
+async fn run_pr_flow(cli: &Cli, args: &PrArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    let skip_confirm = config.preferences.skip_confirmation.unwrap_or(false);
+    pr::run_pr(&config, args, skip_confirm).await
+}
+
+async fn run_changelog_flow(cli: &Cli, args: &ChangelogArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    changelog::run_changelog(&config, args).await
+}
+
+async fn run_release_flow(cli: &Cli, args: &ReleaseArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    let skip_confirm = config.preferences.skip_confirmation.unwrap_or(false);
+    let options = CommitOptions {
+        amend: false,
+        ..commit_options(cli, &config)
+    };
+    release::run_release(&config, args, &options, skip_confirm).await
+}
+
+async fn run_review_flow(cli: &Cli, args: &ReviewArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    review::run_review(&config, args).await
+}
+
+async fn run_explain_flow(cli: &Cli, args: &ExplainArgs) -> Result<()> {
+    let mut config = load_config(&get_config_path()?)?;
+    config.record_dir = cli.record.clone();
+    apply_sampling_args(&mut config, &args.sampling)?;
+
+    explain::run_explain(&config, args).await
+}
+
 async fn run_branch_flow(
     files: &[String],
     all: bool,
     context: Option<&str>,
     yes: bool,
     verbose: bool,
+    sampling: &SamplingArgs,
+    record: Option<&str>,
 ) -> Result<()> {
     let current_branch = get_current_branch()?;
     if verbose {
@@ -339,31 +803,25 @@ async fn run_branch_flow(
     let diff = get_diff(true)?;
     let files_changed = get_files_changed(true)?;
 
-    if diff.trim().is_empty() && files_changed.trim().is_empty() {
+    if diff.is_empty() && files_changed.trim().is_empty() {
         return Err(SageError::GitNoChanges);
     }
 
-    let truncated_diff = smart_truncate_diff(&diff);
-
     let context_str = context.unwrap_or("");
-    let prompt = prompts::build_branch_prompt(
-        context_str,
-        &files_changed,
-        &truncated_diff,
-    );
-
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.blue} {msg}")
-            .unwrap()
-    );
-    spinner.set_message("Generating branch name using AI...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let spinner = spinner("Generating branch name using AI...");
 
     let config_path = get_config_path()?;
-    let config = load_config(&config_path)?;
-    let response = call_ai(&config, &prompt).await?;
+    let mut config = load_config(&config_path)?;
+    config.record_dir = record.map(|dir| dir.to_string());
+    apply_sampling_args(&mut config, sampling)?;
+    let response = call_ai_with_diff(&config, Task::Branch, &diff, |truncated_diff| {
+        prompts::build_branch_prompt(
+            context_str,
+            &files_changed,
+            truncated_diff,
+        )
+    }).await?;
 
     spinner.finish_and_clear();
 
@@ -373,11 +831,7 @@ async fn run_branch_flow(
     println!("{}", branch_name);
 
     if verbose {
-        println!("\n{}", format!("Tokens: {} in / {} out / {} total",
-            response.usage.input_tokens,
-            response.usage.output_tokens,
-            response.usage.total_tokens
-        ).cyan());
+        println!("\n{}", format!("Tokens: {}", response.usage).cyan());
     }
 
     let (should_create, final_branch_name) = if yes {
@@ -403,11 +857,11 @@ async fn run_branch_flow(
 
 fn run_config_wizard(config: &mut config::Config) -> Result<()> {
     println!("{}", "Configuration Wizard".blue().bold());
-    println!("");
+    println!();
 
     println!("Current Settings:");
     config.show();
-    println!("");
+    println!();
 
     println!("{}", "Select what you'd like to configure:".blue());
     println!("  1) Default commit style");
@@ -418,7 +872,7 @@ fn run_config_wizard(config: &mut config::Config) -> Result<()> {
     println!("  6) Verbose mode");
     println!("  7) All preferences");
     println!("  0) Exit wizard");
-    println!("");
+    println!();
 
     loop {
         print!("Enter choice [0-7]: ");
@@ -448,18 +902,18 @@ fn run_config_wizard(config: &mut config::Config) -> Result<()> {
             _ => println!("{}", "Invalid choice".red()),
         }
 
-        println!("");
+        println!();
         print!("Configure another option? [0-7, or 0 to exit]: ");
         io::stdout().flush()?;
     }
 
-    println!("");
+    println!();
     println!("{}", "Configuration updated!".green().bold());
     Ok(())
 }
 
 fn configure_commit_style(config: &mut config::Config) -> Result<()> {
-    println!("");
+    println!();
     println!("Select default commit style:");
     println!("  1) Conventional - single line conventional commits format");
     println!("  2) Detailed - multi-line with summary + bullet points (Git convention)");
@@ -495,7 +949,7 @@ fn configure_commit_style(config: &mut config::Config) -> Result<()> {
 }
 
 fn configure_bool_pref(config: &mut config::Config, key: &str, description: &str) -> Result<()> {
-    println!("");
+    println!();
     println!("{}: [y/n]", description);
     print!("Enable? ");
     io::stdout().flush()?;
@@ -570,7 +1024,20 @@ fn confirm_branch_name(branch_name: &str) -> Result<(bool, String)> {
     }
 }
 
-fn confirm_commit(message: &str) -> Result<(bool, String)> {
+/// Rules the generated message still breaks after the automatic retry
+fn print_style_warnings(problems: &[String]) {
+    if problems.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "Warning: the message does not follow the selected style:".yellow());
+    for problem in problems {
+        println!("  {} {}", "⚠".yellow(), problem);
+    }
+}
+
+fn confirm_commit(message: &str, problems: &[String]) -> Result<(bool, String)> {
+    print_style_warnings(problems);
     print!("\nCommit with this message? [Y/n/e for edit] ");
     io::stdout().flush()?;
 
@@ -579,20 +1046,7 @@ fn confirm_commit(message: &str) -> Result<(bool, String)> {
     let input = input.trim().to_lowercase();
 
     if input == "e" {
-        let temp_file = "/tmp/sage_commit_msg";
-        fs::write(temp_file, message)?;
-
-        let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
-
-        let status = std::process::Command::new(&editor)
-            .arg(temp_file)
-            .status()?;
-
-        if !status.success() {
-            return Err(SageError::EditorFailed);
-        }
-
-        let edited_message = fs::read_to_string(temp_file)?;
+        let edited_message = edit_text("sage-commit-msg-", "", message)?;
         let edited_message = edited_message.trim().to_string();
 
         if edited_message.is_empty() {
@@ -0,0 +1,276 @@
+//! Pull request titles and descriptions (`sage pr`)
+//!
+//! The current branch is compared with its base from their merge base, and the
+//! commits and combined diff are turned into a title plus a markdown body that
+//! follows the repository's pull request template when it has one. With
+//! `--create` the branch is pushed and the pull request opened on its forge.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use colored::Colorize;
+
+use crate::ai::call_ai_validated;
+use crate::cli::{PrArgs, Task};
+use crate::config::{edit_text, Config};
+use crate::error::{Result, SageError};
+use crate::forge::{self, Target};
+use crate::git::{
+    get_commits, get_config_value, get_current_branch, get_merge_base, get_range_diff,
+    get_remote_default_branch, get_repo_root, push_branch,
+};
+use crate::progress::spinner;
+use crate::prompts;
+
+/// Pull request template locations GitHub and GitLab look in, in order
+const TEMPLATE_PATHS: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+    ".gitlab/merge_request_templates/Default.md",
+];
+
+/// Longest title the model is asked for
+const MAX_TITLE_LENGTH: usize = 72;
+
+/// Smallest token limit for pull request descriptions
+const MIN_PR_TOKENS: usize = 1024;
+
+/// Base used when neither the command line, the config nor the remote names one
+const FALLBACK_BASE: &str = "main";
+
+/// Remote to push to when the branch does not track one
+const DEFAULT_REMOTE: &str = "origin";
+
+/// A generated pull request title and description
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrText {
+    pub title: String,
+    pub body: String,
+}
+
+impl PrText {
+    /// Split a model answer into its title (the first line) and body
+    pub fn parse(message: &str) -> PrText {
+        let message = message.trim();
+        let (title, body) = message.split_once('\n').unwrap_or((message, ""));
+
+        let title = title.trim().trim_start_matches('#').trim();
+        let title = title.strip_prefix("Title:").unwrap_or(title).trim();
+
+        PrText {
+            title: title.trim_matches(|c| c == '"' || c == '`').to_string(),
+            body: body.trim().to_string(),
+        }
+    }
+
+    /// Problems that make the answer unusable as a pull request
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let title_length = self.title.chars().count();
+        if title_length == 0 {
+            problems.push("title-empty: The first line must be the pull request title".to_string());
+        } else if title_length > MAX_TITLE_LENGTH {
+            problems.push(format!(
+                "title-max-length: Title is {} characters; keep it to {} or fewer", title_length, MAX_TITLE_LENGTH
+            ));
+        }
+        if self.body.is_empty() {
+            problems.push("body-empty: Add a blank line and the description after the title".to_string());
+        }
+        problems
+    }
+}
+
+impl std::fmt::Display for PrText {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}\n\n{}\n", self.title, self.body)
+    }
+}
+
+/// Contents of the repository's pull request template, if it has one
+fn find_template(root: &Path) -> Option<String> {
+    TEMPLATE_PATHS.iter()
+        .filter_map(|path| fs::read_to_string(root.join(path)).ok())
+        .find(|template| !template.trim().is_empty())
+}
+
+/// Base branch: the given one, then the configured one, then origin's default
+pub fn resolve_base(config: &Config, base: Option<&str>) -> Result<String> {
+    if let Some(base) = base.or(config.pr_base.as_deref()) {
+        return Ok(base.to_string());
+    }
+    Ok(get_remote_default_branch()?.unwrap_or_else(|| FALLBACK_BASE.to_string()))
+}
+
+/// Generate the title and description for the current branch against `base`.
+/// Also returns any problems the answer still has after one retry.
+pub async fn generate_pr(config: &Config, base: &str, context: &str) -> Result<(PrText, Vec<String>)> {
+    let branch = get_current_branch()?;
+    let merge_base = get_merge_base(base, "HEAD")?;
+    let commits = get_commits(&format!("{}..HEAD", merge_base))?;
+    if commits.is_empty() {
+        return Err(SageError::InvalidInput(format!("No commits on {} that are not on {}", branch, base)));
+    }
+
+    let diff = get_range_diff(&merge_base, "HEAD")?;
+    let files_changed = diff.summary();
+    let commit_list = commits.iter()
+        .rev()
+        .map(|commit| format!("- {}", commit.message.trim().replace('\n', "\n  ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let template = find_template(&get_repo_root()?);
+
+    let config = config.with_min_tokens(MIN_PR_TOKENS);
+
+    let spinner = spinner(format!("Describing {} commits on {} using AI...", commits.len(), branch));
+
+    let result = call_ai_validated(&config, Task::Pr, &diff, |truncated_diff| {
+        prompts::build_pr_prompt(
+            template.as_deref(),
+            &branch,
+            base,
+            context,
+            &commit_list,
+            &files_changed,
+            truncated_diff,
+        )
+    }, |message| PrText::parse(message).problems()).await;
+
+    spinner.finish_and_clear();
+
+    let (response, problems) = result?;
+    Ok((PrText::parse(&response.message), problems))
+}
+
+fn edit_pr(pr: &PrText) -> Result<PrText> {
+    Ok(PrText::parse(&edit_text("sage-pr-", ".md", &pr.to_string())?))
+}
+
+/// Show the pull request and ask whether to open it; returns the text to use,
+/// or None when the user aborts
+fn confirm_pr(mut pr: PrText, request_name: &str, skip_confirm: bool) -> Result<Option<PrText>> {
+    loop {
+        println!("\n{}", pr.title.green().bold());
+        println!("\n{}", pr.body);
+
+        if skip_confirm {
+            return Ok(Some(pr));
+        }
+
+        print!("\nOpen this {}? [Y/n/e for edit] ", request_name);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        match input.trim().to_lowercase().as_str() {
+            "" | "y" => return Ok(Some(pr)),
+            "e" => match edit_pr(&pr) {
+                Ok(edited) => pr = edited,
+                Err(e) => eprintln!("\n{}", e.to_string().red()),
+            },
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// `sage pr --create`: push the branch and open or update its pull request
+async fn create_pr(config: &Config, args: &PrArgs, base: &str, skip_confirm: bool) -> Result<()> {
+    let branch = get_current_branch()?;
+    let remote = get_config_value(&format!("branch.{}.remote", branch))?
+        .filter(|remote| remote != ".")
+        .unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+    let remote_url = get_config_value(&format!("remote.{}.url", remote))?
+        .ok_or_else(|| SageError::InvalidInput(format!("Remote '{}' has no URL to open a pull request on", remote)))?;
+    let target = Target::resolve(&remote_url, args.forge, config)?;
+    let request_name = forge::request_name(target.forge());
+
+    let base_branch = base.strip_prefix(&format!("{}/", remote)).unwrap_or(base);
+    if base_branch == branch {
+        return Err(SageError::InvalidInput(format!(
+            "{} is the base branch; check out the branch to open a {} from", branch, request_name
+        )));
+    }
+
+    let (pr, problems) = generate_pr(config, base, args.context.as_deref().unwrap_or("")).await?;
+    for problem in &problems {
+        println!("{}", format!("Warning: {}", problem).yellow());
+    }
+
+    let skip_confirm = skip_confirm || args.yes;
+    let Some(pr) = confirm_pr(pr, request_name, skip_confirm)? else {
+        println!("{}", format!("No {} opened.", request_name).yellow());
+        return Ok(());
+    };
+
+    if let Some(path) = &args.output {
+        fs::write(path, pr.to_string())?;
+    }
+
+    println!("{}", format!("Pushing {} to {}...", branch, remote).blue());
+    push_branch(&remote, &branch)?;
+
+    let pull = forge::submit(config, &target, &branch, base_branch, &pr).await?;
+    let action = if pull.updated { "Updated" } else { "Opened" };
+    println!("{}", format!("{} {}: {}", action, request_name, pull.url).green());
+
+    Ok(())
+}
+
+/// `sage pr`: print the title and description or write them to a file, or
+/// with `--create` open the pull request
+pub async fn run_pr(config: &Config, args: &PrArgs, skip_confirm: bool) -> Result<()> {
+    let base = resolve_base(config, args.base.as_deref())?;
+    if args.create {
+        return create_pr(config, args, &base, skip_confirm).await;
+    }
+
+    let (pr, problems) = generate_pr(config, &base, args.context.as_deref().unwrap_or("")).await?;
+
+    // Status goes to stderr so stdout can be piped into other tools
+    for problem in &problems {
+        eprintln!("{}", format!("Warning: {}", problem).yellow());
+    }
+
+    match &args.output {
+        Some(path) => {
+            fs::write(path, pr.to_string())?;
+            eprintln!("{}", format!("Pull request description written to {}", path).green());
+        },
+        None => print!("{}", pr),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pr_text() {
+        let pr = PrText::parse("# Title: feat(cache): cache parsed diffs\n\n## Summary\n\nSpeeds up `sage split`.\n");
+        assert_eq!(pr.title, "feat(cache): cache parsed diffs");
+        assert_eq!(pr.body, "## Summary\n\nSpeeds up `sage split`.");
+        assert!(pr.problems().is_empty());
+        assert_eq!(pr.to_string(), "feat(cache): cache parsed diffs\n\n## Summary\n\nSpeeds up `sage split`.\n");
+
+        let pr = PrText::parse(&"x".repeat(80));
+        assert_eq!(pr.problems().len(), 2);
+    }
+
+    #[test]
+    fn test_find_template() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(find_template(dir.path()), None);
+
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs/pull_request_template.md"), "## Why\n").unwrap();
+        assert_eq!(find_template(dir.path()).as_deref(), Some("## Why\n"));
+    }
+}
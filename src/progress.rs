@@ -0,0 +1,18 @@
+//! Spinners shown while waiting on the AI provider
+
+use std::borrow::Cow;
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Start a spinner showing `message`; call `finish_and_clear` once the work is done
+pub fn spinner(message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.blue} {msg}")
+            .unwrap()
+    );
+    spinner.set_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
@@ -1,16 +1,92 @@
-/// Prompt templates for AI commit message generation
-///
-/// This module contains all prompt templates used for generating commit messages.
-/// Templates are easy to edit and maintain in one central location.
+//! Prompt templates for AI commit message generation
+//!
+//! This module contains all prompt templates used for generating commit messages.
+//! Templates are easy to edit and maintain in one central location.
+
+use std::fmt;
 
 use crate::cli::CommitStyle;
 
-/// System prompt for OpenAI (sets the AI's role and behavior)
-pub const OPENAI_SYSTEM_PROMPT: &str =
+/// A prompt split into static instructions and per-call input.
+///
+/// Instructions only depend on the task and style, so providers that support
+/// prompt caching can cache them; the input carries the context and diff.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub instructions: String,
+    pub input: String,
+    /// The answer is markdown or JSON (pull request descriptions, reviews)
+    /// rather than a plain-text commit message, so providers keep its formatting
+    pub markdown: bool,
+    /// Further examples a provider may append to cached instructions; empty
+    /// for prompts that have none
+    pub extra_examples: &'static str,
+}
+
+impl Prompt {
+    /// Copy of this prompt that tells the model about the repository tools
+    pub fn with_tool_instructions(&self) -> Prompt {
+        Prompt {
+            instructions: format!("{}\n\n{}", self.instructions, TOOL_INSTRUCTIONS),
+            input: self.input.clone(),
+            markdown: self.markdown,
+            extra_examples: self.extra_examples,
+        }
+    }
+
+    /// System prompt for this prompt's kind of answer
+    pub fn system(&self) -> &'static str {
+        if self.markdown { MARKDOWN_SYSTEM_PROMPT } else { SYSTEM_PROMPT }
+    }
+
+    /// Copy of this prompt that asks the model to fix the rules its previous answer broke
+    pub fn with_feedback(&self, previous: &str, problems: &[String]) -> Prompt {
+        let problems = problems.iter()
+            .map(|problem| format!("- {}", problem))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Prompt {
+            instructions: self.instructions.clone(),
+            input: format!(
+                "{}\n\n{}",
+                self.input,
+                RETRY_FEEDBACK_TEMPLATE
+                    .replace("{previous}", previous)
+                    .replace("{problems}", &problems)
+            ),
+            markdown: self.markdown,
+            extra_examples: self.extra_examples,
+        }
+    }
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.instructions, self.input)
+    }
+}
+
+/// System prompt shared by all providers (sets the AI's role and behavior)
+pub const SYSTEM_PROMPT: &str =
     "You are a helpful assistant that generates concise git commit messages \
      and branch names. You MUST output PLAIN TEXT ONLY with NO markdown \
      formatting whatsoever.";
 
+/// System prompt for answers that keep their formatting (pull request
+/// descriptions, release notes, explanations, reviews)
+pub const MARKDOWN_SYSTEM_PROMPT: &str =
+    "You are a helpful assistant for software developers. Answer in exactly the \
+     format requested, using GitHub-flavored markdown where formatting is needed.";
+
+/// Extra instructions when the model can call read-only repository tools
+pub const TOOL_INSTRUCTIONS: &str = r#"TOOLS:
+You can call read-only tools to read files at HEAD or in the index, list the
+functions touched by the staged changes, and see the git history of a path.
+Use them only when the diff is truncated or does not show enough surrounding
+code to understand WHY the change was made. Tool calls are limited, so ask
+for what matters most. Your final answer must follow the rules above."#;
+
 /// Base prompt template for generating commit messages
 pub const BASE_PROMPT_TEMPLATE: &str = r#"Generate a concise and descriptive git commit message for the following changes.
 
@@ -22,9 +98,138 @@ IMPORTANT RULES:
 - Output only the commit message text, nothing else
 - Keep it concise and focused on WHAT changed and WHY
 
-{style_instructions}
+{style_instructions}"#;
 
-Additional context: {context}
+/// A few example changes and commit headers, sent with every commit prompt
+pub const COMMIT_EXAMPLES: &str = r#"EXAMPLES:
+Each example describes a change and a good commit header for it. They show the
+header line only; follow the style instructions above for everything else.
+Use the type that matches the change, a short scope naming the affected area,
+an imperative subject in lower case, and '!' after the scope for changes that
+break existing users.
+
+Change: Added a --json flag to the status command that prints machine-readable output instead of the table.
+Message: feat(cli): add --json output to status
+
+Change: The login handler compared password hashes with == and leaked timing; it now uses a constant-time comparison.
+Message: fix(auth): compare hashes in constant time
+
+Change: Rewrote the installation section of the README for the new one-line installer and removed the outdated Homebrew steps.
+Message: docs(readme): update installation instructions
+
+Change: Moved the duplicated date parsing from three handlers into a shared helper with no change in behavior.
+Message: refactor(api): share date parsing in handlers
+
+Change: Removed the deprecated /v1/users endpoint; clients must call /v2/users, which returns paginated results.
+Message: feat(api)!: remove deprecated v1 users endpoint
+
+Avoid vague headers such as "update code", "fix bug", "changes" or "wip", and
+do not describe the diff line by line."#;
+
+/// More examples in the same format, only sent by providers that cache the
+/// instructions. They lift commit instructions over the 1024-token minimum
+/// Anthropic needs before it caches a prefix; without caching they would only
+/// add about 1k tokens to every call.
+pub const EXTRA_COMMIT_EXAMPLES: &str = r#"MORE EXAMPLES:
+
+Change: A retry loop in the HTTP client never reset its backoff after a successful request, so later failures waited minutes.
+Message: fix(http): reset retry backoff after success
+
+Change: Added unit tests for the config loader covering missing files, invalid JSON and unknown keys.
+Message: test(config): cover invalid and missing files
+
+Change: Bumped serde from 1.0.188 to 1.0.197 and regenerated the lockfile.
+Message: build(deps): bump serde to 1.0.197
+
+Change: The CI workflow now caches the cargo registry and target directory between runs.
+Message: ci: cache cargo registry and build output
+
+Change: Replaced a linear scan over all users with a hash map lookup in the permissions check; the hot path is 40x faster.
+Message: perf(auth): look up permissions by user id
+
+Change: Ran the formatter over the parser module; only whitespace and import order changed.
+Message: style(parser): apply rustfmt
+
+Change: Reverted the previous commit that switched the cache to Redis because it broke local development without a Redis server.
+Message: revert: switch cache back to in-memory store
+
+Change: Updated the .gitignore to exclude editor swap files and the local coverage report.
+Message: chore: ignore editor swap files and coverage
+
+Change: The date picker showed the wrong month for users in negative UTC offsets because it used local midnight.
+Message: fix(ui): use UTC dates in the date picker
+
+Change: Added a database migration that creates an index on orders.created_at for the monthly report query.
+Message: perf(db): index orders by creation date
+
+Change: Introduced a Notifier trait and moved the email sender behind it so SMS can be added later.
+Message: refactor(notify): extract Notifier trait
+
+Change: Fixed a typo in the error message shown when the upload is too large.
+Message: fix(upload): correct typo in size limit error
+
+Change: Added a --dry-run option to the migrate command that prints the SQL without executing it.
+Message: feat(migrate): add --dry-run option
+
+Change: The export job crashed with an index out of range error when a report had no rows; empty reports now produce a file with only the header row.
+Message: fix(export): handle reports without rows
+
+Change: Added rate limiting to the public search endpoint, allowing 60 requests per minute per API key and returning 429 with a Retry-After header.
+Message: feat(search): rate limit public search requests
+
+Change: Split the 900-line server.rs into routing, middleware and handler modules without changing any behavior.
+Message: refactor(server): split server module by concern
+
+Change: Documented every public function in the storage crate and added a module overview with an example.
+Message: docs(storage): document public storage API
+
+Change: Changed the default config location from ~/.apprc to ~/.config/app/config.toml; the old file is no longer read.
+Message: feat(config)!: move config file to XDG location
+
+Change: Added an integration test that starts the server, uploads a file and downloads it again to check the round trip.
+Message: test(upload): add upload round-trip test
+
+Change: The release workflow now builds macOS arm64 binaries in addition to x86_64.
+Message: ci(release): build macOS arm64 binaries
+
+Change: Image thumbnails are now generated once and stored instead of being resized on every request.
+Message: perf(images): cache generated thumbnails
+
+Change: Closing the settings dialog with Escape discarded unsaved changes without asking; it now shows a confirmation prompt.
+Message: fix(settings): confirm before discarding changes
+
+Change: Removed the unused legacy_parser module and its feature flag.
+Message: chore: remove unused legacy parser
+
+Change: The websocket client reconnected in a tight loop when the server was down, using a full CPU core; reconnects now back off exponentially up to 30 seconds.
+Message: fix(ws): back off between reconnect attempts
+
+Change: Added a dark theme that follows the operating system setting, with a toggle in the preferences screen to override it.
+Message: feat(ui): add dark theme following system setting
+
+Change: Replaced the hand-written argument parsing in the CLI with clap derive structs; the flags and their behavior are unchanged.
+Message: refactor(cli): parse arguments with clap derive
+
+Change: Pinned the Node.js version in the Dockerfile to 20.11 and switched the base image to the slim variant to shrink the image.
+Message: build(docker): pin node 20.11 on slim image
+
+Change: Added a CONTRIBUTING guide describing the branch naming, commit message format and how to run the test suite locally.
+Message: docs: add contributing guide
+
+Change: Tests for the scheduler depended on the wall clock and failed around midnight; they now use a fixed fake clock.
+Message: test(scheduler): use a fake clock in tests"#;
+
+/// Appended to the input when a generated message broke the style rules
+pub const RETRY_FEEDBACK_TEMPLATE: &str = r#"Your previous answer was:
+{previous}
+
+It breaks these rules:
+{problems}
+
+Write a corrected commit message that follows every rule above."#;
+
+/// Per-call input appended to the commit and branch instructions
+pub const CHANGES_INPUT_TEMPLATE: &str = r#"Additional context: {context}
 
 Files changed:
 {files_changed}
@@ -47,74 +252,361 @@ Common patterns:
 - feature/add-authentication
 - bugfix/fix-login-error
 - refactor/simplify-api-calls
-- docs/update-readme
+- docs/update-readme"#;
 
-Additional context: {context}
+/// Header limit the detailed and short styles ask for
+pub const STYLE_HEADER_LENGTH: usize = 50;
 
-Files changed:
-{files_changed}
-
-Diff:
-{diff}"#;
+/// Body line width the detailed style asks for
+pub const STYLE_BODY_WRAP: usize = 72;
 
 /// Get style-specific instructions for commit message generation
-pub fn get_style_instructions(style: Option<CommitStyle>) -> &'static str {
+pub fn get_style_instructions(style: Option<CommitStyle>) -> String {
     match style {
         Some(CommitStyle::Standard) | None => {
             "Use standard conventional commits format: 'type(scope): description'\n\
-             Common types: feat, fix, docs, style, refactor, test, chore"
+             Common types: feat, fix, docs, style, refactor, test, chore".to_string()
         },
-        Some(CommitStyle::Detailed) => {
+        Some(CommitStyle::Detailed) => format!(
             "Create a detailed multi-line commit message following Git convention:\n\
-             - First line: Short summary in conventional commits format (max 50 chars)\n\
+             - First line: Short summary in conventional commits format (max {} chars)\n\
              - Second line: MUST be blank\n\
              - Following lines: Detailed explanation of what changed and why\n\
              - Use bullet points with '- ' for listing changes\n\
-             - Wrap lines at 72 characters\n\
+             - Wrap lines at {} characters\n\
              \n\
              Example format:\n\
              feat(auth): add JWT token validation\n\
              \n\
              - Implement token verification middleware\n\
              - Add expiration checking\n\
-             - Handle refresh token logic"
-        },
-        Some(CommitStyle::Short) => {
+             - Handle refresh token logic",
+            STYLE_HEADER_LENGTH, STYLE_BODY_WRAP
+        ),
+        Some(CommitStyle::Short) => format!(
             "Create an extremely concise one-line commit message.\n\
-             Maximum 50 characters. Be direct and specific."
-        },
+             Maximum {} characters. Be direct and specific.",
+            STYLE_HEADER_LENGTH
+        ),
     }
 }
 
+/// Commit rules for `style`, followed by the examples
+fn commit_instructions(style: Option<CommitStyle>) -> String {
+    format!(
+        "{}\n\n{}",
+        BASE_PROMPT_TEMPLATE.replace("{style_instructions}", &get_style_instructions(style)),
+        COMMIT_EXAMPLES
+    )
+}
+
+fn build_changes_input(context: &str, files_changed: &str, diff: &str) -> String {
+    let context_text = if context.is_empty() { "None" } else { context };
+
+    CHANGES_INPUT_TEMPLATE
+        .replace("{context}", context_text)
+        .replace("{files_changed}", files_changed)
+        .replace("{diff}", diff)
+}
+
 /// Build the complete prompt for commit message generation
 pub fn build_commit_prompt(
     style: Option<CommitStyle>,
     context: &str,
     files_changed: &str,
     diff: &str,
-) -> String {
-    let style_instructions = get_style_instructions(style);
+) -> Prompt {
+    Prompt {
+        instructions: commit_instructions(style),
+        input: build_changes_input(context, files_changed, diff),
+        markdown: false,
+        extra_examples: EXTRA_COMMIT_EXAMPLES,
+    }
+}
+
+/// Instructions for rewriting a commit message that failed `sage lint`
+pub const LINT_FIX_TEMPLATE: &str = r#"Rewrite the following git commit message so it follows the Conventional Commits specification and fixes every listed violation.
+
+IMPORTANT RULES:
+- Header format: type(scope): subject, using one of these types: {types}
+- Keep the original meaning; do not invent changes that are not described
+- Leave a blank line between the header and the body, and before footers
+- Footers use 'Token: value' and breaking changes use 'BREAKING CHANGE: description'
+- Use PLAIN TEXT ONLY - no markdown formatting
+- Output only the commit message text, nothing else"#;
+
+/// Build the prompt for fixing a commit message that failed linting
+pub fn build_lint_fix_prompt(message: &str, violations: &str, types: &[String]) -> Prompt {
+    Prompt {
+        instructions: LINT_FIX_TEMPLATE.replace("{types}", &types.join(", ")),
+        input: format!("Commit message:\n{}\n\nViolations:\n{}", message, violations),
+        markdown: false,
+        extra_examples: "",
+    }
+}
+
+/// Per-call input for squashing several commits into one
+pub const SQUASH_INPUT_TEMPLATE: &str = r#"These commits are being squashed into a single commit. Write one cohesive message for the combined change; do not list the individual commits.
+
+Additional context: {context}
+
+Commits being squashed (oldest first):
+{commits}
+
+Files changed:
+{files_changed}
+
+Combined diff:
+{diff}"#;
+
+/// Build the prompt for the message of a squashed commit range
+pub fn build_squash_prompt(
+    style: Option<CommitStyle>,
+    context: &str,
+    commits: &str,
+    files_changed: &str,
+    diff: &str,
+) -> Prompt {
     let context_text = if context.is_empty() { "None" } else { context };
 
-    BASE_PROMPT_TEMPLATE
-        .replace("{style_instructions}", style_instructions)
-        .replace("{context}", context_text)
-        .replace("{files_changed}", files_changed)
-        .replace("{diff}", diff)
+    Prompt {
+        instructions: commit_instructions(style),
+        input: SQUASH_INPUT_TEMPLATE
+            .replace("{context}", context_text)
+            .replace("{commits}", commits)
+            .replace("{files_changed}", files_changed)
+            .replace("{diff}", diff),
+        markdown: false,
+        extra_examples: EXTRA_COMMIT_EXAMPLES,
+    }
 }
 
-/// Build the prompt for branch name generation
-pub fn build_branch_prompt(
+/// Prompt template for grouping staged changes into separate commits
+pub const SPLIT_TEMPLATE: &str = r#"Group the following numbered changes into logically separate git commits, each focused on a single purpose, and write a commit message for each commit.
+
+IMPORTANT RULES:
+- Every change number must be used in exactly one commit
+- Keep changes that depend on each other in the same commit
+- Do not split related changes; a single commit is fine if everything belongs together
+- Order the commits so that each one builds on the previous ones
+- Respond with JSON ONLY, no markdown and no code blocks, in this shape:
+  {"commits": [{"message": "feat(api): add pagination", "changes": [1, 3]}]}
+
+Each commit message must follow these rules:
+- Use PLAIN TEXT ONLY - no markdown formatting
+{style_instructions}"#;
+
+/// Build the prompt for splitting staged changes into several commits
+pub fn build_split_prompt(style: Option<CommitStyle>, context: &str, changes: &str) -> Prompt {
+    let context_text = if context.is_empty() { "None" } else { context };
+
+    Prompt {
+        instructions: SPLIT_TEMPLATE.replace("{style_instructions}", &get_style_instructions(style)),
+        input: format!("Additional context: {}\n\nChanges:\n{}", context_text, changes),
+        markdown: false,
+        extra_examples: "",
+    }
+}
+
+/// Instructions for pull request titles and descriptions
+pub const PR_TEMPLATE: &str = r#"Write a pull request title and description for the following branch.
+
+IMPORTANT RULES:
+- First line: the title only, at most 72 characters, with no markdown and no trailing period
+- If the commits use conventional commits, write the title as type(scope): description
+- Second line: blank
+- Then the description in markdown
+- Describe WHAT changed and WHY, based only on the commits and diff; do not invent changes
+- Output only the title and description, nothing else
+
+{sections}"#;
+
+/// Description layout used when the repository has no pull request template
+pub const PR_DEFAULT_SECTIONS: &str = r#"Use exactly these sections, in this order:
+## Summary
+One or two sentences on what the pull request does and why.
+## Changes
+A bullet list of the notable changes.
+## Testing
+How the changes were or can be verified.
+## Risk
+What could break, and anything reviewers should look at closely."#;
+
+/// Description layout when the repository has a pull request template
+pub const PR_REPO_TEMPLATE_SECTIONS: &str = r#"Fill in the repository's pull request template below. Keep its headings and their order, fill in every section, leave out HTML comments, and leave checkboxes unchecked unless the changes clearly satisfy them.
+
+Template:
+{template}"#;
+
+/// Per-call input for pull request descriptions
+pub const PR_INPUT_TEMPLATE: &str = r#"Branch: {branch} (into {base})
+
+Additional context: {context}
+
+Commits (oldest first):
+{commits}
+
+Files changed:
+{files_changed}
+
+Diff:
+{diff}"#;
+
+/// Build the prompt for a pull request title and description, following the
+/// repository's pull request template when there is one
+pub fn build_pr_prompt(
+    template: Option<&str>,
+    branch: &str,
+    base: &str,
     context: &str,
+    commits: &str,
     files_changed: &str,
     diff: &str,
-) -> String {
+) -> Prompt {
     let context_text = if context.is_empty() { "None" } else { context };
+    let sections = match template {
+        Some(template) => PR_REPO_TEMPLATE_SECTIONS.replace("{template}", template.trim()),
+        None => PR_DEFAULT_SECTIONS.to_string(),
+    };
 
-    BRANCH_NAME_TEMPLATE
-        .replace("{context}", context_text)
-        .replace("{files_changed}", files_changed)
-        .replace("{diff}", diff)
+    Prompt {
+        instructions: PR_TEMPLATE.replace("{sections}", &sections),
+        input: PR_INPUT_TEMPLATE
+            .replace("{branch}", branch)
+            .replace("{base}", base)
+            .replace("{context}", context_text)
+            .replace("{commits}", commits)
+            .replace("{files_changed}", files_changed)
+            .replace("{diff}", diff),
+        markdown: true,
+        extra_examples: "",
+    }
+}
+
+/// Instructions for turning changelog entries into user-facing release notes
+pub const RELEASE_NOTES_TEMPLATE: &str = r#"Rewrite each numbered changelog entry below as a short, user-facing release note.
+
+IMPORTANT RULES:
+- Describe the change from the user's point of view: what they can now do, or what no longer goes wrong
+- One sentence per note, starting with a capital letter, without a trailing period
+- Do not mention commit types, scopes or hashes, and do not invent details
+- Keep code identifiers as they are, in backticks
+- Output one line per entry in the form '<number>. <note>', using the same numbers, and nothing else"#;
+
+/// Build the prompt for rewriting changelog entries; `entries` are
+/// commit headers, numbered from 1 in the prompt
+pub fn build_release_notes_prompt(entries: &[String]) -> Prompt {
+    let numbered = entries.iter()
+        .enumerate()
+        .map(|(index, entry)| format!("{}. {}", index + 1, entry))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Prompt {
+        instructions: RELEASE_NOTES_TEMPLATE.to_string(),
+        input: format!("Entries:\n{}", numbered),
+        markdown: true,
+        extra_examples: "",
+    }
+}
+
+/// Prompt template for the message of an annotated release tag
+pub const RELEASE_TAG_TEMPLATE: &str = r#"Write the message for the annotated git tag of release {tag}, summarizing the changes listed below.
+
+IMPORTANT RULES:
+- First line: a one-sentence summary of the release, at most 72 characters, without a trailing period
+- Then a blank line and up to eight '- ' bullets with the most important changes, breaking changes first
+- Describe changes from the user's point of view and do not invent details
+- Keep code identifiers as they are, in backticks
+- Plain text only: no headings, no bold text and no version number on the first line
+- Output only the tag message"#;
+
+/// Build the prompt for an annotated release tag message; `notes` is the
+/// release's changelog section
+pub fn build_release_tag_prompt(tag: &str, previous: Option<&str>, context: &str, notes: &str) -> Prompt {
+    let context_text = if context.is_empty() { "None" } else { context };
+
+    Prompt {
+        instructions: RELEASE_TAG_TEMPLATE.replace("{tag}", tag),
+        input: format!(
+            "Previous release: {}\n\nAdditional context: {}\n\nChanges:\n{}",
+            previous.unwrap_or("None"), context_text, notes
+        ),
+        markdown: true,
+        extra_examples: "",
+    }
+}
+
+/// Prompt template for explaining existing commits to someone new to the code
+pub const EXPLAIN_TEMPLATE: &str = r#"Explain what the commits below do, for a developer who does not know this part of the codebase yet.
+
+IMPORTANT RULES:
+- Start with one or two plain-language sentences saying what the change does
+- Then a "What changed" list of short '- ' bullets, by file or area, covering behavior rather than restating lines
+- Then a "Why" paragraph with the likely motivation, based on the commit messages and the code; say so when you are guessing
+- Finish with anything a reviewer should notice (behavior changes, risks, leftovers), or leave it out when there is nothing
+- Keep code identifiers as they are, in backticks
+- Do not invent details that are not in the commits, diff or files"#;
+
+/// Build the prompt for explaining existing commits; `file_context` holds the
+/// changed files' contents when requested
+pub fn build_explain_prompt(
+    context: &str,
+    commits: &str,
+    files_changed: &str,
+    file_context: Option<&str>,
+    diff: &str,
+) -> Prompt {
+    let context_text = if context.is_empty() { "None" } else { context };
+    let files_text = file_context
+        .map(|files| format!("Changed files after the change:\n{}\n\n", files))
+        .unwrap_or_default();
+
+    Prompt {
+        instructions: EXPLAIN_TEMPLATE.to_string(),
+        input: format!(
+            "Additional context: {}\n\nCommits:\n{}\n\nFiles changed:\n{}\n\n{}Diff:\n{}",
+            context_text, commits, files_changed, files_text, diff
+        ),
+        markdown: true,
+        extra_examples: "",
+    }
+}
+
+/// Prompt template for reviewing changes, answered as JSON findings
+pub const REVIEW_TEMPLATE: &str = r#"Review the following code changes and report the problems worth fixing before they are committed.
+
+IMPORTANT RULES:
+- Report only real problems in the changed code: bugs, security issues, new behavior without tests, and style problems that hurt readability
+- Do not praise, summarize or restate the changes; an empty list is a good answer for a clean change
+- "severity" is one of "bug", "security", "test-gap" or "style"
+- "line" is the line number in the new version of the file (count from the @@ hunk headers), or null when the finding is about the whole file
+- Keep code identifiers as they are, in backticks
+- Respond with JSON ONLY, no code blocks, in this shape:
+  {"findings": [{"file": "src/lib.rs", "line": 42, "severity": "bug", "message": "What is wrong and why", "suggestion": "How to fix it"}]}"#;
+
+/// Build the prompt for reviewing changes
+pub fn build_review_prompt(context: &str, files_changed: &str, diff: &str) -> Prompt {
+    Prompt {
+        instructions: REVIEW_TEMPLATE.to_string(),
+        input: build_changes_input(context, files_changed, diff),
+        markdown: true,
+        extra_examples: "",
+    }
+}
+
+/// Build the prompt for branch name generation
+pub fn build_branch_prompt(
+    context: &str,
+    files_changed: &str,
+    diff: &str,
+) -> Prompt {
+    Prompt {
+        instructions: BRANCH_NAME_TEMPLATE.to_string(),
+        input: build_changes_input(context, files_changed, diff),
+        markdown: false,
+        extra_examples: "",
+    }
 }
 
 #[cfg(test)]
@@ -130,10 +622,17 @@ mod tests {
             "+ fixed bug",
         );
 
-        assert!(prompt.contains("PLAIN TEXT ONLY"));
-        assert!(prompt.contains("Bug fix"));
-        assert!(prompt.contains("src/main.rs"));
-        assert!(prompt.contains("+ fixed bug"));
+        assert!(prompt.instructions.contains("PLAIN TEXT ONLY"));
+        assert!(prompt.input.contains("Bug fix"));
+        assert!(prompt.input.contains("src/main.rs"));
+        assert!(prompt.input.contains("+ fixed bug"));
+
+        let text = prompt.to_string();
+        assert!(text.starts_with(&prompt.instructions));
+        assert!(text.ends_with("+ fixed bug"));
+        // Only caching providers append the extra examples
+        assert!(text.contains(COMMIT_EXAMPLES));
+        assert!(!text.contains(EXTRA_COMMIT_EXAMPLES));
     }
 
     #[test]
@@ -151,4 +650,59 @@ mod tests {
         let default = get_style_instructions(None);
         assert!(default.contains("conventional commits"));
     }
+
+    #[test]
+    fn test_build_squash_prompt() {
+        let prompt = build_squash_prompt(None, "", "- wip\n- fix typo", "M  src/main.rs (+2 -1)", "+ fixed bug");
+
+        assert_eq!(prompt.instructions, build_commit_prompt(None, "", "", "").instructions);
+        assert!(prompt.input.contains("Additional context: None"));
+        assert!(prompt.input.contains("(oldest first):\n- wip\n- fix typo"));
+        assert!(prompt.input.ends_with("+ fixed bug"));
+    }
+
+    #[test]
+    fn test_build_pr_prompt() {
+        let prompt = build_pr_prompt(None, "feature/cache", "main", "", "- feat: add cache", "M  src/cache.rs (+10 -0)", "+ cache");
+        assert!(prompt.markdown);
+        assert_eq!(prompt.system(), MARKDOWN_SYSTEM_PROMPT);
+        assert!(prompt.instructions.contains("## Risk"));
+        assert!(prompt.input.starts_with("Branch: feature/cache (into main)"));
+
+        let prompt = build_pr_prompt(Some("## What\n\n## Why\n"), "feature/cache", "main", "", "", "", "");
+        assert!(prompt.instructions.ends_with("Template:\n## What\n\n## Why"));
+        assert!(!prompt.instructions.contains("## Risk"));
+    }
+
+    #[test]
+    fn test_build_explain_prompt() {
+        let prompt = build_explain_prompt("", "abc1234 fix: handle empty input", "M  src/parse.rs (+3 -1)", None, "+ guard");
+        assert!(prompt.markdown);
+        assert!(prompt.input.starts_with("Additional context: None\n\nCommits:\nabc1234 fix: handle empty input"));
+        assert!(!prompt.input.contains("Changed files after the change"));
+
+        let prompt = build_explain_prompt("", "", "", Some("=== src/parse.rs ===\nfn parse() {}"), "+ guard");
+        assert!(prompt.input.contains("Changed files after the change:\n=== src/parse.rs ===\nfn parse() {}\n\nDiff:\n+ guard"));
+    }
+
+    #[test]
+    fn test_build_review_prompt() {
+        let prompt = build_review_prompt("", "M  src/auth.rs (+4 -1)", "+ if token == expected {");
+        assert!(prompt.markdown);
+        assert!(prompt.instructions.contains(r#"{"findings": ["#));
+        assert!(prompt.input.contains("Additional context: None"));
+        assert!(prompt.input.contains("Files changed:\nM  src/auth.rs (+4 -1)"));
+        assert!(prompt.input.ends_with("Diff:\n+ if token == expected {"));
+    }
+
+    #[test]
+    fn test_with_feedback() {
+        let prompt = build_commit_prompt(None, "", "src/main.rs", "+ fixed bug");
+        let retry = prompt.with_feedback("Fixed bug.", &["header-format: Header must look like 'type: subject'".to_string()]);
+
+        assert_eq!(retry.instructions, prompt.instructions);
+        assert!(retry.input.starts_with(&prompt.input));
+        assert!(retry.input.contains("Your previous answer was:\nFixed bug."));
+        assert!(retry.input.contains("- header-format: Header must look like"));
+    }
 }
@@ -0,0 +1,389 @@
+//! Release tagging (`sage release`)
+//!
+//! The commits since the last semver tag decide the next version: breaking
+//! changes bump the major version (the minor one before 1.0.0), features the
+//! minor one and anything else the patch. The release is tagged with an
+//! annotated tag whose message is an AI summary of its changelog section.
+
+use std::fs;
+use std::io::{self, Write};
+use colored::Colorize;
+use regex::Regex;
+
+use crate::ai::call_ai;
+use crate::changelog::{build_groups, render, version_from_tag};
+use crate::cli::{Bump, ChangelogFormat, ReleaseArgs, Task};
+use crate::config::{edit_text, Config};
+use crate::error::{Result, SageError};
+use crate::git::{
+    commit_changes, create_annotated_tag, get_commit_date, get_commits, get_history,
+    get_repo_root, get_tags_merged, has_staged_changes, stage_files, tag_exists,
+    CommitInfo, CommitOptions,
+};
+use crate::lint::parse_conventional;
+use crate::progress::spinner;
+use crate::prompts;
+
+/// Tag prefix used when the repository has no semver tags yet
+const DEFAULT_TAG_PREFIX: &str = "v";
+
+/// Smallest token limit for the release summary
+const MIN_RELEASE_TOKENS: usize = 1024;
+
+/// Semantic version with optional pre-release and build parts, for `--version`
+const SEMVER_PATTERN: &str = r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?(?:\+[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?$";
+
+/// A release version, major.minor.patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// Parse "1.2.3"; pre-release and build suffixes are not releases
+    pub fn parse(text: &str) -> Option<Version> {
+        let mut parts = text.split('.').map(|part| {
+            let digits_only = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            digits_only.then(|| part.parse::<u64>().ok()).flatten()
+        });
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+        };
+        parts.next().is_none().then_some(version)
+    }
+
+    pub fn bump(self, bump: Bump) -> Version {
+        match bump {
+            Bump::Major => Version { major: self.major + 1, minor: 0, patch: 0 },
+            Bump::Minor => Version { minor: self.minor + 1, patch: 0, ..self },
+            Bump::Patch => Version { patch: self.patch + 1, ..self },
+        }
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The newest semver tag reachable from HEAD and its version
+fn last_release() -> Result<Option<(String, Version)>> {
+    Ok(get_tags_merged("HEAD")?
+        .into_iter()
+        .filter_map(|tag| {
+            let version = Version::parse(version_from_tag(&tag))?;
+            Some((tag, version))
+        })
+        .max_by_key(|(_, version)| *version))
+}
+
+/// Version passed with `--version`, without a leading "v"
+fn explicit_version(text: &str) -> Result<String> {
+    let version = text.trim_start_matches('v');
+    if !Regex::new(SEMVER_PATTERN).unwrap().is_match(version) {
+        return Err(SageError::InvalidInput(format!(
+            "'{}' is not a semantic version; use e.g. 1.4.0 or 2.0.0-rc.1", text
+        )));
+    }
+    Ok(version.to_string())
+}
+
+/// Component the commits call for, with a description of what was counted.
+/// Merge commits are skipped.
+fn required_bump(commits: &[CommitInfo]) -> (Bump, String) {
+    let (mut breaking, mut features, mut other) = (0, 0, 0);
+    for info in commits.iter().filter(|info| !info.message.starts_with("Merge ")) {
+        match parse_conventional(&info.message) {
+            Some(commit) if commit.breaking => breaking += 1,
+            Some(commit) if commit.commit_type == "feat" => features += 1,
+            _ => other += 1,
+        }
+    }
+
+    let counts: Vec<String> = [(breaking, "breaking change"), (features, "feature"), (other, "other commit")]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}{}", count, name, if count == 1 { "" } else { "s" }))
+        .collect();
+
+    let bump = if breaking > 0 {
+        Bump::Major
+    } else if features > 0 {
+        Bump::Minor
+    } else {
+        Bump::Patch
+    };
+    (bump, counts.join(", "))
+}
+
+/// Value of `key` in the `[package]` (or `[workspace.package]`) table of a
+/// Cargo.toml, with the line it is on
+fn cargo_package_field<'a>(manifest: &'a str, key: &str) -> Option<(usize, &'a str)> {
+    let field_re = Regex::new(&format!(r#"^\s*{}\s*=\s*"([^"]*)""#, regex::escape(key))).unwrap();
+    let mut in_package = false;
+    for (index, line) in manifest.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed == "[package]" || trimmed == "[workspace.package]";
+        } else if in_package {
+            if let Some(captures) = field_re.captures(line) {
+                return Some((index, captures.get(1)?.as_str()));
+            }
+        }
+    }
+    None
+}
+
+/// Cargo.toml with its package version set to `version`; None when it has no
+/// literal version (e.g. `version.workspace = true`)
+fn set_cargo_version(manifest: &str, version: &str) -> Option<String> {
+    let (line_index, old) = cargo_package_field(manifest, "version")?;
+    let updated: Vec<String> = manifest.split_inclusive('\n')
+        .enumerate()
+        .map(|(index, line)| if index == line_index {
+            line.replacen(&format!("\"{}\"", old), &format!("\"{}\"", version), 1)
+        } else {
+            line.to_string()
+        })
+        .collect();
+    Some(updated.concat())
+}
+
+/// package.json with its version set to `version`, keeping its formatting
+fn set_package_json_version(manifest: &str, version: &str) -> Option<String> {
+    let version_re = Regex::new(r#"(?m)^(\s*"version"\s*:\s*")[^"]*(")"#).unwrap();
+    version_re.is_match(manifest).then(|| {
+        version_re.replace(manifest, |captures: &regex::Captures| {
+            format!("{}{}{}", &captures[1], version, &captures[2])
+        }).into_owned()
+    })
+}
+
+/// Write `version` into the manifests at the repository root (and Cargo.lock's
+/// entry for the package), returning the files changed relative to the root.
+/// Changes into the root so the files can be staged by those paths.
+fn write_version(version: &str) -> Result<Vec<String>> {
+    std::env::set_current_dir(get_repo_root()?)?;
+    let read = |name: &'static str| -> Option<(&'static str, String)> {
+        fs::read_to_string(name).ok().map(|contents| (name, contents))
+    };
+    let mut changed = Vec::new();
+
+    if let Some((path, manifest)) = read("Cargo.toml") {
+        if let Some(updated) = set_cargo_version(&manifest, version) {
+            fs::write(path, updated)?;
+            changed.push(path.to_string());
+
+            let name = cargo_package_field(&manifest, "name").map(|(_, name)| name);
+            let old = cargo_package_field(&manifest, "version").map(|(_, old)| old);
+            if let (Some(name), Some(old), Some((lock_path, lock))) = (name, old, read("Cargo.lock")) {
+                let entry = |version: &str| format!("name = \"{}\"\nversion = \"{}\"\n", name, version);
+                if lock.contains(&entry(old)) {
+                    fs::write(lock_path, lock.replacen(&entry(old), &entry(version), 1))?;
+                    changed.push(lock_path.to_string());
+                }
+            }
+        }
+    }
+
+    if let Some((path, manifest)) = read("package.json") {
+        if let Some(updated) = set_package_json_version(&manifest, version) {
+            fs::write(path, updated)?;
+            changed.push(path.to_string());
+        }
+    }
+
+    if changed.is_empty() {
+        return Err(SageError::InvalidInput(
+            "No Cargo.toml or package.json with a version was found at the repository root".to_string()
+        ));
+    }
+    Ok(changed)
+}
+
+/// Ask the AI for the tag message
+async fn generate_tag_message(config: &Config, tag: &str, previous: Option<&str>, context: &str, notes: &str) -> Result<String> {
+    let config = config.with_min_tokens(MIN_RELEASE_TOKENS);
+
+    let spinner = spinner(format!("Summarizing {} using AI...", tag));
+
+    let prompt = prompts::build_release_tag_prompt(tag, previous, context, notes);
+    let result = call_ai(&config, Task::Summarize, &prompt).await;
+    spinner.finish_and_clear();
+
+    Ok(result?.message.trim().to_string())
+}
+
+fn edit_message(message: &str) -> Result<String> {
+    Ok(edit_text("sage-tag-", ".txt", message)?.trim().to_string())
+}
+
+/// Ask whether to create the tag; returns the message to use, or None when
+/// the user aborts
+fn confirm_tag(mut message: String, tag: &str, skip_confirm: bool) -> Result<Option<String>> {
+    loop {
+        if skip_confirm {
+            return Ok(Some(message));
+        }
+
+        print!("\nCreate tag {}? [Y/n/e for edit] ", tag);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        match input.trim().to_lowercase().as_str() {
+            "" | "y" => return Ok(Some(message)),
+            "e" => match edit_message(&message) {
+                Ok(edited) if !edited.is_empty() => {
+                    message = edited;
+                    println!("\n{}", message);
+                },
+                Ok(_) => eprintln!("\n{}", "The tag message is empty; keeping the previous one".red()),
+                Err(e) => eprintln!("\n{}", e.to_string().red()),
+            },
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// `sage release`: work out the next version, summarize the release and tag it
+pub async fn run_release(config: &Config, args: &ReleaseArgs, options: &CommitOptions, skip_confirm: bool) -> Result<()> {
+    let previous = last_release()?;
+    let commits = match &previous {
+        Some((tag, _)) => get_commits(&format!("{}..HEAD", tag))?,
+        None => get_history("HEAD")?,
+    };
+    let previous_tag = previous.as_ref().map(|(tag, _)| tag.as_str());
+    if commits.is_empty() {
+        return Err(SageError::InvalidInput(format!(
+            "No commits since {}; there is nothing to release", previous_tag.unwrap_or("the first commit")
+        )));
+    }
+
+    let current = previous.as_ref().map(|(_, version)| *version).unwrap_or_default();
+    let (required, counts) = required_bump(&commits);
+    let (version, reason) = match (&args.version, args.bump) {
+        (Some(version), _) => (explicit_version(version)?, "--version".to_string()),
+        (None, Some(bump)) => (current.bump(bump).to_string(), format!("{}, from --bump", bump.as_str())),
+        (None, None) => {
+            // Before 1.0.0 breaking changes only bump the minor version
+            let bump = if required == Bump::Major && current.major == 0 { Bump::Minor } else { required };
+            (current.bump(bump).to_string(), format!("{}: {}", bump.as_str(), counts))
+        },
+    };
+
+    let prefix = previous_tag
+        .map(|tag| &tag[..tag.len() - version_from_tag(tag).len()])
+        .unwrap_or(DEFAULT_TAG_PREFIX);
+    let tag = format!("{}{}", prefix, version);
+    if tag_exists(&tag)? {
+        return Err(SageError::InvalidInput(format!("Tag {} already exists; pass --version or --bump to pick another", tag)));
+    }
+    if args.write_version && !args.dry_run && has_staged_changes()? {
+        return Err(SageError::InvalidInput(
+            "There are staged changes; commit or unstage them before --write-version commits the version bump".to_string()
+        ));
+    }
+
+    println!(
+        "{} {} → {} ({})",
+        "Next version:".bold(),
+        previous_tag.unwrap_or("none"),
+        tag.green().bold(),
+        reason
+    );
+
+    let groups = build_groups(&commits, ChangelogFormat::KeepAChangelog);
+    let notes = render(ChangelogFormat::KeepAChangelog, Some(&version), &get_commit_date("HEAD")?, &groups);
+    let summary_input = if groups.is_empty() {
+        // Only docs, tests and chores: give the model the raw subjects
+        commits.iter()
+            .map(|info| format!("- {}", info.message.lines().next().unwrap_or("")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        notes.clone()
+    };
+
+    let message = generate_tag_message(config, &tag, previous_tag, args.context.as_deref().unwrap_or(""), &summary_input).await?;
+    if message.is_empty() {
+        return Err(SageError::InvalidInput("The AI returned an empty tag message".to_string()));
+    }
+    println!("\n{}", message);
+
+    if args.dry_run {
+        println!("\n{}", "Release notes:".bold());
+        print!("\n{}", notes);
+        return Ok(());
+    }
+
+    let Some(message) = confirm_tag(message, &tag, skip_confirm || args.yes)? else {
+        println!("{}", "Release aborted.".yellow());
+        return Ok(());
+    };
+
+    if args.write_version {
+        let files = write_version(&version)?;
+        stage_files(&files)?;
+        let sha = commit_changes(&format!("chore(release): {}", version), options)?;
+        println!("{}", format!("Committed the version bump ({})", sha.get(..7).unwrap_or(&sha)).green());
+    }
+
+    create_annotated_tag(&tag, &format!("{}\n", message))?;
+    println!("{}", format!("Created tag {}", tag).green());
+    let push = if args.write_version { "git push --follow-tags".to_string() } else { format!("git push origin {}", tag) };
+    println!("{}", format!("Publish it with: {}", push).dimmed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_bump() {
+        assert_eq!(Version::parse("1.2.3"), Some(Version { major: 1, minor: 2, patch: 3 }));
+        assert_eq!(Version::parse("1.2"), None);
+        assert_eq!(Version::parse("1.2.3-rc.1"), None);
+
+        assert_eq!(explicit_version("v2.0.0-rc.1").unwrap(), "2.0.0-rc.1");
+        assert_eq!(explicit_version("1.4.0+build.5").unwrap(), "1.4.0+build.5");
+        assert!(explicit_version("foo").is_err());
+        assert!(explicit_version("1.4").is_err());
+
+        let version = Version::parse("0.4.2").unwrap();
+        assert_eq!(version.bump(Bump::Major).to_string(), "1.0.0");
+        assert_eq!(version.bump(Bump::Minor).to_string(), "0.5.0");
+        assert_eq!(version.bump(Bump::Patch).to_string(), "0.4.3");
+
+        let commit = |message: &str| CommitInfo { sha: "0".repeat(40), message: message.to_string() };
+        let commits = vec![commit("fix: a"), commit("docs: b"), commit("Merge branch 'x'")];
+        assert_eq!(required_bump(&commits), (Bump::Patch, "2 other commits".to_string()));
+
+        let commits = vec![commit("feat: a"), commit("fix: b\n\nBREAKING CHANGE: c")];
+        assert_eq!(required_bump(&commits), (Bump::Major, "1 breaking change, 1 feature".to_string()));
+    }
+
+    #[test]
+    fn test_set_manifest_versions() {
+        let cargo = "[package]\nname = \"sage\"\nversion = \"0.1.0\"\n\n[dependencies]\nclap = { version = \"4.4\" }\n";
+        assert_eq!(
+            set_cargo_version(cargo, "0.2.0").unwrap(),
+            "[package]\nname = \"sage\"\nversion = \"0.2.0\"\n\n[dependencies]\nclap = { version = \"4.4\" }\n"
+        );
+        assert_eq!(set_cargo_version("[package]\nversion.workspace = true\n", "0.2.0"), None);
+
+        let package = "{\n  \"name\": \"app\",\n  \"version\": \"1.0.0\",\n  \"dependencies\": {}\n}\n";
+        assert_eq!(
+            set_package_json_version(package, "1.1.0").unwrap(),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.1.0\",\n  \"dependencies\": {}\n}\n"
+        );
+    }
+}
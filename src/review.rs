@@ -0,0 +1,375 @@
+//! AI code review (`sage review`, `sage --review`)
+//!
+//! The staged diff, or a branch's changes since it forked from its base, is
+//! sent with a review prompt. The model answers with JSON findings anchored to
+//! files and lines, which are shown in the terminal or written as JSON or
+//! SARIF for code scanning tools.
+
+use std::fs;
+use std::io::{self, Write};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::ai::call_ai_with_diff;
+use crate::cli::{ReviewArgs, ReviewFormat, Task};
+use crate::config::Config;
+use crate::error::{Result, SageError};
+use crate::git::{get_diff, get_merge_base, get_range_diff, Diff};
+use crate::progress::spinner;
+use crate::prompts;
+
+/// Smallest token limit for reviews, which return JSON findings
+const MIN_REVIEW_TOKENS: usize = 2048;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const TOOL_URI: &str = "https://github.com/thanipro/sage";
+
+/// Kind of problem, most serious first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Security,
+    Bug,
+    TestGap,
+    Style,
+}
+
+impl Severity {
+    const ALL: [Severity; 4] = [Severity::Security, Severity::Bug, Severity::TestGap, Severity::Style];
+
+    /// Read the model's label; anything unrecognized counts as style
+    fn parse(label: &str) -> Severity {
+        match label.trim().to_lowercase().replace(['_', ' '], "-").as_str() {
+            "security" | "vulnerability" => Severity::Security,
+            "bug" | "error" => Severity::Bug,
+            "test-gap" | "test" | "tests" | "missing-test" | "missing-tests" => Severity::TestGap,
+            _ => Severity::Style,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Security => "security",
+            Severity::Bug => "bug",
+            Severity::TestGap => "test-gap",
+            Severity::Style => "style",
+        }
+    }
+
+    /// SARIF result level
+    fn level(&self) -> &'static str {
+        match self {
+            Severity::Security | Severity::Bug => "error",
+            Severity::TestGap => "warning",
+            Severity::Style => "note",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Severity::Security => "Security issue",
+            Severity::Bug => "Likely bug",
+            Severity::TestGap => "Changed behavior without tests",
+            Severity::Style => "Readability or style problem",
+        }
+    }
+
+    fn colored(&self) -> colored::ColoredString {
+        let label = format!("{:<8}", self.as_str());
+        match self {
+            Severity::Security => label.red().bold(),
+            Severity::Bug => label.red(),
+            Severity::TestGap => label.yellow(),
+            Severity::Style => label.blue(),
+        }
+    }
+}
+
+/// One problem found in the changes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub file: String,
+    /// Line in the new version of the file; None for the whole file
+    pub line: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AiReview {
+    #[serde(default)]
+    findings: Vec<AiFinding>,
+}
+
+#[derive(Deserialize)]
+struct AiFinding {
+    #[serde(default)]
+    file: String,
+    #[serde(default)]
+    line: Option<u32>,
+    #[serde(default)]
+    severity: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    suggestion: Option<String>,
+}
+
+/// Turn the AI's answer into findings, most serious first. Paths written with
+/// the diff's a/ or b/ prefix are matched to the diff's files.
+fn parse_findings(response: &str, diff: &Diff) -> Result<Vec<Finding>> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => return Err(SageError::ReviewInvalid("the AI did not answer with JSON findings".to_string())),
+    };
+
+    let review: AiReview = serde_json::from_str(json)
+        .map_err(|e| SageError::ReviewInvalid(format!("could not parse the AI's findings: {}", e)))?;
+
+    let in_diff = |path: &str| diff.files.iter().any(|file| file.path() == path);
+    let mut findings: Vec<Finding> = review.findings.into_iter()
+        .filter(|finding| !finding.message.trim().is_empty())
+        .map(|finding| {
+            let file = finding.file.trim();
+            let file = match file.strip_prefix("a/").or_else(|| file.strip_prefix("b/")) {
+                Some(stripped) if !in_diff(file) && in_diff(stripped) => stripped,
+                _ => file,
+            };
+            Finding {
+                file: file.to_string(),
+                line: finding.line.filter(|line| *line > 0),
+                severity: Severity::parse(&finding.severity),
+                message: finding.message.trim().to_string(),
+                suggestion: finding.suggestion
+                    .map(|suggestion| suggestion.trim().to_string())
+                    .filter(|suggestion| !suggestion.is_empty()),
+            }
+        })
+        .collect();
+
+    findings.sort_by(|a, b| (a.severity, &a.file, a.line).cmp(&(b.severity, &b.file, b.line)));
+    Ok(findings)
+}
+
+/// Review `diff` and return its findings
+pub async fn review_diff(config: &Config, diff: &Diff, context: &str) -> Result<Vec<Finding>> {
+    let files_changed = diff.summary();
+
+    let config = config.with_min_tokens(MIN_REVIEW_TOKENS);
+
+    let count = diff.files.len();
+    let spinner = spinner(format!("Reviewing {} file{} using AI...", count, if count == 1 { "" } else { "s" }));
+
+    let result = call_ai_with_diff(&config, Task::Review, diff, |truncated_diff| {
+        prompts::build_review_prompt(context, &files_changed, truncated_diff)
+    }).await;
+
+    spinner.finish_and_clear();
+
+    parse_findings(&result?.message, diff)
+}
+
+/// "3 findings (1 bug, 2 style)"
+fn summary(findings: &[Finding]) -> String {
+    let counts: Vec<String> = Severity::ALL.iter()
+        .filter_map(|severity| {
+            let count = findings.iter().filter(|finding| finding.severity == *severity).count();
+            (count > 0).then(|| format!("{} {}", count, severity.as_str()))
+        })
+        .collect();
+    let plural = if findings.len() == 1 { "" } else { "s" };
+    format!("{} finding{} ({})", findings.len(), plural, counts.join(", "))
+}
+
+fn render_text(findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return format!("{}\n", "No problems found.".green());
+    }
+
+    let mut out = String::new();
+    for finding in findings {
+        let anchor = match finding.line {
+            Some(line) => format!("{}:{}", finding.file, line),
+            None => finding.file.clone(),
+        };
+        out.push_str(&format!("{} {}\n", finding.severity.colored(), anchor.bold()));
+        out.push_str(&format!("         {}\n", finding.message));
+        if let Some(suggestion) = &finding.suggestion {
+            out.push_str(&format!("         {} {}\n", "Suggestion:".dimmed(), suggestion));
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!("{}\n", summary(findings)));
+    out
+}
+
+fn render_json(findings: &[Finding]) -> Result<String> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(&json!({ "findings": findings }))?))
+}
+
+fn render_sarif(findings: &[Finding]) -> Result<String> {
+    let rules: Vec<_> = Severity::ALL.iter()
+        .map(|severity| json!({
+            "id": severity.as_str(),
+            "shortDescription": { "text": severity.description() },
+            "defaultConfiguration": { "level": severity.level() },
+        }))
+        .collect();
+
+    let results: Vec<_> = findings.iter()
+        .map(|finding| {
+            let text = match &finding.suggestion {
+                Some(suggestion) => format!("{}\n\nSuggestion: {}", finding.message, suggestion),
+                None => finding.message.clone(),
+            };
+            let mut location = json!({ "artifactLocation": { "uri": finding.file } });
+            if let Some(line) = finding.line {
+                location["region"] = json!({ "startLine": line });
+            }
+            json!({
+                "ruleId": finding.severity.as_str(),
+                "level": finding.severity.level(),
+                "message": { "text": text },
+                "locations": [{ "physicalLocation": location }],
+            })
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": { "name": "sage", "informationUri": TOOL_URI, "rules": rules } },
+            "results": results,
+        }],
+    });
+    Ok(format!("{}\n", serde_json::to_string_pretty(&sarif)?))
+}
+
+/// `sage --review`: review the staged changes and, when there are findings,
+/// ask whether to go on with the commit. Returns whether to commit.
+pub async fn review_before_commit(config: &Config, context: &str, skip_confirm: bool) -> Result<bool> {
+    let diff = get_diff(false)?;
+    if diff.is_empty() {
+        return Ok(true);
+    }
+
+    let findings = review_diff(config, &diff, context).await?;
+    println!("\n{}", "Review:".green().bold());
+    print!("{}", render_text(&findings));
+
+    if findings.is_empty() || skip_confirm {
+        return Ok(true);
+    }
+
+    print!("\nCommit anyway? [y/N] ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// `sage review`: review the staged changes, or the branch against `--base`
+pub async fn run_review(config: &Config, args: &ReviewArgs) -> Result<()> {
+    let diff = match &args.base {
+        Some(base) => get_range_diff(&get_merge_base(base, "HEAD")?, "HEAD")?,
+        None => get_diff(false)?,
+    };
+    if diff.is_empty() {
+        return Err(match args.base {
+            Some(_) => SageError::GitNoChanges,
+            None => SageError::GitNoStagedChanges,
+        });
+    }
+
+    let findings = review_diff(config, &diff, args.context.as_deref().unwrap_or("")).await?;
+
+    let Some(path) = &args.output else {
+        print!("{}", render(&findings, args.format)?);
+        return Ok(());
+    };
+
+    // Files never get color codes; restore colors even if writing fails
+    colored::control::set_override(false);
+    let written = render(&findings, args.format).and_then(|report| Ok(fs::write(path, report)?));
+    colored::control::unset_override();
+    written?;
+
+    // Status goes to stderr so stdout stays clean for other tools
+    let status = if findings.is_empty() { "no findings".to_string() } else { summary(&findings) };
+    eprintln!("{}", format!("Review written to {} ({})", path, status).green());
+
+    Ok(())
+}
+
+fn render(findings: &[Finding], format: ReviewFormat) -> Result<String> {
+    match format {
+        ReviewFormat::Text => Ok(render_text(findings)),
+        ReviewFormat::Json => render_json(findings),
+        ReviewFormat::Sarif => render_sarif(findings),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff() -> Diff {
+        Diff::parse("diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1,2 @@\n fn a() {}\n+fn b() {}\n")
+    }
+
+    #[test]
+    fn test_parse_findings() {
+        let response = r#"```json
+{"findings": [
+  {"file": "b/src/lib.rs", "line": 2, "severity": "style", "message": "Name `b` says nothing"},
+  {"file": "src/lib.rs", "line": 0, "severity": "Security", "message": "Reads `user_input` unchecked", "suggestion": " Validate it "},
+  {"file": "src/lib.rs", "severity": "bug", "message": "  "}
+]}
+```"#;
+        let findings = parse_findings(response, &diff()).unwrap();
+        assert_eq!(findings, vec![
+            Finding {
+                file: "src/lib.rs".to_string(),
+                line: None,
+                severity: Severity::Security,
+                message: "Reads `user_input` unchecked".to_string(),
+                suggestion: Some("Validate it".to_string()),
+            },
+            Finding {
+                file: "src/lib.rs".to_string(),
+                line: Some(2),
+                severity: Severity::Style,
+                message: "Name `b` says nothing".to_string(),
+                suggestion: None,
+            },
+        ]);
+        assert_eq!(summary(&findings), "2 findings (1 security, 1 style)");
+
+        assert!(parse_findings(r#"{"findings": []}"#, &diff()).unwrap().is_empty());
+        assert!(parse_findings("Looks good to me!", &diff()).is_err());
+    }
+
+    #[test]
+    fn test_render_sarif() {
+        let findings = vec![Finding {
+            file: "src/lib.rs".to_string(),
+            line: Some(2),
+            severity: Severity::TestGap,
+            message: "`b` is untested".to_string(),
+            suggestion: None,
+        }];
+        let sarif: serde_json::Value = serde_json::from_str(&render_sarif(&findings).unwrap()).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "test-gap");
+        assert_eq!(result["level"], "warning");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startLine"], 2);
+
+        let json: serde_json::Value = serde_json::from_str(&render_json(&findings).unwrap()).unwrap();
+        assert_eq!(json["findings"][0]["severity"], "test-gap");
+    }
+}
@@ -0,0 +1,271 @@
+//! Regenerating the messages of existing commits (`sage reword`)
+//!
+//! Each commit in the range gets a new message generated from its own diff.
+//! After review, the commits and everything above them on the branch are
+//! recreated with their original trees, authors, committers and dates
+//! (re-signed where they were signed), and HEAD is moved in one step.
+
+use std::io::{self, Write};
+use colored::Colorize;
+
+use crate::ai::call_ai_validated;
+use crate::cli::{CommitStyle, Task};
+use crate::config::{edit_text, Config};
+use crate::error::{Result, SageError};
+use crate::git::{
+    get_commit_diff, get_commits, get_first_parents, get_upstream_branch, is_ancestor, read_commit,
+    update_head, write_commit, RawCommit,
+};
+use crate::lint::{load_lint_config, validate_style};
+use crate::prompts;
+
+/// Line starting each message block in the editable plan
+const BLOCK_PREFIX: &str = "=== ";
+
+/// A commit with its new message
+struct Reword {
+    sha: String,
+    old_message: String,
+    new_message: String,
+}
+
+fn short(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+fn subject(message: &str) -> &str {
+    message.lines().next().unwrap_or("")
+}
+
+/// The commits to recreate, oldest first: the oldest target and every
+/// commit above it on HEAD's first-parent history
+fn rewrite_chain(targets: &[String]) -> Result<Vec<RawCommit>> {
+    let history = get_first_parents("HEAD")?;
+
+    let mut oldest = 0;
+    for target in targets {
+        let position = history.iter().position(|sha| sha == target).ok_or_else(|| SageError::InvalidInput(
+            format!("Commit {} is not on the current branch", short(target))
+        ))?;
+        oldest = oldest.max(position);
+    }
+
+    history[..=oldest].iter()
+        .rev()
+        .map(|sha| {
+            let commit = read_commit(sha)?;
+            if commit.parents.len() > 1 {
+                return Err(SageError::InvalidInput(format!(
+                    "Cannot reword across merge commit {}; reword the commits after it instead", short(sha)
+                )));
+            }
+            Ok(commit)
+        })
+        .collect()
+}
+
+/// The plan as text for editing
+fn render_plan(rewords: &[Reword]) -> String {
+    let mut out = String::from(
+        "# Edit the new commit messages below. Each block starts with a '=== <sha>'\n\
+         # line. Leave a message empty to keep the original. Lines starting with\n\
+         # '#' are ignored.\n"
+    );
+    for reword in rewords {
+        out.push_str(&format!(
+            "\n{}{} {}\n{}\n",
+            BLOCK_PREFIX, short(&reword.sha), subject(&reword.old_message), reword.new_message
+        ));
+    }
+    out
+}
+
+/// Apply an edited plan; blocks are matched to commits by their SHA
+fn parse_plan(text: &str, rewords: &mut [Reword]) -> Result<()> {
+    let mut edited: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix(BLOCK_PREFIX) {
+            let sha = header.split_whitespace().next().unwrap_or("").to_string();
+            edited.push((sha, Vec::new()));
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some((_, lines)) = edited.last_mut() {
+            lines.push(line.trim_end());
+        }
+    }
+
+    for (sha, lines) in edited {
+        let reword = rewords.iter_mut()
+            .find(|reword| !sha.is_empty() && reword.sha.starts_with(&sha))
+            .ok_or_else(|| SageError::InvalidInput(format!("Unknown commit in edited plan: '{}'", sha)))?;
+
+        let message = lines.join("\n").trim().to_string();
+        reword.new_message = if message.is_empty() {
+            reword.old_message.clone()
+        } else {
+            message
+        };
+    }
+
+    Ok(())
+}
+
+fn print_plan(rewords: &[Reword]) {
+    println!("\n{}", format!("New messages for {} commit(s):", rewords.len()).green().bold());
+    for reword in rewords {
+        println!("\n{} {}", short(&reword.sha).yellow(), subject(&reword.old_message).dimmed());
+        for line in reword.new_message.lines() {
+            println!("  {}", line);
+        }
+    }
+}
+
+fn edit_plan(rewords: &mut [Reword]) -> Result<()> {
+    parse_plan(&edit_text("sage-reword-", "", &render_plan(rewords))?, rewords)
+}
+
+/// `sage reword <range>`: regenerate commit messages and rewrite history
+pub async fn run_reword(
+    config: &Config,
+    range: &str,
+    style: Option<CommitStyle>,
+    context: &str,
+    force: bool,
+    dry_run: bool,
+    skip_confirm: bool,
+) -> Result<()> {
+    let targets: Vec<String> = get_commits(range)?.into_iter().map(|commit| commit.sha).collect();
+    if targets.is_empty() {
+        return Err(SageError::InvalidInput(format!("No commits in range: {}", range)));
+    }
+
+    let chain = rewrite_chain(&targets)?;
+
+    if !force {
+        if let Some(upstream) = get_upstream_branch()? {
+            if is_ancestor(&chain[0].sha, &upstream)? {
+                return Err(SageError::RewritePublished { upstream });
+            }
+        }
+    }
+
+    let lint_config = load_lint_config()?;
+    let mut rewords = Vec::new();
+    for (index, commit) in chain.iter().filter(|commit| targets.contains(&commit.sha)).enumerate() {
+        println!("{}", format!(
+            "Generating message {}/{} for {}...", index + 1, targets.len(), short(&commit.sha)
+        ).blue());
+
+        let diff = get_commit_diff(&commit.sha)?;
+        let files_changed = diff.summary();
+        let context = if context.is_empty() {
+            format!("Original message: {}", commit.message.trim())
+        } else {
+            format!("{}\nOriginal message: {}", context, commit.message.trim())
+        };
+
+        let (response, _) = call_ai_validated(config, Task::Commit, &diff, |truncated_diff| {
+            prompts::build_commit_prompt(style, &context, &files_changed, truncated_diff)
+        }, |message| {
+            validate_style(message, style, &lint_config)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        }).await?;
+
+        rewords.push(Reword {
+            sha: commit.sha.clone(),
+            old_message: commit.message.clone(),
+            new_message: response.message,
+        });
+    }
+
+    loop {
+        print_plan(&rewords);
+
+        if dry_run {
+            println!("\n{}", "Dry run - history was not rewritten.".yellow());
+            return Ok(());
+        }
+        if skip_confirm {
+            break;
+        }
+
+        print!("\nRewrite {} commit(s) with these messages? [Y/n/e for edit] ", chain.len());
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        match input.trim().to_lowercase().as_str() {
+            "" | "y" => break,
+            "e" => {
+                if let Err(e) = edit_plan(&mut rewords) {
+                    eprintln!("\n{}", e.to_string().red());
+                }
+            },
+            _ => {
+                println!("{}", "Reword aborted.".yellow());
+                return Ok(());
+            },
+        }
+    }
+
+    let old_head = chain.last().map(|commit| commit.sha.clone()).unwrap_or_default();
+    let mut parent: Vec<String> = chain[0].parents.clone();
+    let mut unsigned = Vec::new();
+
+    for commit in &chain {
+        let message = rewords.iter()
+            .find(|reword| reword.sha == commit.sha)
+            .map(|reword| format!("{}\n", reword.new_message.trim_end()))
+            .unwrap_or_else(|| commit.message.clone());
+
+        let (sha, signed) = write_commit(&commit.tree, &parent, &commit.author, &commit.committer, &message, commit.signed)?;
+        if commit.signed && !signed {
+            unsigned.push(short(&commit.sha).to_string());
+        }
+        parent = vec![sha];
+    }
+
+    let new_head = parent.pop().unwrap_or_default();
+    update_head(&new_head, &old_head, "sage reword")?;
+
+    println!("\n{}", format!("Reworded {} commit(s); HEAD is now {}", rewords.len(), short(&new_head)).green());
+    if !unsigned.is_empty() {
+        println!("{}", format!("Could not re-sign {}; the rewritten commits are unsigned", unsigned.join(", ")).yellow());
+    }
+    println!("{}", format!("Undo with: git reset --soft {}", short(&old_head)).dimmed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_round_trips_through_editor_text() {
+        let mut rewords = vec![
+            Reword {
+                sha: "1111111aaaa".to_string(),
+                old_message: "wip\n".to_string(),
+                new_message: "feat: add parser".to_string(),
+            },
+            Reword {
+                sha: "2222222bbbb".to_string(),
+                old_message: "fix\n".to_string(),
+                new_message: "fix: handle empty input".to_string(),
+            },
+        ];
+
+        let text = render_plan(&rewords)
+            .replace("fix: handle empty input", "fix(parser): handle empty input\n\nExplain why.")
+            .replace("feat: add parser", "");
+        parse_plan(&text, &mut rewords).unwrap();
+
+        assert_eq!(rewords[0].new_message, "wip\n");
+        assert_eq!(rewords[1].new_message, "fix(parser): handle empty input\n\nExplain why.");
+        assert!(parse_plan("=== 3333333 other\nfeat: x\n", &mut rewords).is_err());
+    }
+}
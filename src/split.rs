@@ -0,0 +1,488 @@
+//! Splitting staged changes into several commits
+//!
+//! The staged diff is cut into numbered changes: single hunks of modified
+//! files, or whole files for additions, deletions, renames, binary and mode
+//! changes. The AI groups the changes into commits, the plan can be edited,
+//! and each group is then staged on its own with `git apply --cached` and
+//! committed. The working tree is never touched; if anything fails the
+//! branch and index are reset to where they started.
+
+use std::fs;
+use std::io::{self, Write};
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::ai::call_ai;
+use crate::cli::{CommitStyle, Task};
+use crate::config::{edit_text, Config};
+use crate::error::{Result, SageError};
+use crate::git::{
+    apply_to_index, commit_changes, get_git_path, get_head_sha, get_staged_patch, reset_to,
+    ChangeKind, CommitOptions, Diff,
+};
+use crate::prompts;
+
+/// Original staged patch, kept in the git directory while the split runs
+const BACKUP_FILE: &str = "sage-split.patch";
+/// Hunk body lines shown to the AI for each change
+const PREVIEW_LINES: usize = 30;
+/// Line starting each commit block in the editable plan
+const PLAN_SEPARATOR: &str = "=== commit";
+
+/// A numbered change that is committed as a whole
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    file: usize,
+    /// Hunk within the file; None for the whole file
+    hunk: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlannedCommit {
+    message: String,
+    /// Indices into the change list
+    changes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct AiPlan {
+    commits: Vec<AiCommit>,
+}
+
+#[derive(Deserialize)]
+struct AiCommit {
+    message: String,
+    /// 1-based change numbers
+    #[serde(default)]
+    changes: Vec<usize>,
+}
+
+/// Cut the diff into changes; only plain edits of text files are split by hunk
+fn split_changes(diff: &Diff) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for (index, file) in diff.files.iter().enumerate() {
+        let by_hunk = file.kind == ChangeKind::Modified
+            && !file.binary
+            && !file.mode_changed()
+            && file.hunks.len() > 1;
+
+        if by_hunk {
+            changes.extend((0..file.hunks.len()).map(|hunk| Change { file: index, hunk: Some(hunk) }));
+        } else {
+            changes.push(Change { file: index, hunk: None });
+        }
+    }
+
+    changes
+}
+
+/// One-line label, e.g. `src/lib.rs @@ -1,3 +1,4 @@ fn main`
+fn describe(diff: &Diff, change: &Change) -> String {
+    let file = &diff.files[change.file];
+    match change.hunk {
+        Some(hunk) => format!("{} {}", file.path(), file.hunks[hunk].header),
+        None => file.summary(),
+    }
+}
+
+/// Numbered changes with a preview of each hunk, for the prompt
+fn describe_for_prompt(diff: &Diff, changes: &[Change]) -> String {
+    let mut out = String::new();
+
+    for (number, change) in changes.iter().enumerate() {
+        out.push_str(&format!("[{}] {}\n", number + 1, describe(diff, change)));
+
+        let file = &diff.files[change.file];
+        let hunks = match change.hunk {
+            Some(hunk) => &file.hunks[hunk..=hunk],
+            None => &file.hunks[..],
+        };
+        for hunk in hunks {
+            if change.hunk.is_none() {
+                out.push_str(&hunk.header);
+                out.push('\n');
+            }
+            for line in hunk.lines.iter().take(PREVIEW_LINES) {
+                out.push_str(line);
+                out.push('\n');
+            }
+            if hunk.lines.len() > PREVIEW_LINES {
+                out.push_str(&format!("...[{} more lines]...\n", hunk.lines.len() - PREVIEW_LINES));
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Turn the AI's answer into a plan; unknown or repeated numbers are dropped
+/// and changes it left out go into the last commit
+fn parse_ai_plan(response: &str, change_count: usize) -> Result<Vec<PlannedCommit>> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => return Err(SageError::SplitPlanInvalid("the AI did not answer with a JSON plan".to_string())),
+    };
+
+    let plan: AiPlan = serde_json::from_str(json)
+        .map_err(|e| SageError::SplitPlanInvalid(format!("could not parse the AI's plan: {}", e)))?;
+
+    let mut assigned = vec![false; change_count];
+    let mut commits = Vec::new();
+
+    for commit in plan.commits {
+        let message = commit.message.trim();
+        if message.is_empty() {
+            continue;
+        }
+
+        let mut changes = Vec::new();
+        for number in commit.changes {
+            match number.checked_sub(1) {
+                Some(index) if index < change_count && !assigned[index] => {
+                    assigned[index] = true;
+                    changes.push(index);
+                },
+                _ => {},
+            }
+        }
+
+        if !changes.is_empty() {
+            commits.push(PlannedCommit { message: message.to_string(), changes });
+        }
+    }
+
+    let leftover: Vec<usize> = (0..change_count).filter(|&i| !assigned[i]).collect();
+    if !leftover.is_empty() {
+        match commits.last_mut() {
+            Some(last) => last.changes.extend(leftover),
+            None => return Err(SageError::SplitPlanInvalid("the AI's plan has no commits".to_string())),
+        }
+    }
+
+    Ok(commits)
+}
+
+/// The plan as text for editing
+fn render_plan(diff: &Diff, changes: &[Change], plan: &[PlannedCommit]) -> String {
+    let mut out = String::from(
+        "# Each commit starts with a '=== commit' line, followed by a 'changes:' line\n\
+         # listing change numbers and then the commit message. Every change must\n\
+         # belong to exactly one commit. Lines starting with '#' are ignored;\n\
+         # remove all commits to abort.\n\
+         #\n\
+         # Changes:\n"
+    );
+    for (number, change) in changes.iter().enumerate() {
+        out.push_str(&format!("#  {:>3}  {}\n", number + 1, describe(diff, change)));
+    }
+
+    for commit in plan {
+        let numbers: Vec<String> = commit.changes.iter().map(|i| (i + 1).to_string()).collect();
+        out.push_str(&format!("\n{}\nchanges: {}\n{}\n", PLAN_SEPARATOR, numbers.join(" "), commit.message));
+    }
+
+    out
+}
+
+/// Parse an edited plan; an empty plan means abort
+fn parse_plan(text: &str, change_count: usize) -> Result<Vec<PlannedCommit>> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    for line in text.lines() {
+        if line.trim_end() == PLAN_SEPARATOR {
+            blocks.push(Vec::new());
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some(block) = blocks.last_mut() {
+            block.push(line.trim_end());
+        } else if !line.trim().is_empty() {
+            return Err(SageError::SplitPlanInvalid(format!("text before the first '{}' line: '{}'", PLAN_SEPARATOR, line)));
+        }
+    }
+
+    let mut assigned = vec![false; change_count];
+    let mut commits = Vec::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let mut lines = block.iter().skip_while(|line| line.trim().is_empty());
+        let numbers = lines.next()
+            .and_then(|line| line.trim().strip_prefix("changes:"))
+            .ok_or_else(|| SageError::SplitPlanInvalid(format!("commit {} has no 'changes:' line", index + 1)))?;
+
+        let mut changes = Vec::new();
+        for number in numbers.split(|c: char| c == ',' || c.is_whitespace()).filter(|n| !n.is_empty()) {
+            let change = number.parse::<usize>().ok()
+                .and_then(|n| n.checked_sub(1))
+                .filter(|&i| i < change_count)
+                .ok_or_else(|| SageError::SplitPlanInvalid(format!("unknown change number '{}'", number)))?;
+            if std::mem::replace(&mut assigned[change], true) {
+                return Err(SageError::SplitPlanInvalid(format!("change {} is in more than one commit", change + 1)));
+            }
+            changes.push(change);
+        }
+
+        let message = lines.copied().collect::<Vec<_>>().join("\n").trim().to_string();
+        if message.is_empty() {
+            return Err(SageError::SplitPlanInvalid(format!("commit {} has no message", index + 1)));
+        }
+        if changes.is_empty() {
+            return Err(SageError::SplitPlanInvalid(format!("commit {} has no changes", index + 1)));
+        }
+
+        commits.push(PlannedCommit { message, changes });
+    }
+
+    if !commits.is_empty() {
+        if let Some(missing) = assigned.iter().position(|&a| !a) {
+            return Err(SageError::SplitPlanInvalid(format!("change {} is not in any commit", missing + 1)));
+        }
+    }
+
+    Ok(commits)
+}
+
+fn print_plan(diff: &Diff, changes: &[Change], plan: &[PlannedCommit]) {
+    println!("\n{}", format!("Proposed commits ({}):", plan.len()).green().bold());
+    for (number, commit) in plan.iter().enumerate() {
+        println!("\n{} {}", format!("{}.", number + 1).bold(), commit.message.lines().next().unwrap_or(""));
+        for line in commit.message.lines().skip(1) {
+            println!("   {}", line);
+        }
+        for &change in &commit.changes {
+            println!("   {}", format!("[{}] {}", change + 1, describe(diff, &changes[change])).dimmed());
+        }
+    }
+}
+
+/// Patch staging exactly the given changes, in diff order
+fn build_patch(diff: &Diff, changes: &[Change], selected: &[usize]) -> String {
+    let mut patch = String::new();
+
+    for (index, file) in diff.files.iter().enumerate() {
+        let chosen: Vec<&Change> = selected.iter()
+            .map(|&i| &changes[i])
+            .filter(|change| change.file == index)
+            .collect();
+
+        if chosen.is_empty() {
+            continue;
+        }
+
+        if chosen.iter().any(|change| change.hunk.is_none()) {
+            patch.push_str(&file.to_string());
+        } else {
+            let mut hunks: Vec<usize> = chosen.iter().filter_map(|change| change.hunk).collect();
+            hunks.sort_unstable();
+            patch.push_str(&file.with_hunks(&hunks).to_string());
+        }
+    }
+
+    patch
+}
+
+/// Stage and commit each group on top of `head`, returning the new SHAs
+fn commit_plan(diff: &Diff, changes: &[Change], plan: &[PlannedCommit], head: &str, options: &CommitOptions) -> Result<Vec<String>> {
+    reset_to(head)?;
+
+    plan.iter()
+        .map(|commit| {
+            apply_to_index(&build_patch(diff, changes, &commit.changes))?;
+            commit_changes(&commit.message, options)
+        })
+        .collect()
+}
+
+/// Run the plan, restoring the branch and index if any step fails
+fn apply_plan(diff: &Diff, changes: &[Change], plan: &[PlannedCommit], head: &str, options: &CommitOptions) -> Result<Vec<String>> {
+    let original = diff.to_string();
+    let backup = get_git_path(BACKUP_FILE)?;
+    fs::write(&backup, &original)?;
+
+    match commit_plan(diff, changes, plan, head, options) {
+        Ok(shas) => {
+            let _ = fs::remove_file(&backup);
+            Ok(shas)
+        },
+        Err(e) => {
+            // Drop the nested tip; the split error carries its own
+            let details = e.to_string().split("\n\nTip:").next().unwrap_or("").trim().to_string();
+
+            match reset_to(head).and_then(|_| apply_to_index(&original)) {
+                Ok(()) => {
+                    let _ = fs::remove_file(&backup);
+                    Err(SageError::SplitFailed { details })
+                },
+                Err(_) => Err(SageError::SplitRollbackFailed {
+                    details,
+                    head: head.to_string(),
+                    backup: backup.display().to_string(),
+                }),
+            }
+        },
+    }
+}
+
+/// Open the plan in the editor and parse the result
+fn edit_plan(diff: &Diff, changes: &[Change], plan: &[PlannedCommit]) -> Result<Vec<PlannedCommit>> {
+    let edited = edit_text("sage-split-", "", &render_plan(diff, changes, plan))?;
+    parse_plan(&edited, changes.len())
+}
+
+/// `sage split`: group the staged changes into commits and create them
+pub async fn run_split(
+    config: &Config,
+    style: Option<CommitStyle>,
+    context: &str,
+    options: &CommitOptions,
+    dry_run: bool,
+    skip_confirm: bool,
+) -> Result<()> {
+    let head = get_head_sha()?.ok_or_else(|| SageError::InvalidInput(
+        "sage split needs an existing commit to build on; create the first commit with 'sage'".to_string()
+    ))?;
+
+    let diff = get_staged_patch()?;
+    if diff.is_empty() {
+        return Err(SageError::GitNoStagedChanges);
+    }
+
+    let changes = split_changes(&diff);
+
+    println!("{}", format!("Grouping {} staged change(s) using AI...", changes.len()).blue());
+    let prompt = prompts::build_split_prompt(style, context, &describe_for_prompt(&diff, &changes));
+    let response = call_ai(config, Task::Commit, &prompt).await?;
+    let mut plan = parse_ai_plan(&response.message, changes.len())?;
+
+    loop {
+        print_plan(&diff, &changes, &plan);
+
+        if dry_run {
+            println!("\n{}", "Dry run - no commits were created.".yellow());
+            return Ok(());
+        }
+        if skip_confirm {
+            break;
+        }
+
+        print!("\nCreate these commits? [Y/n/e for edit] ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        match input.trim().to_lowercase().as_str() {
+            "" | "y" => break,
+            "e" => match edit_plan(&diff, &changes, &plan) {
+                Ok(edited) if edited.is_empty() => {
+                    println!("{}", "Split aborted.".yellow());
+                    return Ok(());
+                },
+                Ok(edited) => plan = edited,
+                Err(e) => eprintln!("\n{}", e.to_string().red()),
+            },
+            _ => {
+                println!("{}", "Split aborted.".yellow());
+                return Ok(());
+            },
+        }
+    }
+
+    let shas = apply_plan(&diff, &changes, &plan, &head, options)?;
+
+    println!("\n{}", format!("Created {} commit(s):", shas.len()).green());
+    for (sha, commit) in shas.iter().zip(&plan) {
+        let short_sha = sha.get(..7).unwrap_or(sha);
+        println!("  {} {}", short_sha.yellow(), commit.message.lines().next().unwrap_or(""));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
++use std::fmt;
+ mod a;
+ mod b;
+ mod c;
+@@ -20,3 +21,3 @@ fn run() {
+ let x = 1;
+-let y = 2;
++let y = 3;
+ let z = 4;
+diff --git a/README.md b/README.md
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/README.md
+@@ -0,0 +1 @@
++# Demo
+";
+
+    #[test]
+    fn test_split_changes() {
+        let diff = Diff::parse(DIFF);
+        let changes = split_changes(&diff);
+        assert_eq!(changes, vec![
+            Change { file: 0, hunk: Some(0) },
+            Change { file: 0, hunk: Some(1) },
+            Change { file: 1, hunk: None },
+        ]);
+        assert_eq!(describe(&diff, &changes[1]), "src/lib.rs @@ -20,3 +21,3 @@ fn run() {");
+    }
+
+    #[test]
+    fn test_parse_ai_plan() {
+        let response = r#"Here you go: {"commits": [
+            {"message": "feat: import fmt", "changes": [1, 9]},
+            {"message": "fix: set y to 3", "changes": [2, 1]}
+        ]}"#;
+        let plan = parse_ai_plan(response, 3).unwrap();
+        assert_eq!(plan[0].changes, vec![0]);
+        // The repeated change is dropped and the forgotten one lands in the last commit
+        assert_eq!(plan[1].changes, vec![1, 2]);
+
+        assert!(parse_ai_plan("no plan", 3).is_err());
+        assert!(parse_ai_plan(r#"{"commits": []}"#, 3).is_err());
+    }
+
+    #[test]
+    fn test_plan_round_trips_through_editor_text() {
+        let diff = Diff::parse(DIFF);
+        let changes = split_changes(&diff);
+        let plan = vec![
+            PlannedCommit { message: "docs: add readme".to_string(), changes: vec![2] },
+            PlannedCommit { message: "fix: set y\n\nBody line.".to_string(), changes: vec![0, 1] },
+        ];
+
+        let text = render_plan(&diff, &changes, &plan);
+        assert_eq!(parse_plan(&text, changes.len()).unwrap(), plan);
+
+        assert!(parse_plan("# only comments\n", 3).unwrap().is_empty());
+        assert!(parse_plan("=== commit\nchanges: 1 2\nfeat: x\n", 3).is_err());
+        assert!(parse_plan("=== commit\nchanges: 1 2 3 3\nfeat: x\n", 3).is_err());
+        assert!(parse_plan("=== commit\nchanges: 1 2 3\n", 3).is_err());
+    }
+
+    #[test]
+    fn test_build_patch_keeps_selected_hunks() {
+        let diff = Diff::parse(DIFF);
+        let changes = split_changes(&diff);
+
+        let patch = build_patch(&diff, &changes, &[1]);
+        assert!(patch.starts_with("diff --git a/src/lib.rs b/src/lib.rs\n"));
+        assert!(patch.contains("+let y = 3;"));
+        assert!(!patch.contains("+use std::fmt;"));
+        assert!(!patch.contains("README.md"));
+
+        assert_eq!(build_patch(&diff, &changes, &[2, 1, 0]), DIFF);
+    }
+}
@@ -0,0 +1,112 @@
+//! End-to-end run of the commit flow: record a response from a stub
+//! OpenAI-compatible server, then replay it offline into a fresh commit.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::Command as StdCommand;
+use std::thread;
+
+use assert_cmd::Command;
+
+const MESSAGE: &str = "feat: add greeting";
+
+/// Answer one chat completion request with `MESSAGE`, returning the server's URL
+fn serve_one_completion() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut request = vec![0; length];
+        reader.read_exact(&mut request).unwrap();
+
+        let body = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": MESSAGE}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 100, "completion_tokens": 5, "total_tokens": 105}
+        }).to_string();
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ).unwrap();
+    });
+
+    url
+}
+
+fn git(repo: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git").args(args).current_dir(repo).output().unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn write_config(home: &Path, config: serde_json::Value) {
+    fs::write(home.join(".sage-config.json"), config.to_string()).unwrap();
+}
+
+fn sage(home: &Path, repo: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("sage").unwrap();
+    cmd.current_dir(repo)
+        .env("HOME", home)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("NO_PROXY", "127.0.0.1");
+    cmd
+}
+
+#[test]
+fn test_commit_flow_records_and_replays() {
+    let home = tempfile::tempdir().unwrap();
+    let repo = tempfile::tempdir().unwrap();
+    let fixtures = home.path().join("fixtures");
+
+    git(repo.path(), &["init", "--quiet"]);
+    git(repo.path(), &["config", "user.name", "Sage Test"]);
+    git(repo.path(), &["config", "user.email", "sage@example.com"]);
+    fs::write(repo.path().join("hello.txt"), "hello\n").unwrap();
+    git(repo.path(), &["add", "hello.txt"]);
+
+    write_config(home.path(), serde_json::json!({
+        "active_provider": "openai",
+        "providers": {"openai": {"api_key": "test-key", "model": "gpt-4o", "base_url": serve_one_completion()}},
+        "max_tokens": 300,
+        "default_style": "standard"
+    }));
+    sage(home.path(), repo.path())
+        .args(["--dry-run", "--record", fixtures.to_str().unwrap()])
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&fixtures).unwrap().count(), 1);
+
+    // The stub server is gone; the same staged change must now come from the fixture
+    write_config(home.path(), serde_json::json!({
+        "active_provider": "replay",
+        "providers": {"replay": {"api_key": "", "fixtures_dir": fixtures.to_str().unwrap()}},
+        "max_tokens": 300,
+        "default_style": "standard"
+    }));
+    sage(home.path(), repo.path())
+        .arg("--yes")
+        .assert()
+        .success();
+
+    assert_eq!(git(repo.path(), &["log", "-1", "--format=%s"]), MESSAGE);
+    assert_eq!(git(repo.path(), &["status", "--porcelain"]), "");
+}